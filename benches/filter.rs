@@ -0,0 +1,96 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use sampara::{Processor, Signal};
+use sampara::wavegen::{Phase, Sine};
+
+use regulus::filter::{FilterPass, Kind};
+#[cfg(feature = "simd")]
+use regulus::filter_simd;
+use regulus::{FilteredSamples, GatedPowerBlocks, KWeightFilter};
+
+const SAMPLE_RATE: u32 = 48000;
+
+/// A 997 Hz sine wave, copied into every channel.
+fn sine<const N: usize>(num_frames: usize) -> Vec<[f64; N]> {
+    let mut signal = Phase::fixed_hz(SAMPLE_RATE as f64, [997.0; N]).gen_wave(Sine);
+
+    std::iter::from_fn(|| signal.next()).take(num_frames).collect()
+}
+
+fn filter_pass<const N: usize>(c: &mut Criterion, name: &str) {
+    let signal = sine::<N>(1_000_000);
+    let coefficients = Kind::shelving_coefficients(SAMPLE_RATE);
+
+    c.bench_function(name, |b| b.iter(|| {
+        let mut pass = FilterPass::<[f64; N], N>::new_custom(coefficients);
+        let mut last = [0.0; N];
+
+        for &frame in black_box(&signal).iter() {
+            last = pass.process(frame);
+        }
+
+        last
+    }));
+}
+
+fn filter(c: &mut Criterion) {
+    filter_pass::<1>(c, "filter_pass_mono");
+    filter_pass::<5>(c, "filter_pass_5_channel");
+
+    let signal = sine::<1>(1_000_000);
+
+    c.bench_function("filtered_samples_collect", |b| b.iter(|| {
+        FilteredSamples::new(black_box(&signal).iter().copied(), SAMPLE_RATE).unwrap()
+            .collect::<Vec<_>>()
+    }));
+
+    let minute = sine::<2>(SAMPLE_RATE as usize * 60);
+
+    c.bench_function("gated_power_blocks", |b| b.iter(|| {
+        let filtered = FilteredSamples::new(black_box(&minute).iter().copied(), SAMPLE_RATE).unwrap();
+
+        GatedPowerBlocks::new(filtered, SAMPLE_RATE).unwrap().collect::<Vec<_>>()
+    }));
+}
+
+/// Compares the ways of K-weighting a minute of mono audio at 48 kHz.
+fn k_weighting_minute(c: &mut Criterion) {
+    let minute = sine::<1>(SAMPLE_RATE as usize * 60);
+    let mut group = c.benchmark_group("k_weighting_minute");
+
+    group.bench_function("filtered_samples", |b| b.iter(|| {
+        let mut output = vec![[0.0]; minute.len()];
+
+        let filtered = FilteredSamples::new(black_box(&minute).iter().copied(), SAMPLE_RATE).unwrap();
+        for (y, frame) in output.iter_mut().zip(filtered) {
+            *y = frame;
+        }
+
+        output
+    }));
+
+    group.bench_function("apply_block", |b| b.iter(|| {
+        let mut output = vec![[0.0]; minute.len()];
+
+        let mut filter = KWeightFilter::new(SAMPLE_RATE).unwrap();
+        filter.apply_block(black_box(&minute), &mut output);
+
+        output
+    }));
+
+    #[cfg(feature = "simd")]
+    group.bench_function("simd", |b| b.iter(|| {
+        let mut output = black_box(&minute).iter().map(|&[x]| x).collect::<Vec<_>>();
+
+        let mut shelving = FilterPass::new_custom(Kind::shelving_coefficients(SAMPLE_RATE));
+        let mut highpass = FilterPass::new_custom(Kind::highpass_coefficients(SAMPLE_RATE));
+        filter_simd::process_mono(&mut shelving, &mut output);
+        filter_simd::process_mono(&mut highpass, &mut output);
+
+        output
+    }));
+
+    group.finish();
+}
+
+criterion_group!(benches, filter, k_weighting_minute);
+criterion_main!(benches);
@@ -0,0 +1,42 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use sampara::Signal;
+use sampara::wavegen::{Phase, Sine};
+
+use regulus::FilteredSamples;
+
+const SAMPLE_RATE: u32 = 48000;
+
+/// About 21 seconds of a 997 Hz sine wave.
+fn sine() -> Vec<[f64; 1]> {
+    let mut signal = Phase::fixed_hz(SAMPLE_RATE as f64, [997.0]).gen_wave(Sine);
+
+    std::iter::from_fn(|| signal.next()).take(1_000_000).collect()
+}
+
+fn filtered_samples(c: &mut Criterion) {
+    let signal = sine();
+
+    let mut group = c.benchmark_group("filtered_samples");
+
+    group.bench_function("next", |b| b.iter(|| {
+        let filtered = FilteredSamples::new(black_box(&signal).iter().copied(), SAMPLE_RATE).unwrap();
+        let mut sum = 0.0;
+
+        // A `for` loop calls `next` for each frame.
+        for [x] in filtered {
+            sum += x * x;
+        }
+
+        sum
+    }));
+
+    group.bench_function("fold", |b| b.iter(|| {
+        FilteredSamples::new(black_box(&signal).iter().copied(), SAMPLE_RATE).unwrap()
+            .fold(0.0, |sum, [x]| sum + x * x)
+    }));
+
+    group.finish();
+}
+
+criterion_group!(benches, filtered_samples);
+criterion_main!(benches);
@@ -0,0 +1,36 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use regulus::Loudness;
+
+/// An hour of momentary gate blocks, at one block every 100 ms.
+fn hour_of_blocks() -> Vec<[f64; 2]> {
+    (0..36000).map(|i| {
+        let x = (i as f64 * 0.731).sin().abs();
+        [x * x * 0.5, x * 0.25]
+    }).collect()
+}
+
+fn gated_powers(c: &mut Criterion) {
+    let blocks = hour_of_blocks();
+
+    let mut group = c.benchmark_group("gated_powers");
+
+    group.bench_function("sequential", |b| b.iter(|| {
+        let mut loudness = Loudness::new([1.0, 1.0]);
+
+        for &block in black_box(&blocks).iter() {
+            loudness.push(block);
+        }
+
+        loudness.calculate()
+    }));
+
+    group.bench_function("parallel", |b| b.iter(|| {
+        Loudness::from_gated_powers_par(black_box(&blocks), [1.0, 1.0])
+    }));
+
+    group.finish();
+}
+
+criterion_group!(benches, gated_powers);
+criterion_main!(benches);
@@ -0,0 +1,185 @@
+//! Album loudness, which ReplayGain and EBU R128 both define as the
+//! integrated loudness of all of the tracks of an album played back to back.
+
+use crate::error::Error;
+use crate::gated_loudness::{GatePassStats, Loudness, LoudnessResult, ABSOLUTE_LOUDNESS_THRESHOLD_BS1770_4};
+use crate::util::Util;
+
+/// Converts a loudness back into the weighted power it was calculated from.
+fn power(loudness: f64) -> f64 {
+    10.0f64.powf((loudness - Util::lufs(1.0)) / 10.0)
+}
+
+/// Estimates album loudness from the results of measuring each track. This is
+/// for when the tracks were measured separately, and their gate blocks are no
+/// longer available.
+///
+/// The absolute gating pass of the album is exact, since it only depends on
+/// the mean power and number of blocks that passed the absolute gate in each
+/// track. The relative gate cannot be applied again without the blocks, so the
+/// blocks that passed the relative gate of each track are used instead. This
+/// differs from the exact result when a block passes the relative gate of its
+/// own track but not that of the album, or the other way round, such as with
+/// an album that has a much quieter track. Use `AlbumLoudnessAccumulator` for
+/// an exact result.
+#[derive(Debug, Clone, Default)]
+pub struct AlbumLoudness {
+    abs_num_blocks: usize,
+    abs_power_sum: f64,
+    rel_num_blocks: usize,
+    rel_power_sum: f64,
+}
+
+impl AlbumLoudness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_track(&mut self, result: LoudnessResult) {
+        let GatePassStats { num_blocks, mean_loudness_lufs, .. } = result.absolute_pass;
+        self.abs_num_blocks += num_blocks;
+        self.abs_power_sum += num_blocks as f64 * power(mean_loudness_lufs);
+
+        let GatePassStats { num_blocks, mean_loudness_lufs, .. } = result.relative_pass;
+        self.rel_num_blocks += num_blocks;
+        self.rel_power_sum += num_blocks as f64 * power(mean_loudness_lufs);
+    }
+
+    /// Estimates the loudness of the tracks pushed so far. Returns
+    /// `Error::InsufficientLoudContent` if no blocks of any track were loud
+    /// enough to be measured.
+    pub fn finish(&self) -> Result<LoudnessResult, Error> {
+        if self.abs_num_blocks == 0 || self.rel_num_blocks == 0 {
+            return Err(Error::InsufficientLoudContent);
+        }
+
+        let abs_loudness = Util::lufs(self.abs_power_sum / self.abs_num_blocks as f64);
+        let rel_loudness = Util::lufs(self.rel_power_sum / self.rel_num_blocks as f64);
+
+        let absolute_pass = GatePassStats {
+            num_blocks: self.abs_num_blocks,
+            mean_loudness_lufs: abs_loudness,
+            threshold_lufs: ABSOLUTE_LOUDNESS_THRESHOLD_BS1770_4,
+        };
+
+        let relative_pass = GatePassStats {
+            num_blocks: self.rel_num_blocks,
+            mean_loudness_lufs: rel_loudness,
+            threshold_lufs: abs_loudness - 10.0,
+        };
+
+        Ok(LoudnessResult {
+            integrated: rel_loudness,
+            absolute_pass,
+            relative_pass,
+            max_sample_peak_dbfs: None,
+        })
+    }
+}
+
+/// Measures album loudness exactly, from the gate block powers of each track.
+/// The blocks of all tracks go through the two gating passes together, as if
+/// the tracks were one signal, while the loudness of each track is also
+/// measured on its own.
+pub struct AlbumLoudnessAccumulator<const N: usize> {
+    g_weights: [f64; N],
+    album: Loudness<[f64; N], N>,
+    num_tracks: usize,
+}
+
+impl<const N: usize> AlbumLoudnessAccumulator<N> {
+    pub fn new(g_weights: [f64; N]) -> Self {
+        Self {
+            g_weights,
+            album: Loudness::new(g_weights),
+            num_tracks: 0,
+        }
+    }
+
+    /// Adds the gate block powers of a track, such as from a
+    /// `GatedPowerBlocks`, and returns the loudness of that track alone. The
+    /// blocks count towards the album even if the track itself is too quiet to
+    /// be measured, in which case `Error::InsufficientLoudContent` is
+    /// returned.
+    pub fn push_track<I>(&mut self, gated_powers: I) -> Result<LoudnessResult, Error>
+    where
+        I: IntoIterator<Item = [f64; N]>,
+    {
+        let mut track = Loudness::new(self.g_weights);
+
+        for block_powers in gated_powers {
+            track.push(block_powers);
+            self.album.push(block_powers);
+        }
+
+        self.num_tracks += 1;
+
+        track.calculate().ok_or(Error::InsufficientLoudContent)
+    }
+
+    pub fn num_tracks(&self) -> usize {
+        self.num_tracks
+    }
+
+    /// Calculates the loudness of all of the tracks pushed so far. Returns
+    /// `Error::InsufficientLoudContent` if no blocks of any track were loud
+    /// enough to be measured.
+    pub fn finish(&self) -> Result<LoudnessResult, Error> {
+        self.album.calculate().ok_or(Error::InsufficientLoudContent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn album_loudness_accumulator() {
+        let tracks = [
+            vec![[0.5, 0.5], [0.25, 0.5], [0.125, 0.125]],
+            vec![[1e-9, 1e-9], [0.0, 0.0]],
+            vec![[0.01, 0.02], [0.5, 0.25], [0.4, 0.4], [0.3, 0.1]],
+        ];
+
+        let mut accumulator = AlbumLoudnessAccumulator::new([1.0, 1.0]);
+        assert_eq!(accumulator.finish(), Err(Error::InsufficientLoudContent));
+
+        for track in tracks.iter() {
+            let produced = accumulator.push_track(track.iter().copied());
+            assert_eq!(produced, Loudness::from_gated_powers_checked(track.iter().copied(), [1.0, 1.0]));
+        }
+
+        assert_eq!(accumulator.num_tracks(), 3);
+
+        // The album is the same as all of the tracks back to back.
+        let expected = Loudness::from_gated_powers(tracks.iter().flatten().copied(), [1.0, 1.0]);
+        assert_eq!(accumulator.finish(), Ok(expected));
+    }
+
+    #[test]
+    fn album_loudness() {
+        let tracks = [
+            vec![[0.5, 0.5], [0.25, 0.5], [0.125, 0.125]],
+            vec![[0.01, 0.02], [0.5, 0.25], [0.4, 0.4], [0.3, 0.1]],
+        ];
+
+        let mut album = AlbumLoudness::new();
+        assert_eq!(album.finish(), Err(Error::InsufficientLoudContent));
+
+        for track in tracks.iter() {
+            album.push_track(Loudness::from_gated_powers(track.iter().copied(), [1.0, 1.0]));
+        }
+
+        let expected = Loudness::from_gated_powers(tracks.iter().flatten().copied(), [1.0, 1.0]);
+        let produced = album.finish().unwrap();
+
+        // The absolute pass is exact, and these tracks are close enough in
+        // loudness that the relative pass keeps the same blocks.
+        assert_eq!(produced.absolute_pass.num_blocks, expected.absolute_pass.num_blocks);
+        assert_abs_diff_eq!(produced.absolute_gated(), expected.absolute_gated(), epsilon = 1e-9);
+        assert_eq!(produced.relative_pass.num_blocks, expected.relative_pass.num_blocks);
+        assert_abs_diff_eq!(produced.integrated, expected.integrated, epsilon = 1e-9);
+    }
+}
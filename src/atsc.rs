@@ -0,0 +1,65 @@
+//! Loudness policy of ATSC A/85, the North American recommended practice for
+//! broadcast television loudness.
+//!
+//! A/85 measures loudness with ITU-R BS.1770, so the filter, gate blocks and
+//! gate thresholds are the same as those used for EBU R128, and only the
+//! target level and its tolerance differ.
+
+use crate::gated_loudness::{AbsoluteGateKind, GateConfig, LoudnessResult};
+use crate::gating::GatingConfig;
+
+/// The ATSC A/85 loudness policy.
+pub struct AtscA85;
+
+impl AtscA85 {
+    /// The target integrated loudness, in LKFS.
+    pub const TARGET_LKFS: f64 = -24.0;
+
+    /// The tolerance around the target that is usually accepted for delivery,
+    /// in LU.
+    pub const TOLERANCE_LU: f64 = 2.0;
+
+    /// The loudness gates used by A/85, which are the BS.1770-4 gates.
+    pub fn gate_config() -> GateConfig {
+        GateConfig { absolute_gate: AbsoluteGateKind::Bs17704 }
+    }
+
+    /// The gate blocks used by A/85, which are the BS.1770-4 blocks.
+    pub const fn gating_config() -> GatingConfig {
+        GatingConfig::bs1770()
+    }
+
+    /// Checks if an integrated loudness is within `tolerance_lu` of the
+    /// -24 LKFS target, inclusive.
+    pub fn is_compliant(result: &LoudnessResult, tolerance_lu: f64) -> bool {
+        (result.integrated - Self::TARGET_LKFS).abs() <= tolerance_lu
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::gated_loudness::Loudness;
+    use crate::test_util::TestUtil;
+
+    #[test]
+    fn is_compliant() {
+        assert!(AtscA85::is_compliant(&TestUtil::loudness_result(-24.0, None), 0.0));
+        assert!(AtscA85::is_compliant(&TestUtil::loudness_result(-22.0, None), AtscA85::TOLERANCE_LU));
+        assert!(AtscA85::is_compliant(&TestUtil::loudness_result(-25.5, None), AtscA85::TOLERANCE_LU));
+        assert!(!AtscA85::is_compliant(&TestUtil::loudness_result(-26.5, None), AtscA85::TOLERANCE_LU));
+
+        // EBU R128 material at -23 LUFS is within the usual tolerance, but
+        // not a strict one.
+        assert!(AtscA85::is_compliant(&TestUtil::loudness_result(-23.0, None), AtscA85::TOLERANCE_LU));
+        assert!(!AtscA85::is_compliant(&TestUtil::loudness_result(-23.0, None), 0.5));
+
+        // The gates are the same as the default BS.1770-4 ones.
+        let mut loudness = Loudness::with_gate_config([1.0], AtscA85::gate_config());
+        loudness.push([1.0e-8]);
+        assert!(loudness.calculate().is_none());
+
+        assert_eq!(AtscA85::gating_config(), GatingConfig::default());
+    }
+}
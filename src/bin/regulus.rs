@@ -0,0 +1,181 @@
+//! Prints the integrated loudness of WAV files.
+//!
+//! Usage: `regulus [--target <LUFS>] [--format table|json] <FILE>...`
+//!
+//! With `--target`, the gain needed to bring each file to the target loudness
+//! is also printed. The exit status is non-zero if any file could not be
+//! measured, or if the arguments are invalid. `-h` or `--help` prints the
+//! usage instead.
+
+use std::process;
+
+use regulus::measurement::measure_file;
+use regulus::util::Util;
+use regulus::Error;
+
+const USAGE: &str = "usage: regulus [--target <LUFS>] [--format table|json] <FILE>...";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Format {
+    Table,
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Args {
+    target: Option<f64>,
+    format: Format,
+    paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    Measure(Args),
+    Help,
+}
+
+fn parse_args<I>(args: I) -> Result<Command, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter();
+
+    let mut target = None;
+    let mut format = Format::Table;
+    let mut paths = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--target" => {
+                let value = args.next().ok_or("--target needs a value")?;
+                let lufs = value.parse::<f64>().map_err(|_| format!("invalid target loudness: {}", value))?;
+
+                target = Some(lufs);
+            },
+            "--format" => {
+                format = match args.next().as_deref() {
+                    Some("table") => Format::Table,
+                    Some("json") => Format::Json,
+                    Some(other) => return Err(format!("unknown format: {}", other)),
+                    None => return Err("--format needs a value".into()),
+                };
+            },
+            "-h" | "--help" => return Ok(Command::Help),
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.is_empty() {
+        return Err(USAGE.into());
+    }
+
+    Ok(Command::Measure(Args { target, format, paths }))
+}
+
+fn print_table(args: &Args, results: &[(&str, Result<f64, Error>)]) {
+    let width = results.iter().map(|(path, _)| path.len()).max().unwrap_or(0).max("FILE".len());
+
+    match args.target {
+        Some(_) => println!("{:<width$}  {:>11}  {:>9}", "FILE", "INTEGRATED", "GAIN", width = width),
+        None => println!("{:<width$}  {:>11}", "FILE", "INTEGRATED", width = width),
+    }
+
+    for (path, result) in results {
+        match (result, args.target) {
+            (Ok(lufs), Some(target)) => println!(
+                "{:<width$}  {:>6.1} LUFS  {:>+6.2} dB",
+                path, lufs, Util::normalization_gain_db(*lufs, target), width = width,
+            ),
+            (Ok(lufs), None) => println!("{:<width$}  {:>6.1} LUFS", path, lufs, width = width),
+            (Err(e), _) => println!("{:<width$}  error: {}", path, e, width = width),
+        }
+    }
+}
+
+fn print_json(args: &Args, results: &[(&str, Result<f64, Error>)]) {
+    let objects = results.iter()
+        .map(|(path, result)| match (result, args.target) {
+            (Ok(lufs), Some(target)) => serde_json::json!({
+                "file": *path,
+                "integrated_lufs": *lufs,
+                "gain_db": Util::normalization_gain_db(*lufs, target),
+            }),
+            (Ok(lufs), None) => serde_json::json!({
+                "file": *path,
+                "integrated_lufs": *lufs,
+            }),
+            (Err(e), _) => serde_json::json!({
+                "file": *path,
+                "error": e.to_string(),
+            }),
+        })
+        .collect::<Vec<_>>()
+    ;
+
+    println!("{}", serde_json::json!(objects));
+}
+
+fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Command::Measure(args)) => args,
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            return;
+        },
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(2);
+        },
+    };
+
+    let results = args.paths.iter()
+        .map(|path| (path.as_str(), measure_file(path).map(|r| r.integrated)))
+        .collect::<Vec<_>>()
+    ;
+
+    match args.format {
+        Format::Table => print_table(&args, &results),
+        Format::Json => print_json(&args, &results),
+    }
+
+    if results.iter().any(|(_, result)| result.is_err()) {
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        super::parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parse_args() {
+        let expected = Args {
+            target: None,
+            format: Format::Table,
+            paths: vec!["a.wav".to_string(), "b.wav".to_string()],
+        };
+        assert_eq!(parse(&["a.wav", "b.wav"]), Ok(Command::Measure(expected)));
+
+        // Options can come before or after the paths.
+        let expected = Args {
+            target: Some(-23.0),
+            format: Format::Json,
+            paths: vec!["a.wav".to_string()],
+        };
+        assert_eq!(parse(&["--target", "-23", "a.wav", "--format", "json"]), Ok(Command::Measure(expected)));
+
+        // Help is not an error, even with other arguments.
+        assert_eq!(parse(&["-h"]), Ok(Command::Help));
+        assert_eq!(parse(&["a.wav", "--help"]), Ok(Command::Help));
+
+        assert_eq!(parse(&[]), Err(USAGE.to_string()));
+        assert_eq!(parse(&["a.wav", "--target"]), Err("--target needs a value".to_string()));
+        assert_eq!(parse(&["--target", "loud", "a.wav"]), Err("invalid target loudness: loud".to_string()));
+        assert_eq!(parse(&["--format", "xml", "a.wav"]), Err("unknown format: xml".to_string()));
+        assert_eq!(parse(&["a.wav", "--format"]), Err("--format needs a value".to_string()));
+    }
+}
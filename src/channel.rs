@@ -0,0 +1,184 @@
+//! Channel layouts and their loudness weightings, according to the BS.1770
+//! spec.
+
+/// Weighting for channels in front of and behind the listener.
+const FRONT: f64 = 1.0;
+
+/// Weighting for surround channels to the sides of the listener, between
+/// 60 and 120 degrees of azimuth (+1.5 dB).
+const SIDE: f64 = 1.41;
+
+/// Low frequency effects channels are not included in the measurement.
+const LFE: f64 = 0.0;
+
+/// Standard loudspeaker configurations from ITU-R BS.1770-4. Names follow the
+/// "front/surround" notation, with a trailing ".1" written as `One` for
+/// configurations with a low frequency effects channel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChannelLayout {
+    /// C
+    Mono,
+    /// L, R
+    Stereo,
+    /// L, R, S
+    TwoOne,
+    /// L, R, C
+    ThreeZero,
+    /// L, R, C, S
+    ThreeOne,
+    /// L, R, Ls, Rs
+    TwoTwo,
+    /// L, R, C, Ls, Rs
+    ThreeTwo,
+    /// L, R, C, LFE, Ls, Rs
+    ThreeTwoOne,
+    /// L, R, C, Ls, Rs, Lb, Cb, Rb
+    ThreeTwoThree,
+    /// L, R, Lc, Rc
+    FourZero,
+    /// L, R, C, LFE, Lss, Rss, Lrs, Rrs
+    ThreeFourOne,
+}
+
+impl ChannelLayout {
+    /// The number of channels in this layout.
+    pub const fn num_channels(&self) -> usize {
+        self.weights().len()
+    }
+
+    /// The weightings of each channel in this layout, in channel order.
+    /// Channels are weighted by their position: side surround channels are
+    /// given a weighting of 1.41, LFE channels are ignored, and all others are
+    /// given a weighting of 1.0.
+    pub const fn weights(&self) -> &'static [f64] {
+        match self {
+            Self::Mono => &[FRONT],
+            Self::Stereo => &[FRONT, FRONT],
+            Self::TwoOne => &[FRONT, FRONT, FRONT],
+            Self::ThreeZero => &[FRONT, FRONT, FRONT],
+            Self::ThreeOne => &[FRONT, FRONT, FRONT, FRONT],
+            Self::TwoTwo => &[FRONT, FRONT, SIDE, SIDE],
+            Self::ThreeTwo => &[FRONT, FRONT, FRONT, SIDE, SIDE],
+            Self::ThreeTwoOne => &[FRONT, FRONT, FRONT, LFE, SIDE, SIDE],
+            Self::ThreeTwoThree => &[FRONT, FRONT, FRONT, SIDE, SIDE, FRONT, FRONT, FRONT],
+            Self::FourZero => &[FRONT, FRONT, FRONT, FRONT],
+            Self::ThreeFourOne => &[FRONT, FRONT, FRONT, LFE, SIDE, SIDE, FRONT, FRONT],
+        }
+    }
+}
+
+/// Per-channel weightings to use when summing channel powers into a loudness.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ChannelWeights<const N: usize>([f64; N]);
+
+impl<const N: usize> ChannelWeights<N> {
+    pub const fn new(weights: [f64; N]) -> Self {
+        Self(weights)
+    }
+
+    /// Weights for a non-standard layout. Each weight applies to the channel
+    /// at the same index in the frames being measured, so the weights must be
+    /// given in the same channel order as the audio. Use 1.0 for channels in
+    /// front of or behind the listener, 1.41 for side surround channels, and
+    /// 0.0 for channels that should not count towards the loudness (such as
+    /// LFE).
+    pub const fn custom(weights: [f64; N]) -> Self {
+        Self::new(weights)
+    }
+
+    /// Returns the standard weights for a channel layout. Panics if the number
+    /// of channels in the layout is not `N`, which becomes a compile-time
+    /// error when used in a const context.
+    pub const fn from_channel_layout(layout: ChannelLayout) -> Self {
+        let layout_weights = layout.weights();
+
+        assert!(layout_weights.len() == N, "channel layout does not have N channels");
+
+        let mut weights = [0.0; N];
+        let mut i = 0;
+
+        while i < N {
+            weights[i] = layout_weights[i];
+            i += 1;
+        }
+
+        Self(weights)
+    }
+
+    pub const fn weights(&self) -> [f64; N] {
+        self.0
+    }
+}
+
+impl ChannelWeights<1> {
+    /// Weights for mono (C).
+    pub const fn mono() -> Self {
+        Self::from_channel_layout(ChannelLayout::Mono)
+    }
+}
+
+impl ChannelWeights<2> {
+    /// Weights for stereo (L, R).
+    pub const fn stereo() -> Self {
+        Self::from_channel_layout(ChannelLayout::Stereo)
+    }
+}
+
+impl ChannelWeights<5> {
+    /// Weights for 5.0 surround (L, R, C, Ls, Rs).
+    pub const fn surround_5_0() -> Self {
+        Self::from_channel_layout(ChannelLayout::ThreeTwo)
+    }
+}
+
+impl ChannelWeights<6> {
+    /// Weights for 5.1 surround (L, R, C, LFE, Ls, Rs).
+    pub const fn surround_5_1() -> Self {
+        Self::from_channel_layout(ChannelLayout::ThreeTwoOne)
+    }
+}
+
+impl ChannelWeights<8> {
+    /// Weights for the 7.1 bed of an Atmos mix (L, R, C, LFE, Lss, Rss, Lrs,
+    /// Rrs). The rear surrounds are behind the 120 degree mark, and so are
+    /// weighted the same as the front channels.
+    pub const fn atmos_7_1() -> Self {
+        Self::from_channel_layout(ChannelLayout::ThreeFourOne)
+    }
+}
+
+impl<const N: usize> From<ChannelWeights<N>> for [f64; N] {
+    fn from(channel_weights: ChannelWeights<N>) -> Self {
+        channel_weights.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_channel_layout() {
+        const SURROUND: ChannelWeights<6> = ChannelWeights::from_channel_layout(ChannelLayout::ThreeTwoOne);
+
+        assert_eq!(SURROUND.weights(), [1.0, 1.0, 1.0, 0.0, 1.41, 1.41]);
+        assert_eq!(ChannelWeights::<2>::from_channel_layout(ChannelLayout::Stereo).weights(), [1.0, 1.0]);
+        assert_eq!(ChannelLayout::ThreeTwoThree.num_channels(), 8);
+    }
+
+    #[test]
+    fn presets() {
+        assert_eq!(ChannelWeights::mono().weights(), [1.0]);
+        assert_eq!(ChannelWeights::stereo().weights(), [1.0, 1.0]);
+        assert_eq!(ChannelWeights::surround_5_0().weights(), [1.0, 1.0, 1.0, 1.41, 1.41]);
+        assert_eq!(ChannelWeights::surround_5_1().weights(), [1.0, 1.0, 1.0, 0.0, 1.41, 1.41]);
+        assert_eq!(ChannelWeights::atmos_7_1().weights(), [1.0, 1.0, 1.0, 0.0, 1.41, 1.41, 1.0, 1.0]);
+        assert_eq!(ChannelWeights::custom([0.5, 2.0, 0.0]).weights(), [0.5, 2.0, 0.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn from_channel_layout_mismatch() {
+        ChannelWeights::<2>::from_channel_layout(ChannelLayout::ThreeTwo);
+    }
+}
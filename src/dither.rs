@@ -0,0 +1,124 @@
+//! Dithering of signals prior to measurement.
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
+use sampara::{Frame, Signal};
+
+/// Adds triangular probability density function (TPDF) dither to each sample
+/// of a signal. Each channel receives independent noise.
+pub struct TpdfDither<S, const N: usize>
+where
+    S: Signal<N>,
+    S::Frame: Frame<N, Sample = f64>,
+{
+    frames: S,
+    amplitude: f64,
+    rng: SmallRng,
+}
+
+impl<S, const N: usize> TpdfDither<S, N>
+where
+    S: Signal<N>,
+    S::Frame: Frame<N, Sample = f64>,
+{
+    /// Creates a new dither adapter, with noise peaking at `amplitude`. The
+    /// seed makes the added noise reproducible.
+    pub fn new(frames: S, amplitude: f64, seed: u64) -> Self {
+        Self {
+            frames,
+            amplitude,
+            rng: SmallRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Creates a new dither adapter, with noise peaking at one LSB of a signal
+    /// quantized to `bit_depth` bits, i.e. `2^(1 - bit_depth)`.
+    pub fn for_bit_depth(frames: S, bit_depth: u32, seed: u64) -> Self {
+        let amplitude = 2.0f64.powi(1 - bit_depth as i32);
+
+        Self::new(frames, amplitude, seed)
+    }
+}
+
+impl<S, const N: usize> Signal<N> for TpdfDither<S, N>
+where
+    S: Signal<N>,
+    S::Frame: Frame<N, Sample = f64>,
+{
+    type Frame = S::Frame;
+
+    fn next(&mut self) -> Option<Self::Frame> {
+        let mut frame = self.frames.next()?;
+
+        for x in frame.channels_mut() {
+            // The sum of two uniform distributions is a triangular
+            // distribution, centered at 0.0.
+            let r1: f64 = self.rng.gen_range(-1.0..=1.0);
+            let r2: f64 = self.rng.gen_range(-1.0..=1.0);
+
+            *x += (r1 + r2) * self.amplitude / 2.0;
+        }
+
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sampara::signal;
+
+    use approx::assert_abs_diff_eq;
+
+    const NUM_FRAMES: usize = 100_000;
+
+    fn silence() -> impl Signal<2, Frame = [f64; 2]> {
+        signal::from_frames(std::iter::repeat([0.0, 0.0]).take(NUM_FRAMES))
+    }
+
+    #[test]
+    fn tpdf_dither() {
+        const AMPLITUDE: f64 = 0.5;
+
+        let mut dither = TpdfDither::new(silence(), AMPLITUDE, 1);
+
+        let mut sum = [0.0; 2];
+        let mut num_center = 0;
+        let mut num_edge = 0;
+        let mut num_frames = 0;
+
+        while let Some(frame) = dither.next() {
+            for (s, x) in sum.iter_mut().zip(frame.into_channels()) {
+                assert!(x.abs() <= AMPLITUDE);
+
+                // A triangular distribution is much more likely to produce
+                // values near its center than near its edges.
+                if x.abs() < AMPLITUDE / 4.0 { num_center += 1 }
+                else if x.abs() > AMPLITUDE * 3.0 / 4.0 { num_edge += 1 }
+
+                *s += x;
+            }
+
+            num_frames += 1;
+        }
+
+        assert_eq!(num_frames, NUM_FRAMES);
+        assert!(num_center > 4 * num_edge);
+
+        // The noise is centered at 0.0 on each channel.
+        for s in sum.iter() {
+            assert_abs_diff_eq!(s / NUM_FRAMES as f64, 0.0, epsilon = 1e-2);
+        }
+
+        // The same seed produces the same noise.
+        let mut dither_a = TpdfDither::for_bit_depth(silence(), 16, 42);
+        let mut dither_b = TpdfDither::for_bit_depth(silence(), 16, 42);
+
+        while let Some(a) = dither_a.next() {
+            let b = dither_b.next().unwrap();
+            assert_eq!(a, b);
+            assert!(a.into_channels().all(|x| x.abs() <= 2.0f64.powi(-15)));
+        }
+    }
+}
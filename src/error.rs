@@ -0,0 +1,89 @@
+use core::fmt::{Display, Formatter, Result as FmtResult};
+
+/// Errors that can occur when configuring filters and loudness calculations.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The sample rate is not supported.
+    InvalidSampleRate(u32),
+    /// The number of channels does not match what was expected.
+    InvalidChannelCount(usize),
+    /// The per-channel buffers of a planar signal have different lengths,
+    /// given as the expected length and the first length that differs.
+    MismatchedChannelLengths(usize, usize),
+    /// The filter order is not supported.
+    InvalidFilterOrder(u32),
+    /// The cutoff frequency, in Hz, is not between 0 and the Nyquist
+    /// frequency.
+    InvalidCutoff(f64),
+    /// The Q factor of a filter is not positive.
+    InvalidQFactor(f64),
+    /// The filter has poles on or outside of the unit circle.
+    UnstableFilter,
+    /// A complex zero or pole was provided without its conjugate, which would
+    /// produce complex filter coefficients.
+    UnpairedComplexRoot,
+    /// The gating block duration, in milliseconds, is too short to contain
+    /// any frames at the given sample rate.
+    InvalidBlockDuration(u32),
+    /// The overlap between gating blocks is not in the range `[0.0, 1.0)`.
+    InvalidOverlap(f64),
+    /// The numerator or denominator of a resampling ratio is zero.
+    InvalidResamplingRatio(u32, u32),
+    /// No gate blocks were loud enough to measure the loudness of, such as
+    /// when the signal is silent or too short to fill a block.
+    InsufficientLoudContent,
+    /// An I/O operation failed.
+    #[cfg(feature = "std")]
+    Io(std::io::ErrorKind),
+    /// The input could not be decoded as a WAV file.
+    #[cfg(feature = "std")]
+    InvalidWav(String),
+    /// The audio is not in a supported format, such as a WAV file that is not
+    /// PCM.
+    UnsupportedFormat,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::InvalidSampleRate(r) => write!(f, "invalid sample rate: {}", r),
+            Self::InvalidChannelCount(n) => write!(f, "invalid channel count: {}", n),
+            Self::MismatchedChannelLengths(e, a) => write!(f, "mismatched channel lengths: expected {}, got {}", e, a),
+            Self::InvalidFilterOrder(o) => write!(f, "invalid filter order: {}", o),
+            Self::InvalidCutoff(c) => write!(f, "invalid cutoff frequency: {} Hz", c),
+            Self::InvalidQFactor(q) => write!(f, "invalid Q factor: {}", q),
+            Self::UnstableFilter => write!(f, "filter poles are not inside the unit circle"),
+            Self::UnpairedComplexRoot => write!(f, "complex roots must be given as conjugate pairs"),
+            Self::InvalidBlockDuration(d) => write!(f, "invalid gating block duration: {} ms", d),
+            Self::InvalidOverlap(o) => write!(f, "invalid gating block overlap: {}", o),
+            Self::InvalidResamplingRatio(n, d) => write!(f, "invalid resampling ratio: {}/{}", n, d),
+            Self::InsufficientLoudContent => write!(f, "no gate blocks were loud enough to be measured"),
+            #[cfg(feature = "std")]
+            Self::Io(k) => write!(f, "I/O error: {:?}", k),
+            #[cfg(feature = "std")]
+            Self::InvalidWav(e) => write!(f, "invalid WAV input: {}", e),
+            Self::UnsupportedFormat => write!(f, "unsupported audio format"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err.kind())
+    }
+}
+
+#[cfg(all(feature = "std", feature = "hound"))]
+impl From<hound::Error> for Error {
+    fn from(err: hound::Error) -> Self {
+        match err {
+            hound::Error::IoError(e) => e.into(),
+            hound::Error::Unsupported => Self::UnsupportedFormat,
+            e => Self::InvalidWav(e.to_string()),
+        }
+    }
+}
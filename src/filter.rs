@@ -1,15 +1,613 @@
-use std::f64::consts::PI;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::f64::consts::{FRAC_PI_4, PI};
+use core::iter::{Chain, FusedIterator};
+use core::option;
+#[cfg(feature = "std")]
+use std::io::Read;
 
+use num_complex::{Complex, Complex64};
 use sampara::{Frame, Processor};
-use sampara::biquad::{Params, Biquad as BQ};
 
+use crate::error::Error;
+use crate::frames::{InterleavedFrames, PlanarFrames};
+#[cfg(feature = "std")]
+use crate::frames::{PcmFormat, PcmFrames};
+use crate::util::Util;
+#[cfg(not(any(feature = "std", test)))]
+use crate::float::F64Ext;
+
+// The length of the blocks checked for silence by `TakeWhileLoud`, in
+// milliseconds. This is the length of a BS.1770 gating block.
+const SILENCE_BLOCK_MS: u64 = 400;
+
+// The conventional reference frequency for comparing filter gains, in Hz.
+const REFERENCE_FREQ_HZ: f64 = 1000.0;
+
+// Imaginary parts smaller than this are treated as rounding noise when
+// expanding conjugate root pairs into real coefficients.
+const IMAG_THRESHOLD: f64 = 1.0e-9;
+
+/// Normalized biquad coefficients, with `a0` fixed at 1.0.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Coefficients {
+    pub b0: f64,
+    pub b1: f64,
+    pub b2: f64,
+    pub a1: f64,
+    pub a2: f64,
+}
+
+impl Coefficients {
+    /// Coefficients that pass the input through unchanged.
+    pub const IDENTITY: Self = Self::new(1.0, 0.0, 0.0, 0.0, 0.0);
+
+    pub const fn new(b0: f64, b1: f64, b2: f64, a1: f64, a2: f64) -> Self {
+        Self { b0, b1, b2, a1, a2 }
+    }
+
+    /// Decomposes these coefficients into zero-pole-gain form, returning the
+    /// two zeros, the two poles, and the gain (`b0 / a0`).
+    ///
+    /// If `b0` is zero, the numerator has a lower degree than the denominator,
+    /// and the missing zeros are at infinity, which is given as a real part of
+    /// positive infinity. The gain is then the first non-zero coefficient of
+    /// the numerator.
+    pub fn to_zpk(&self) -> ([Complex<f64>; 2], [Complex<f64>; 2], f64) {
+        const INFINITE_ZERO: Complex<f64> = Complex::new(f64::INFINITY, 0.0);
+
+        // The numerator is factored as `b0 * (z^2 + (b1/b0)z + (b2/b0))`, so
+        // that the zeros are the roots of the monic polynomial.
+        let (zeros, gain) =
+            if self.b0 != 0.0 { (quadratic_roots(self.b1 / self.b0, self.b2 / self.b0), self.b0) }
+            else if self.b1 != 0.0 { ([Complex::new(-self.b2 / self.b1, 0.0), INFINITE_ZERO], self.b1) }
+            else { ([INFINITE_ZERO; 2], self.b2) }
+        ;
+
+        let poles = quadratic_roots(self.a1, self.a2);
+
+        (zeros, poles, gain)
+    }
+
+    /// Constructs coefficients from zero-pole-gain form, as produced by
+    /// `to_zpk` or by SciPy's `signal.zpk2sos`. Complex zeros and poles must
+    /// be provided as conjugate pairs, and the resulting filter must be
+    /// stable. Zeros at infinity, as produced by `to_zpk`, lower the degree
+    /// of the numerator.
+    pub fn from_zpk(zeros: [Complex<f64>; 2], poles: [Complex<f64>; 2], gain: f64) -> Result<Self, Error> {
+        // Expands `gain * (z - z1)(z - z2)` and `(z - p1)(z - p2)`, leaving
+        // out the factors of any infinite zeros.
+        let (b0, b1, b2) = match zeros {
+            [z1, z2] if z1.is_finite() && z2.is_finite() => {
+                let (b1, b2) = expand_roots(zeros)?;
+                (gain, gain * b1, gain * b2)
+            },
+            [z, _] | [_, z] if z.is_finite() => {
+                if z.im.abs() > IMAG_THRESHOLD {
+                    return Err(Error::UnpairedComplexRoot);
+                }
+
+                (0.0, gain, -gain * z.re)
+            },
+            _ => (0.0, 0.0, gain),
+        };
+
+        let (a1, a2) = expand_roots(poles)?;
+
+        let coeffs = Self::new(b0, b1, b2, a1, a2);
+
+        if coeffs.is_stable() { Ok(coeffs) }
+        else { Err(Error::UnstableFilter) }
+    }
+
+    /// Constructs coefficients from the poles and zeros of an analog
+    /// second-order filter, in rad/s, using the bilinear transform. The
+    /// analog transfer function is
+    /// `gain * (s - z1)(s - z2) / ((s - p1)(s - p2))`, so both zeros must be
+    /// finite. Complex poles and zeros must be provided as conjugate pairs,
+    /// and the resulting filter must be stable.
+    ///
+    /// The transform is pre-warped at the natural frequency of the poles,
+    /// `sqrt(|p1 * p2|)`, which is the -3 dB frequency of a Butterworth
+    /// section, so that the digital filter has the same response as the
+    /// analog one there. This is how the coefficients of both passes of the
+    /// K-weighting filter are derived. Poles at or above a quarter of the
+    /// sample rate are not pre-warped, as with the Butterworth helpers.
+    pub fn from_analog_poles_zeros(poles: [Complex64; 2], zeros: [Complex64; 2], gain: f64, sample_rate: u32) -> Result<Self, Error> {
+        if sample_rate == 0 {
+            return Err(Error::InvalidSampleRate(sample_rate));
+        }
+
+        let fs2 = 2.0 * sample_rate as f64;
+        let w0 = (poles[0] * poles[1]).norm().sqrt();
+
+        // The bilinear transform substitutes `s = k * (z - 1) / (z + 1)`,
+        // where `k = 2 * fs` without pre-warping.
+        let k =
+            if w0 > 0.0 && w0 < fs2 * FRAC_PI_4 { w0 / (w0 / fs2).tan() }
+            else { fs2 }
+        ;
+
+        // Each factor `(s - r)` becomes `((k - r)z - (k + r)) / (z + 1)`, and
+        // the `(z + 1)` terms cancel out, as there are as many zeros as poles.
+        let map_root = |r: Complex64| (r + k) / (k - r);
+        let digital_gain = gain * ((k - zeros[0]) * (k - zeros[1]) / ((k - poles[0]) * (k - poles[1]))).re;
+
+        Self::from_zpk(
+            [map_root(zeros[0]), map_root(zeros[1])],
+            [map_root(poles[0]), map_root(poles[1])],
+            digital_gain,
+        )
+    }
+
+    /// Checks if both poles lie strictly inside the unit circle.
+    pub const fn is_stable(&self) -> bool {
+        // This is the stability triangle for the denominator `z^2 + a1z + a2`.
+        // The absolute values are taken by hand, as `f64::abs` is not const.
+        let a1_abs = if self.a1 < 0.0 { -self.a1 } else { self.a1 };
+        let a2_abs = if self.a2 < 0.0 { -self.a2 } else { self.a2 };
+
+        a2_abs < 1.0 && a1_abs < 1.0 + self.a2
+    }
+
+    /// Panics if either pole does not lie strictly inside the unit circle.
+    pub const fn assert_stable(&self) {
+        assert!(
+            self.is_stable(),
+            "unstable biquad coefficients: poles of z^2 + a1*z + a2 must lie strictly inside the unit circle",
+        );
+    }
+
+    /// Evaluates the transfer function `H(e^jw)` at a given frequency.
+    pub fn frequency_response(&self, freq_hz: f64, sample_rate: u32) -> Complex64 {
+        // Evaluates the transfer function on the unit circle, in terms of
+        // `z^-1`.
+        let z1 = Complex::from_polar(1.0, -2.0 * PI * freq_hz / sample_rate as f64);
+        let z2 = z1 * z1;
+
+        let num = z1 * self.b1 + z2 * self.b2 + self.b0;
+        let den = z1 * self.a1 + z2 * self.a2 + 1.0;
+
+        num / den
+    }
+
+    /// The sections of a cascade that applies this filter and then `other`.
+    /// The transfer function of the cascade is the product of the two, which
+    /// is of fourth order, and so in general cannot be reduced to a single
+    /// biquad.
+    #[cfg(feature = "alloc")]
+    pub fn cascade(&self, other: &Coefficients) -> Vec<Coefficients> {
+        [*self, *other].to_vec()
+    }
+
+    /// The linear gain of the filter at a given frequency.
+    pub fn magnitude_response(&self, freq_hz: f64, sample_rate: u32) -> f64 {
+        self.frequency_response(freq_hz, sample_rate).norm()
+    }
+
+    /// The gain of the filter at a given frequency, in dB.
+    pub fn magnitude_db(&self, freq_hz: f64, sample_rate: u32) -> f64 {
+        20.0 * self.magnitude_response(freq_hz, sample_rate).log10()
+    }
+
+    /// The gain of the filter at a given frequency, in dB. This is the same as
+    /// `magnitude_db`.
+    pub fn gain_db_at(&self, freq_hz: f64, sample_rate: u32) -> f64 {
+        self.magnitude_db(freq_hz, sample_rate)
+    }
+
+    /// The linear gain of the filter at 1 kHz, the conventional reference
+    /// frequency for comparing filters.
+    pub fn gain_at_1khz(&self, sample_rate: u32) -> f64 {
+        self.magnitude_response(REFERENCE_FREQ_HZ, sample_rate)
+    }
+
+    /// Orders two sets of coefficients by their gain at 1 kHz, such as when
+    /// ranking filter configurations during A/B testing. This is not a
+    /// `PartialOrd` implementation, since the gain depends on the sample rate.
+    pub fn cmp_gain_at_1khz(&self, other: &Self, sample_rate: u32) -> Option<Ordering> {
+        self.gain_at_1khz(sample_rate).partial_cmp(&other.gain_at_1khz(sample_rate))
+    }
+
+    /// The phase shift of the filter at a given frequency, in radians.
+    pub fn phase_radians(&self, freq_hz: f64, sample_rate: u32) -> f64 {
+        self.frequency_response(freq_hz, sample_rate).arg()
+    }
+
+    /// The group delay of the filter at a given frequency, in samples. This is
+    /// the negative derivative of the phase response with respect to angular
+    /// frequency. It is undefined at frequencies where the filter has a zero
+    /// on the unit circle, such as DC for a highpass filter.
+    pub fn group_delay(&self, freq_hz: f64, sample_rate: u32) -> f64 {
+        // For a polynomial `P(z) = sum(p[k] * z^-k)`, the group delay is
+        // `Re(sum(k * p[k] * z^-k) / P(z))`, and the delay of the denominator
+        // is subtracted from that of the numerator.
+        let z1 = Complex::from_polar(1.0, -2.0 * PI * freq_hz / sample_rate as f64);
+        let z2 = z1 * z1;
+
+        let num = z1 * self.b1 + z2 * self.b2 + self.b0;
+        let num_ramp = z1 * self.b1 + z2 * (2.0 * self.b2);
+
+        let den = z1 * self.a1 + z2 * self.a2 + 1.0;
+        let den_ramp = z1 * self.a1 + z2 * (2.0 * self.a2);
+
+        (num_ramp / num).re - (den_ramp / den).re
+    }
+}
+
+/// Maps a real pole of an analog filter at `-2 * pi * freq_hz` rad/s into the
+/// digital domain, using the bilinear transform.
+fn bilinear_pole(freq_hz: f64, sample_rate: u32) -> Complex<f64> {
+    let k = 2.0 * sample_rate as f64;
+    let w = 2.0 * PI * freq_hz;
+
+    Complex::new((k - w) / (k + w), 0.0)
+}
+
+/// Maps a pole of an analog filter, in rad/s, into the digital domain using the
+/// bilinear transform. Poles below a quarter of the sample rate are pre-warped
+/// first, so that they keep their frequency after the transform.
+#[cfg(feature = "alloc")]
+fn prewarped_bilinear_pole(pole: Complex<f64>, sample_rate: u32) -> Complex<f64> {
+    let k = 2.0 * sample_rate as f64;
+    let w = pole.norm();
+
+    let pole =
+        if w < k * FRAC_PI_4 { pole * (k * (w / k).tan() / w) }
+        else { pole }
+    ;
+
+    (pole + k) / (k - pole)
+}
+
+/// Expands `(z - r1)(z - r2)` into the monic polynomial `z^2 + pz + q`,
+/// returning `(p, q)`.
+fn expand_roots(roots: [Complex<f64>; 2]) -> Result<(f64, f64), Error> {
+    let [r1, r2] = roots;
+
+    let p = -(r1 + r2);
+    let q = r1 * r2;
+
+    if p.im.abs() > IMAG_THRESHOLD || q.im.abs() > IMAG_THRESHOLD {
+        return Err(Error::UnpairedComplexRoot);
+    }
+
+    Ok((p.re, q.re))
+}
+
+/// Finds the (possibly complex) roots of the monic polynomial `z^2 + pz + q`.
+fn quadratic_roots(p: f64, q: f64) -> [Complex<f64>; 2] {
+    let disc_sqrt = Complex::new(p * p - 4.0 * q, 0.0).sqrt();
+
+    [(-p + disc_sqrt) / 2.0, (-p - disc_sqrt) / 2.0]
+}
+
+/// Calculates the coefficients of a lowpass Butterworth filter of a given
+/// order, as a cascade of biquads. Odd orders include one first-order pass.
+#[cfg(feature = "alloc")]
+fn butterworth_lowpass(cutoff_hz: f64, sample_rate: u32, order: u32) -> Result<Vec<Coefficients>, Error> {
+    if sample_rate == 0 {
+        return Err(Error::InvalidSampleRate(sample_rate));
+    }
+
+    if !(1..=4).contains(&order) {
+        return Err(Error::InvalidFilterOrder(order));
+    }
+
+    if !(cutoff_hz > 0.0 && cutoff_hz < sample_rate as f64 / 2.0) {
+        return Err(Error::InvalidCutoff(cutoff_hz));
+    }
+
+    let k = (PI * cutoff_hz / sample_rate as f64).tan();
+    let k_sq = k * k;
+
+    let mut passes = Vec::with_capacity(((order + 1) / 2) as usize);
+
+    // Each conjugate pair of analog poles becomes one second-order pass.
+    for i in 0..(order / 2) {
+        let theta =
+            if order % 2 == 0 { PI * (2 * i + 1) as f64 / (2 * order) as f64 }
+            else { PI * (i + 1) as f64 / order as f64 }
+        ;
+        let q = 1.0 / (2.0 * theta.cos());
+        let k_by_q = k / q;
+
+        let a0 = 1.0 + k_by_q + k_sq;
+        let b0 = k_sq / a0;
+
+        passes.push(Coefficients {
+            a1: 2.0 * (k_sq - 1.0) / a0,
+            a2: (1.0 - k_by_q + k_sq) / a0,
+            b0,
+            b1: 2.0 * b0,
+            b2: b0,
+        });
+    }
+
+    // The lone real pole of an odd order filter.
+    if order % 2 == 1 {
+        let b0 = k / (k + 1.0);
+
+        passes.push(Coefficients {
+            a1: (k - 1.0) / (k + 1.0),
+            a2: 0.0,
+            b0,
+            b1: b0,
+            b2: 0.0,
+        });
+    }
+
+    Ok(passes)
+}
+
+/// A single biquad filter pass, using the transposed direct form II. Passes
+/// can be cascaded to build higher order filters.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FilterPass<F, const N: usize>
+where
+    F: Frame<N, Sample = f64>,
+{
+    coefficients: Coefficients,
+
+    // Delayed state for each channel.
+    m1: F,
+    m2: F,
+}
+
+impl<F, const N: usize> FilterPass<F, N>
+where
+    F: Frame<N, Sample = f64>,
+{
+    /// Creates a pass from arbitrary biquad coefficients, such as alternative
+    /// pre-emphasis curves to the ones used for K-weighting. In debug builds,
+    /// this panics if the coefficients are unstable.
+    pub const fn new_custom(coefficients: Coefficients) -> Self {
+        if cfg!(debug_assertions) {
+            coefficients.assert_stable();
+        }
+
+        Self {
+            coefficients,
+            m1: F::EQUILIBRIUM,
+            m2: F::EQUILIBRIUM,
+        }
+    }
+
+    /// Creates a pass from arbitrary biquad coefficients, with the delay
+    /// state of an earlier pass as taken by `state`. This allows a long signal
+    /// to be filtered in chunks, with each chunk continuing seamlessly from
+    /// where the previous one left off.
+    pub fn with_initial_conditions(coefficients: Coefficients, m1: F, m2: F) -> Self {
+        let mut pass = Self::new_custom(coefficients);
+
+        pass.m1 = m1;
+        pass.m2 = m2;
+
+        pass
+    }
+
+    /// The coefficients of this pass.
+    pub const fn coefficients(&self) -> Coefficients {
+        self.coefficients
+    }
+
+    /// The current values of the two delay state variables of each channel.
+    pub fn state(&self) -> (F, F) {
+        (self.m1, self.m2)
+    }
+
+    pub fn reset(&mut self) {
+        self.m1 = F::EQUILIBRIUM;
+        self.m2 = F::EQUILIBRIUM;
+    }
+
+    /// The group delay of this pass at a given frequency, in samples.
+    pub fn group_delay(&self, freq_hz: f64, sample_rate: u32) -> f64 {
+        self.coefficients.group_delay(freq_hz, sample_rate)
+    }
+
+    /// Filters a slice of frames into an output slice of the same length,
+    /// with the same result as processing each frame in turn. Looping over
+    /// slices avoids the overhead of iterators, and gives the compiler a
+    /// better chance to vectorize the loop.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the input and output slices have different lengths.
+    pub fn apply_block<I>(&mut self, input: &[I], output: &mut [F])
+    where
+        I: Copy + Into<F>,
+    {
+        assert_eq!(input.len(), output.len(), "input and output blocks must have the same length");
+
+        for (&x, y) in input.iter().zip(output.iter_mut()) {
+            *y = self.process(x.into());
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<F, const N: usize> FilterPass<F, N>
+where
+    F: Frame<N, Sample = f64>,
+{
+    /// The first `n_samples` frames of the step response of this pass, which
+    /// is its output for an input of all ones, starting from rest. This is
+    /// the running sum of the impulse response, and it settles at the gain
+    /// of the pass at DC, such as 0.0 for a highpass pass. The state of this
+    /// pass is not changed.
+    pub fn step_response(&self, n_samples: usize) -> Vec<F> {
+        let mut pass = Self::new_custom(self.coefficients);
+
+        let mut step = F::EQUILIBRIUM;
+        for x in step.channels_mut() {
+            *x = 1.0;
+        }
+
+        (0..n_samples).map(|_| pass.process(step)).collect()
+    }
+}
+
+impl<F, const N: usize> From<Coefficients> for FilterPass<F, N>
+where
+    F: Frame<N, Sample = f64>,
+{
+    fn from(coefficients: Coefficients) -> Self {
+        Self::new_custom(coefficients)
+    }
+}
+
+impl<F, const N: usize> Processor for FilterPass<F, N>
+where
+    F: Frame<N, Sample = f64>,
+{
+    type Input = F;
+    type Output = F;
+
+    fn process(&mut self, input: Self::Input) -> Self::Output {
+        let Coefficients { b0, b1, b2, a1, a2 } = self.coefficients;
+
+        let mut output = input;
+
+        let channels = output.channels_mut()
+            .zip(self.m1.channels_mut())
+            .zip(self.m2.channels_mut())
+        ;
+
+        for ((y, m1), m2) in channels {
+            let x = *y;
+
+            *y = b0 * x + *m1;
+            *m1 = b1 * x - a1 * *y + *m2;
+            *m2 = b2 * x - a2 * *y;
+        }
+
+        output
+    }
+}
+
+/// A single biquad filter pass, using the direct form I. This keeps the last
+/// two inputs and outputs of each channel as state, which takes twice as much
+/// state as `FilterPass`, but behaves better when the coefficients are
+/// quantized, as there is no internal state that can overflow. At `f64`
+/// precision, the output is the same as that of `FilterPass`.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FilterPassDf1<F, const N: usize>
+where
+    F: Frame<N, Sample = f64>,
+{
+    coefficients: Coefficients,
+
+    // Delayed inputs and outputs for each channel.
+    x1: F,
+    x2: F,
+    y1: F,
+    y2: F,
+}
+
+impl<F, const N: usize> FilterPassDf1<F, N>
+where
+    F: Frame<N, Sample = f64>,
+{
+    /// Creates a pass from arbitrary biquad coefficients. In debug builds,
+    /// this panics if the coefficients are unstable.
+    pub const fn new_custom(coefficients: Coefficients) -> Self {
+        if cfg!(debug_assertions) {
+            coefficients.assert_stable();
+        }
+
+        Self {
+            coefficients,
+            x1: F::EQUILIBRIUM,
+            x2: F::EQUILIBRIUM,
+            y1: F::EQUILIBRIUM,
+            y2: F::EQUILIBRIUM,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.x1 = F::EQUILIBRIUM;
+        self.x2 = F::EQUILIBRIUM;
+        self.y1 = F::EQUILIBRIUM;
+        self.y2 = F::EQUILIBRIUM;
+    }
+
+    /// The group delay of this pass at a given frequency, in samples.
+    pub fn group_delay(&self, freq_hz: f64, sample_rate: u32) -> f64 {
+        self.coefficients.group_delay(freq_hz, sample_rate)
+    }
+}
+
+impl<F, const N: usize> From<Coefficients> for FilterPassDf1<F, N>
+where
+    F: Frame<N, Sample = f64>,
+{
+    fn from(coefficients: Coefficients) -> Self {
+        Self::new_custom(coefficients)
+    }
+}
+
+impl<F, const N: usize> Processor for FilterPassDf1<F, N>
+where
+    F: Frame<N, Sample = f64>,
+{
+    type Input = F;
+    type Output = F;
+
+    fn process(&mut self, input: Self::Input) -> Self::Output {
+        let Coefficients { b0, b1, b2, a1, a2 } = self.coefficients;
+
+        let mut output = input;
+
+        let channels = output.channels_mut()
+            .zip(self.x1.channels_mut())
+            .zip(self.x2.channels_mut())
+            .zip(self.y1.channels_mut())
+            .zip(self.y2.channels_mut())
+        ;
+
+        for ((((y, x1), x2), y1), y2) in channels {
+            let x = *y;
+
+            *y = b0 * x + b1 * *x1 + b2 * *x2 - a1 * *y1 - a2 * *y2;
+
+            *x2 = *x1;
+            *x1 = x;
+            *y2 = *y1;
+            *y1 = *y;
+        }
+
+        output
+    }
+}
+
+/// The two passes of the K-weighting filter.
 #[derive(Copy, Clone, Debug)]
-enum Kind {
+pub enum Kind {
     Shelving, HighPass,
 }
 
 impl Kind {
-    fn coefficients(&self, sample_rate: u32) -> Params<f64> {
+    /// The coefficients of the shelving pass, which models the acoustic
+    /// effect of the head, for a given non-zero sample rate.
+    pub fn shelving_coefficients(sample_rate: u32) -> Coefficients {
+        Self::Shelving.coefficients(sample_rate)
+    }
+
+    /// The coefficients of the highpass pass, which models the frequency
+    /// response of human hearing (the RLB weighting curve), for a given
+    /// non-zero sample rate.
+    pub fn highpass_coefficients(sample_rate: u32) -> Coefficients {
+        Self::HighPass.coefficients(sample_rate)
+    }
+
+    fn coefficients(&self, sample_rate: u32) -> Coefficients {
         let (f0, q) =
             match self {
                 Self::Shelving => (1681.974450955533, 0.7071752369554196),
@@ -43,7 +641,78 @@ impl Kind {
             }
         ;
 
-        Params { a1, a2, b0, b1, b2, }
+        Coefficients { a1, a2, b0, b1, b2, }
+    }
+}
+
+/// Designs biquad coefficients from a corner or center frequency, a Q factor
+/// and, for shelving filters, a gain, using the bilinear transform
+/// pre-warped at that frequency. These are the same formulas as the passes of
+/// the K-weighting filter, and all of the filters other than the shelving
+/// one have unity gain in their passband.
+///
+/// Returns `Error::InvalidSampleRate` if the sample rate is 0,
+/// `Error::InvalidCutoff` if the frequency is not strictly between 0 and the
+/// Nyquist frequency, and `Error::InvalidQFactor` if the Q factor is not
+/// positive.
+pub struct FilterDesigner;
+
+impl FilterDesigner {
+    pub fn lowpass(cutoff_hz: f64, q: f64, sample_rate: u32) -> Result<Coefficients, Error> {
+        Self::design(cutoff_hz, q, sample_rate, |_, k_sq| (k_sq, 2.0 * k_sq, k_sq))
+    }
+
+    pub fn highpass(cutoff_hz: f64, q: f64, sample_rate: u32) -> Result<Coefficients, Error> {
+        Self::design(cutoff_hz, q, sample_rate, |_, _| (1.0, -2.0, 1.0))
+    }
+
+    /// A bandpass filter with a gain of 0 dB at the center frequency.
+    pub fn bandpass(center_hz: f64, q: f64, sample_rate: u32) -> Result<Coefficients, Error> {
+        Self::design(center_hz, q, sample_rate, |k_by_q, _| (k_by_q, 0.0, -k_by_q))
+    }
+
+    /// A high shelving filter, with a gain of `gain_db` above `f0_hz`, and
+    /// half of that gain at `f0_hz`.
+    pub fn shelving_high(f0_hz: f64, gain_db: f64, q: f64, sample_rate: u32) -> Result<Coefficients, Error> {
+        let vh = 10.0f64.powf(gain_db / 20.0);
+        let vb = vh.sqrt();
+
+        Self::design(f0_hz, q, sample_rate, |k_by_q, k_sq| (vh + vb * k_by_q + k_sq, 2.0 * (k_sq - vh), vh - vb * k_by_q + k_sq))
+    }
+
+    // Builds coefficients from the numerator given by `numerator`, which is
+    // passed `k / q` and `k^2`, and the shared denominator of all of the
+    // filters. All of the coefficients are normalized by `a0`.
+    fn design<P>(freq_hz: f64, q: f64, sample_rate: u32, numerator: P) -> Result<Coefficients, Error>
+    where
+        P: Fn(f64, f64) -> (f64, f64, f64),
+    {
+        if sample_rate == 0 {
+            return Err(Error::InvalidSampleRate(sample_rate));
+        }
+
+        if !(freq_hz > 0.0 && freq_hz < sample_rate as f64 / 2.0) {
+            return Err(Error::InvalidCutoff(freq_hz));
+        }
+
+        if q.is_nan() || q <= 0.0 {
+            return Err(Error::InvalidQFactor(q));
+        }
+
+        let k = (PI * freq_hz / sample_rate as f64).tan();
+        let k_by_q = k / q;
+        let k_sq = k * k;
+
+        let a0 = 1.0 + k_by_q + k_sq;
+        let (b0, b1, b2) = numerator(k_by_q, k_sq);
+
+        Ok(Coefficients {
+            a1: 2.0 * (k_sq - 1.0) / a0,
+            a2: (1.0 - k_by_q + k_sq) / a0,
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+        })
     }
 }
 
@@ -51,59 +720,1002 @@ pub struct KWeightFilter<F, const N: usize>
 where
     F: Frame<N, Sample = f64>,
 {
-    bq_shelving: BQ<F, N>,
-    bq_highpass: BQ<F, N>,
+    sample_rate: u32,
+    shelving: Coefficients,
+    highpass: Coefficients,
+    pass_shelving: FilterPass<F, N>,
+    pass_highpass: FilterPass<F, N>,
 }
 
 impl<F, const N: usize> KWeightFilter<F, N>
 where
     F: Frame<N, Sample = f64>,
 {
-    pub fn new(sample_rate: u32) -> Self {
-        let bq_shelving = BQ::from(Kind::Shelving.coefficients(sample_rate));
-        let bq_highpass = BQ::from(Kind::HighPass.coefficients(sample_rate));
+    /// The lowest sample rate accepted by `new_at_rate`, the lowest rate
+    /// addressed by the BS.1770 spec.
+    pub const MIN_SAMPLE_RATE: u32 = 8000;
+
+    /// The highest sample rate accepted by `new_at_rate`.
+    pub const MAX_SAMPLE_RATE: u32 = 384000;
+
+    /// Creates a K-weighting filter for a given sample rate. The BS.1770 spec
+    /// addresses sample rates from 8 kHz up to at least 192 kHz. A sample
+    /// rate of 0 is an error.
+    ///
+    /// At very low sample rates the bilinear transform used to design the
+    /// filter breaks down, and the response no longer matches the spec. Below
+    /// about 3.4 kHz the shelving pass is not even stable, and
+    /// `Error::UnstableFilter` is returned. Use `new_at_rate` to reject all
+    /// rates outside of the range addressed by the spec.
+    pub fn new(sample_rate: u32) -> Result<Self, Error> {
+        if sample_rate == 0 {
+            return Err(Error::InvalidSampleRate(sample_rate));
+        }
+
+        let shelving = Kind::Shelving.coefficients(sample_rate);
+        let highpass = Kind::HighPass.coefficients(sample_rate);
 
-        Self { bq_shelving, bq_highpass }
+        if !(shelving.is_stable() && highpass.is_stable()) {
+            return Err(Error::UnstableFilter);
+        }
+
+        Ok(Self::with_coefficients(sample_rate, shelving, highpass))
     }
 
-    pub fn reset(&mut self) {
-        self.bq_shelving.reset();
-        self.bq_highpass.reset();
+    /// Creates a K-weighting filter for a given sample rate, which must be
+    /// between `MIN_SAMPLE_RATE` and `MAX_SAMPLE_RATE`, inclusive.
+    pub fn new_at_rate(sample_rate: u32) -> Result<Self, Error> {
+        if !(Self::MIN_SAMPLE_RATE..=Self::MAX_SAMPLE_RATE).contains(&sample_rate) {
+            return Err(Error::InvalidSampleRate(sample_rate));
+        }
+
+        Self::new(sample_rate)
     }
 
-    pub fn process(&mut self, input: F) -> F {
-        Processor::process(self, input)
+    /// Creates a K-weighting filter for a given sample rate, with the delay
+    /// state of an earlier filter as taken by `state`.
+    pub fn with_state(sample_rate: u32, state: ((F, F), (F, F))) -> Result<Self, Error> {
+        let mut filter = Self::new(sample_rate)?;
+        let ((s1, s2), (h1, h2)) = state;
+
+        filter.pass_shelving = FilterPass::with_initial_conditions(filter.shelving, s1, s2);
+        filter.pass_highpass = FilterPass::with_initial_conditions(filter.highpass, h1, h2);
+
+        Ok(filter)
     }
-}
 
-impl<F, const N: usize> Processor for KWeightFilter<F, N>
-where
-    F: Frame<N, Sample = f64>,
-{
-    type Input = F;
-    type Output = F;
+    /// The sample rate that this filter was designed for.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
 
-    fn process(&mut self, input: Self::Input) -> Self::Output {
-        self.bq_highpass.process(self.bq_shelving.process(input))
+    /// The current delay state of the shelving and highpass passes, in that
+    /// order.
+    pub fn state(&self) -> ((F, F), (F, F)) {
+        (self.pass_shelving.state(), self.pass_highpass.state())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Creates a cascade of passes for a lowpass Butterworth filter of order
+    /// 1 through 4, with a given cutoff frequency.
+    #[cfg(feature = "alloc")]
+    pub fn new_lowpass_butterworth(cutoff_hz: f64, sample_rate: u32, order: u32) -> Result<Vec<FilterPass<F, N>>, Error> {
+        let passes = butterworth_lowpass(cutoff_hz, sample_rate, order)?;
 
-    #[test]
-    fn coefficients() {
-        // ITU BS.1770 provides coefficients for both filters at a 48KHz
-        // sampling rate:
-        //     Shelving:
-        //         a1: -1.69065929318241
-        //         a2:  0.73248077421585
-        //         b1: -2.69169618940638
-        //         b2:  1.19839281085285
-        //         b0:  1.53512485958697
-        //     Highpass:
-        //         a1: -1.99004745483398
+        Ok(passes.into_iter().map(FilterPass::from).collect())
+    }
+
+    /// Creates the anti-imaging lowpass filter to apply to a signal after it
+    /// has been upsampled by `oversample_factor` for true peak measurement,
+    /// which removes content above the Nyquist frequency of the original
+    /// sample rate.
+    #[cfg(feature = "alloc")]
+    pub fn new_for_true_peak(oversample_factor: u32, sample_rate: u32) -> Result<Vec<FilterPass<F, N>>, Error> {
+        let oversampled_rate = sample_rate.checked_mul(oversample_factor).unwrap_or(0);
+
+        Self::new_lowpass_butterworth(sample_rate as f64 / 2.0, oversampled_rate, 4)
+    }
+
+    /// Decomposes both filter passes into zero-pole-gain form. The zeros and
+    /// poles of the shelving pass come first, followed by those of the
+    /// highpass pass. The gain is the product of the gains of both passes.
+    #[cfg(feature = "alloc")]
+    pub fn to_zpk(&self) -> (Vec<Complex<f64>>, Vec<Complex<f64>>, f64) {
+        let (zeros_s, poles_s, gain_s) = self.shelving.to_zpk();
+        let (zeros_h, poles_h, gain_h) = self.highpass.to_zpk();
+
+        let zeros = zeros_s.iter().chain(zeros_h.iter()).copied().collect();
+        let poles = poles_s.iter().chain(poles_h.iter()).copied().collect();
+
+        (zeros, poles, gain_s * gain_h)
+    }
+
+    /// Evaluates the combined transfer function of both passes at a given
+    /// frequency, at the sample rate the filter was designed for.
+    pub fn frequency_response(&self, freq_hz: f64) -> Complex64 {
+        self.shelving.frequency_response(freq_hz, self.sample_rate)
+        * self.highpass.frequency_response(freq_hz, self.sample_rate)
+    }
+
+    /// The linear gain of both passes combined at a given frequency.
+    pub fn combined_frequency_response(&self, freq_hz: f64) -> f64 {
+        self.frequency_response(freq_hz).norm()
+    }
+
+    /// The combined group delay of both passes at a given frequency, in
+    /// samples.
+    pub fn group_delay(&self, freq_hz: f64) -> f64 {
+        self.pass_shelving.group_delay(freq_hz, self.sample_rate)
+        + self.pass_highpass.group_delay(freq_hz, self.sample_rate)
+    }
+
+    pub fn reset(&mut self) {
+        self.pass_shelving.reset();
+        self.pass_highpass.reset();
+    }
+
+    /// Applies the filter forwards and then backwards over an entire signal,
+    /// using a fresh filter state for each direction. This cancels out the
+    /// phase distortion of the filter, at the cost of squaring its magnitude
+    /// response and needing the full signal up front.
+    #[cfg(feature = "alloc")]
+    pub fn apply_zero_phase(&self, signal: &[F]) -> Vec<F> {
+        let mut forward = Self::with_coefficients(self.sample_rate, self.shelving, self.highpass);
+        let mut output = signal.iter().map(|&x| forward.process(x)).collect::<Vec<_>>();
+
+        output.reverse();
+
+        let mut backward = Self::with_coefficients(self.sample_rate, self.shelving, self.highpass);
+        for x in output.iter_mut() {
+            *x = backward.process(*x);
+        }
+
+        output.reverse();
+
+        output
+    }
+
+    fn with_coefficients(sample_rate: u32, shelving: Coefficients, highpass: Coefficients) -> Self {
+        let pass_shelving = FilterPass::from(shelving);
+        let pass_highpass = FilterPass::from(highpass);
+
+        Self { sample_rate, shelving, highpass, pass_shelving, pass_highpass }
+    }
+
+    pub fn process(&mut self, input: F) -> F {
+        Processor::process(self, input)
+    }
+
+    /// Filters a frame, and then scales the output by a gain factor.
+    pub fn apply_with_gain(&mut self, input: F, gain: f64) -> F {
+        Self::scale(self.process(input), gain)
+    }
+
+    /// Scales a frame by a gain factor, and then filters it.
+    pub fn apply_with_pregain(&mut self, input: F, gain: f64) -> F {
+        self.process(Self::scale(input, gain))
+    }
+
+    fn scale(mut frame: F, gain: f64) -> F {
+        for x in frame.channels_mut() {
+            *x *= gain;
+        }
+
+        frame
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl KWeightFilter<[f64; 1], 1> {
+    /// The first `n_samples` samples of the impulse response of the
+    /// K-weighting filter at a given sample rate, which is its output for a
+    /// unit impulse followed by silence. The filter is stable, so this decays
+    /// towards zero; at 48 kHz, it is below -60 dB after a few thousand
+    /// samples.
+    pub fn impulse_response(n_samples: usize, sample_rate: u32) -> Result<Vec<[f64; 1]>, Error> {
+        let mut filter = Self::new(sample_rate)?;
+
+        let impulse = (0..n_samples).map(|i| [if i == 0 { 1.0 } else { 0.0 }]);
+
+        Ok(impulse.map(|x| filter.process(x)).collect())
+    }
+}
+
+impl<F, const N: usize> Processor for KWeightFilter<F, N>
+where
+    F: Frame<N, Sample = f64>,
+{
+    type Input = F;
+    type Output = F;
+
+    fn process(&mut self, input: Self::Input) -> Self::Output {
+        self.pass_highpass.process(self.pass_shelving.process(input))
+    }
+}
+
+impl<F, const N: usize> KWeightFilter<F, N>
+where
+    F: Frame<N, Sample = f64>,
+{
+    /// Filters a slice of frames into an output slice of the same length,
+    /// with the same result as processing each frame in turn. Each frame goes
+    /// through both passes before the next one is read, so no intermediate
+    /// buffer is needed between the passes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the input and output slices have different lengths.
+    pub fn apply_block<I>(&mut self, input: &[I], output: &mut [F])
+    where
+        I: Copy + Into<F>,
+    {
+        assert_eq!(input.len(), output.len(), "input and output blocks must have the same length");
+
+        for (&x, y) in input.iter().zip(output.iter_mut()) {
+            *y = self.process(x.into());
+        }
+    }
+}
+
+/// The unfiltered frames left in a `FilteredSamples`, as returned by
+/// `into_inner` and `into_parts`: any frame held back by `skip_transient`,
+/// followed by the rest of the inner iterator.
+pub type RemainingSamples<I> = Chain<option::IntoIter<<I as Iterator>::Item>, I>;
+
+/// Iterator adapter that applies K-weighting to each frame of an iterator.
+///
+/// If the inner iterator is double-ended, frames can also be taken from the
+/// back. As the filter is causal, frames taken from the back are filtered by a
+/// separate filter, which starts fresh at the end of the stream and runs in
+/// reverse. This means that the output for a frame depends on which end it
+/// was taken from, and taking all frames from the back gives the time
+/// reversal of filtering the reversed signal, not the reversal of filtering
+/// the signal forwards.
+pub struct FilteredSamples<I, const N: usize>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    samples: I,
+    filter: KWeightFilter<I::Item, N>,
+    filter_back: KWeightFilter<I::Item, N>,
+
+    // A frame that has been taken from `samples` but not yielded yet, such as
+    // the one that `skip_transient` pre-rolls the filter with.
+    pending: Option<I::Item>,
+}
+
+impl<I, const N: usize> FilteredSamples<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    pub fn new(samples: I, sample_rate: u32) -> Result<Self, Error> {
+        Ok(Self {
+            samples,
+            filter: KWeightFilter::new(sample_rate)?,
+            filter_back: KWeightFilter::new(sample_rate)?,
+            pending: None,
+        })
+    }
+
+    /// Creates an adapter that continues filtering with an existing filter,
+    /// such as one returned by `into_parts`. Frames taken from the back are
+    /// filtered by a fresh filter with the same coefficients.
+    pub fn from_parts(samples: I, filter: KWeightFilter<I::Item, N>) -> Self {
+        let filter_back = KWeightFilter::with_coefficients(filter.sample_rate, filter.shelving, filter.highpass);
+
+        Self { samples, filter, filter_back, pending: None }
+    }
+
+    /// The sample rate that the frames are filtered at, as given when this was
+    /// created.
+    pub fn sample_rate(&self) -> u32 {
+        self.filter.sample_rate()
+    }
+
+    /// Returns the remaining unfiltered frames, discarding the filter state.
+    /// This includes the frame held back by `skip_transient`, if it has not
+    /// been yielded yet.
+    pub fn into_inner(self) -> RemainingSamples<I> {
+        self.pending.into_iter().chain(self.samples)
+    }
+
+    /// Returns the remaining unfiltered frames, along with the filter used for
+    /// frames taken from the front. The filter keeps its state, so filtering
+    /// can be resumed with `from_parts`.
+    pub fn into_parts(self) -> (RemainingSamples<I>, KWeightFilter<I::Item, N>) {
+        (self.pending.into_iter().chain(self.samples), self.filter)
+    }
+
+    /// Warms up the filter by pre-rolling it with `n_samples` copies of the
+    /// next frame, without yielding them, so that the output starts from the
+    /// filter's steady state instead of with a startup transient. Pre-rolling
+    /// with silence would have no effect, as a filter at rest stays at rest.
+    ///
+    /// The transient mostly comes from the highpass pass. At 48 kHz, it falls
+    /// below -60 dB relative to the first frame after about 1800 frames
+    /// (37.5 ms), and below -100 dB after about 2880 frames (60 ms). This
+    /// scales with the sample rate.
+    ///
+    /// The next frame is taken from the inner iterator up front, and is held
+    /// back until it is yielded as the first frame after the pre-roll.
+    pub fn skip_transient(mut self, n_samples: usize) -> Self {
+        if self.pending.is_none() {
+            self.pending = self.samples.next();
+        }
+
+        if let Some(first) = self.pending {
+            for _ in 0..n_samples {
+                self.filter.process(first);
+            }
+        }
+
+        self
+    }
+
+    /// Converts this into an adapter that yields each input frame along with
+    /// its filtered output, continuing from the current filter state.
+    pub fn with_raw(self) -> FilteredSamplesWithRaw<I, N> {
+        FilteredSamplesWithRaw {
+            samples: self.samples,
+            filter: self.filter,
+            pending: self.pending,
+        }
+    }
+
+    /// Stops iterating once `consecutive_silent_blocks` consecutive 400 ms
+    /// blocks of filtered frames all have a loudness below `threshold_lufs`,
+    /// using the channel weights `g_weights`, as for the gating blocks of
+    /// `Loudness`. This avoids filtering long trailing silence. The frames of
+    /// the silent blocks are still yielded, and only the frames after them
+    /// are skipped. If `consecutive_silent_blocks` is 0, iteration never
+    /// stops early.
+    ///
+    /// Any run of silent blocks stops iteration, not only one at the end of
+    /// the signal, so a pause in the middle of a signal that is at least
+    /// `consecutive_silent_blocks` blocks long skips everything after it.
+    ///
+    /// The shortened signal has the same integrated loudness as the whole
+    /// signal if none of the gating blocks that overlap the skipped frames are
+    /// above the absolute gate. This is usually the case for trailing
+    /// silence with a threshold at or below the -70 LUFS absolute gate, but it
+    /// is not guaranteed, since the 400 ms blocks checked here do not line up
+    /// with the overlapping gating blocks.
+    pub fn take_while_loud(self, g_weights: I::Item, threshold_lufs: f64, consecutive_silent_blocks: usize) -> TakeWhileLoud<I, N> {
+        let block_len = (Util::ms_to_samples(SILENCE_BLOCK_MS, self.sample_rate()) as usize).max(1);
+
+        TakeWhileLoud {
+            samples: self,
+            block_len,
+            g_weights,
+            threshold_lufs,
+            consecutive_silent_blocks,
+            num_silent_blocks: 0,
+            block_power_sum: Frame::EQUILIBRIUM,
+            block_pos: 0,
+            done: false,
+        }
+    }
+}
+
+impl<'a, const N: usize> FilteredSamples<InterleavedFrames<'a, N>, N> {
+    /// Applies K-weighting to an interleaved buffer of `f32` samples, with `N`
+    /// channels. Frames are read straight out of the buffer as they are
+    /// filtered, and any samples at the end that do not fill a whole frame are
+    /// ignored.
+    pub fn from_interleaved_slice(samples: &'a [f32], sample_rate: u32) -> Result<Self, Error> {
+        if N == 0 {
+            return Err(Error::InvalidChannelCount(N));
+        }
+
+        Self::new(InterleavedFrames::new(samples), sample_rate)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read, const N: usize> FilteredSamples<PcmFrames<R, N>, N> {
+    /// Applies K-weighting to raw interleaved PCM audio read from a reader,
+    /// with `N` channels, such as audio piped from `ffmpeg -f s16le`. A
+    /// `bit_depth` of 16 or 24 reads little-endian integers, and 32 reads
+    /// little-endian floats. Any other bit depth returns
+    /// `Error::UnsupportedFormat`. See `PcmFrames` for how the end of the
+    /// input and I/O errors are handled.
+    pub fn from_reader(reader: R, sample_rate: u32, bit_depth: u32) -> Result<Self, Error> {
+        if N == 0 {
+            return Err(Error::InvalidChannelCount(N));
+        }
+
+        Self::new(PcmFrames::new(reader, PcmFormat::from_bit_depth(bit_depth)?), sample_rate)
+    }
+}
+
+impl<'a, const N: usize> FilteredSamples<PlanarFrames<'a, N>, N> {
+    /// Applies K-weighting to a planar buffer of `f32` samples, with one slice
+    /// per channel. Returns `Error::MismatchedChannelLengths` if the slices do
+    /// not all have the same length.
+    pub fn from_planar_slices(channels: [&'a [f32]; N], sample_rate: u32) -> Result<Self, Error> {
+        Self::new(PlanarFrames::new(channels)?, sample_rate)
+    }
+}
+
+impl<I, const N: usize> FilteredSamples<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    /// Clears the filter state, so that the next frame is filtered as if it
+    /// were the start of a new signal. This applies to both ends.
+    pub fn reset(&mut self) {
+        self.filter.reset();
+        self.filter_back.reset();
+    }
+
+    /// Filters all of the remaining frames into a `Vec`.
+    #[cfg(feature = "alloc")]
+    pub fn collect_vec(self) -> Vec<I::Item> {
+        let mut filtered = Vec::with_capacity(self.size_hint().0);
+        filtered.extend(self);
+        filtered
+    }
+}
+
+impl<I, const N: usize> FilteredSamples<I, N>
+where
+    I: ExactSizeIterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    /// Filters all of the remaining frames into a boxed slice. As the number
+    /// of frames is known up front, exactly that many are allocated, and no
+    /// spare capacity needs to be trimmed off afterwards.
+    #[cfg(feature = "alloc")]
+    pub fn into_boxed_slice(self) -> Box<[I::Item]> {
+        let mut filtered = Vec::with_capacity(self.len());
+        filtered.extend(self);
+
+        debug_assert_eq!(filtered.len(), filtered.capacity());
+
+        filtered.into_boxed_slice()
+    }
+}
+
+/// Applies K-weighting to a whole signal, collecting the filtered frames into
+/// a `Vec`. This is shorthand for creating a `FilteredSamples` and collecting
+/// it.
+#[cfg(feature = "alloc")]
+pub fn process_signal<F, I, const N: usize>(signal: I, sample_rate: u32) -> Result<Vec<F>, Error>
+where
+    F: Frame<N, Sample = f64>,
+    I: IntoIterator<Item = F>,
+{
+    Ok(FilteredSamples::new(signal.into_iter(), sample_rate)?.collect_vec())
+}
+
+impl<I, const N: usize> Iterator for FilteredSamples<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.pending.take().or_else(|| self.samples.next())?;
+        Some(self.filter.process(frame))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        pending_size_hint(&self.pending, self.samples.size_hint())
+    }
+
+    // Drives the inner iterator's own `fold`, which avoids checking for the
+    // end of the iterator on each frame and lets the filter loop be optimized
+    // as a whole.
+    fn fold<B, G>(self, init: B, mut g: G) -> B
+    where
+        G: FnMut(B, Self::Item) -> B,
+    {
+        let mut filter = self.filter;
+
+        let init = match self.pending {
+            Some(frame) => g(init, filter.process(frame)),
+            None => init,
+        };
+
+        self.samples.fold(init, move |acc, frame| g(acc, filter.process(frame)))
+    }
+}
+
+impl<I, const N: usize> DoubleEndedIterator for FilteredSamples<I, N>
+where
+    I: DoubleEndedIterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        // A held back frame comes before all of the frames left in `samples`.
+        let frame = self.samples.next_back().or_else(|| self.pending.take())?;
+        Some(self.filter_back.process(frame))
+    }
+}
+
+impl<I, const N: usize> ExactSizeIterator for FilteredSamples<I, N>
+where
+    I: ExactSizeIterator,
+    I::Item: Frame<N, Sample = f64>,
+{}
+
+impl<I, const N: usize> FusedIterator for FilteredSamples<I, N>
+where
+    I: FusedIterator,
+    I::Item: Frame<N, Sample = f64>,
+{}
+
+// Adds a held back frame, if any, to the size hint of an inner iterator.
+fn pending_size_hint<F>(pending: &Option<F>, (lower, upper): (usize, Option<usize>)) -> (usize, Option<usize>) {
+    let extra = pending.is_some() as usize;
+
+    (lower.saturating_add(extra), upper.and_then(|u| u.checked_add(extra)))
+}
+
+/// Iterator adapter that applies K-weighting to each frame of an iterator, and
+/// yields `(raw, filtered)` pairs of each input frame and its filtered output.
+/// This is useful for inspecting the effect of the filter without running the
+/// signal through two separate iterators.
+pub struct FilteredSamplesWithRaw<I, const N: usize>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    samples: I,
+    filter: KWeightFilter<I::Item, N>,
+    pending: Option<I::Item>,
+}
+
+impl<I, const N: usize> FilteredSamplesWithRaw<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    pub fn new(samples: I, sample_rate: u32) -> Result<Self, Error> {
+        Ok(FilteredSamples::new(samples, sample_rate)?.with_raw())
+    }
+
+    /// Clears the filter state, so that the next frame is filtered as if it
+    /// were the start of a new signal.
+    pub fn reset(&mut self) {
+        self.filter.reset();
+    }
+}
+
+impl<I, const N: usize> Iterator for FilteredSamplesWithRaw<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    type Item = (I::Item, I::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.pending.take().or_else(|| self.samples.next())?;
+        Some((frame, self.filter.process(frame)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        pending_size_hint(&self.pending, self.samples.size_hint())
+    }
+}
+
+impl<I, const N: usize> ExactSizeIterator for FilteredSamplesWithRaw<I, N>
+where
+    I: ExactSizeIterator,
+    I::Item: Frame<N, Sample = f64>,
+{}
+
+impl<I, const N: usize> FusedIterator for FilteredSamplesWithRaw<I, N>
+where
+    I: FusedIterator,
+    I::Item: Frame<N, Sample = f64>,
+{}
+
+/// Iterator adapter that yields the frames of a `FilteredSamples` until a run
+/// of silent blocks is found. See `FilteredSamples::take_while_loud`.
+pub struct TakeWhileLoud<I, const N: usize>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    samples: FilteredSamples<I, N>,
+    block_len: usize,
+    g_weights: I::Item,
+    threshold_lufs: f64,
+    consecutive_silent_blocks: usize,
+    num_silent_blocks: usize,
+
+    // The sum of the squares of the frames in the current block, for each
+    // channel, and the number of frames in the block so far.
+    block_power_sum: I::Item,
+    block_pos: usize,
+    done: bool,
+}
+
+impl<I, const N: usize> Iterator for TakeWhileLoud<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let frame = self.samples.next()?;
+
+        self.block_power_sum.zip_transform(frame, |sum, x| sum + x * x);
+        self.block_pos += 1;
+
+        if self.block_pos == self.block_len {
+            let mut mean_sq = self.block_power_sum;
+            for x in mean_sq.channels_mut() {
+                *x /= self.block_len as f64;
+            }
+
+            if Util::loudness(mean_sq, self.g_weights) < self.threshold_lufs { self.num_silent_blocks += 1; }
+            else { self.num_silent_blocks = 0; }
+
+            self.block_power_sum = Frame::EQUILIBRIUM;
+            self.block_pos = 0;
+            self.done = self.consecutive_silent_blocks > 0 && self.num_silent_blocks >= self.consecutive_silent_blocks;
+        }
+
+        Some(frame)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done { (0, Some(0)) }
+        else { (0, self.samples.size_hint().1) }
+    }
+}
+
+impl<I, const N: usize> FusedIterator for TakeWhileLoud<I, N>
+where
+    I: FusedIterator,
+    I::Item: Frame<N, Sample = f64>,
+{}
+
+/// Iterator adapter with the same interface as `FilteredSamples`, but which
+/// applies Z-weighting (i.e. no frequency weighting at all). This allows code
+/// to compare weighted and unweighted measurements using the same pipeline.
+pub struct ZWeightedSamples<I, const N: usize>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    samples: I,
+    filter: FilterPass<I::Item, N>,
+}
+
+impl<I, const N: usize> ZWeightedSamples<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    pub fn new(samples: I) -> Self {
+        Self {
+            samples,
+            filter: FilterPass::new_custom(Coefficients::IDENTITY),
+        }
+    }
+
+    /// Clears the filter state. As the filter is a pass-through, this has no
+    /// effect on the output, and is only provided for parity with
+    /// `FilteredSamples`.
+    pub fn reset(&mut self) {
+        self.filter.reset();
+    }
+}
+
+impl<I, const N: usize> Iterator for ZWeightedSamples<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.samples.next()?;
+        Some(self.filter.process(frame))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.samples.size_hint()
+    }
+}
+
+impl<I, const N: usize> ExactSizeIterator for ZWeightedSamples<I, N>
+where
+    I: ExactSizeIterator,
+    I::Item: Frame<N, Sample = f64>,
+{}
+
+impl<I, const N: usize> FusedIterator for ZWeightedSamples<I, N>
+where
+    I: FusedIterator,
+    I::Item: Frame<N, Sample = f64>,
+{}
+
+/// Real poles of the analog ITU-R 468 weighting curve, in rad/s.
+#[cfg(feature = "alloc")]
+const ITU_468_REAL_POLES: [f64; 2] = [-62675.17005846771, -25903.701047816834];
+
+/// Complex poles of the analog ITU-R 468 weighting curve, in rad/s. Only one
+/// pole of each conjugate pair is listed.
+#[cfg(feature = "alloc")]
+const ITU_468_COMPLEX_POLES: [Complex<f64>; 2] = [
+    Complex::new(-23615.53521363502, 36379.90893732917),
+    Complex::new(-18743.746690721448, 62460.156452506504),
+];
+
+/// Calculates the coefficients of the three passes of the ITU-R 468 noise
+/// weighting filter for a given non-zero sample rate, normalized to have unity
+/// gain at 1 kHz.
+///
+/// The analog curve is digitized using a pre-warped bilinear transform, which
+/// follows the curve to within 0.5 dB up to 8 kHz at 44.1 kHz and 48 kHz. The
+/// response falls off faster than the curve above that, as it is forced to
+/// zero at the Nyquist frequency, so higher sample rates are needed to follow
+/// the curve all the way up to 20 kHz.
+#[cfg(feature = "alloc")]
+pub fn coefficients_468(sample_rate: u32) -> Result<Vec<Coefficients>, Error> {
+    if sample_rate == 0 {
+        return Err(Error::InvalidSampleRate(sample_rate));
+    }
+
+    let pole = |p| prewarped_bilinear_pole(p, sample_rate);
+
+    // The analog curve has a single zero at DC, which maps to `z = 1`, and
+    // five more poles than zeros, which map to zeros at `z = -1`.
+    let dc = Complex::new(1.0, 0.0);
+    let nyquist = Complex::new(-1.0, 0.0);
+
+    let [r1, r2] = ITU_468_REAL_POLES;
+    let [c1, c2] = ITU_468_COMPLEX_POLES;
+
+    let mut passes = [
+        Coefficients::from_zpk([dc, nyquist], [pole(r1.into()), pole(r2.into())], 1.0)?,
+        Coefficients::from_zpk([nyquist, nyquist], [pole(c1), pole(c1.conj())], 1.0)?,
+        Coefficients::from_zpk([nyquist, nyquist], [pole(c2), pole(c2.conj())], 1.0)?,
+    ];
+
+    let gain_1k = passes.iter().map(|c| c.magnitude_response(1000.0, sample_rate)).product::<f64>();
+
+    let first = &mut passes[0];
+    first.b0 /= gain_1k;
+    first.b1 /= gain_1k;
+    first.b2 /= gain_1k;
+
+    Ok(passes.to_vec())
+}
+
+/// Filter that applies ITU-R 468 noise weighting, as a cascade of biquad
+/// passes. This follows the same interface as `KWeightFilter`.
+#[cfg(feature = "alloc")]
+pub struct Itu468Filter<F, const N: usize>
+where
+    F: Frame<N, Sample = f64>,
+{
+    passes: Vec<FilterPass<F, N>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<F, const N: usize> Itu468Filter<F, N>
+where
+    F: Frame<N, Sample = f64>,
+{
+    pub fn new(sample_rate: u32) -> Result<Self, Error> {
+        let passes = coefficients_468(sample_rate)?;
+
+        Ok(Self {
+            passes: passes.into_iter().map(FilterPass::from).collect(),
+        })
+    }
+
+    pub fn reset(&mut self) {
+        for pass in self.passes.iter_mut() {
+            pass.reset();
+        }
+    }
+
+    pub fn process(&mut self, input: F) -> F {
+        Processor::process(self, input)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<F, const N: usize> Processor for Itu468Filter<F, N>
+where
+    F: Frame<N, Sample = f64>,
+{
+    type Input = F;
+    type Output = F;
+
+    fn process(&mut self, input: Self::Input) -> Self::Output {
+        self.passes.iter_mut().fold(input, |x, pass| pass.process(x))
+    }
+}
+
+/// Iterator adapter that applies ITU-R 468 weighting to each frame of an
+/// iterator.
+#[cfg(feature = "alloc")]
+pub struct Itu468FilteredSamples<I, const N: usize>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    samples: I,
+    filter: Itu468Filter<I::Item, N>,
+}
+
+#[cfg(feature = "alloc")]
+impl<I, const N: usize> Itu468FilteredSamples<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    pub fn new(samples: I, sample_rate: u32) -> Result<Self, Error> {
+        Ok(Self {
+            samples,
+            filter: Itu468Filter::new(sample_rate)?,
+        })
+    }
+
+    /// Clears the filter state, so that the next frame is filtered as if it
+    /// were the start of a new signal.
+    pub fn reset(&mut self) {
+        self.filter.reset();
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I, const N: usize> Iterator for Itu468FilteredSamples<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.samples.next()?;
+        Some(self.filter.process(frame))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.samples.size_hint()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I, const N: usize> ExactSizeIterator for Itu468FilteredSamples<I, N>
+where
+    I: ExactSizeIterator,
+    I::Item: Frame<N, Sample = f64>,
+{}
+
+#[cfg(feature = "alloc")]
+impl<I, const N: usize> FusedIterator for Itu468FilteredSamples<I, N>
+where
+    I: FusedIterator,
+    I::Item: Frame<N, Sample = f64>,
+{}
+
+/// Pole frequencies of the analog A-weighting curve from IEC 61672-1, in Hz.
+const A_WEIGHTING_POLES_HZ: [f64; 4] = [20.598997, 107.65265, 737.86223, 12194.217];
+
+/// Filter that applies A-weighting, as a cascade of three biquad passes. This
+/// follows the same interface as `KWeightFilter`.
+pub struct AWeightedFilter<F, const N: usize>
+where
+    F: Frame<N, Sample = f64>,
+{
+    passes: [FilterPass<F, N>; 3],
+}
+
+impl<F, const N: usize> AWeightedFilter<F, N>
+where
+    F: Frame<N, Sample = f64>,
+{
+    pub fn new(sample_rate: u32) -> Result<Self, Error> {
+        let [c0, c1, c2] = Self::coefficients(sample_rate)?;
+
+        Ok(Self {
+            passes: [c0.into(), c1.into(), c2.into()],
+        })
+    }
+
+    /// Calculates the coefficients of the three passes for a given non-zero
+    /// sample rate, using the bilinear transform of the analog A-weighting
+    /// curve. The cascade is normalized to have unity gain at 1 kHz. As the
+    /// bilinear transform compresses the frequency axis, the response rolls
+    /// off faster than the analog curve close to the Nyquist frequency.
+    pub fn coefficients(sample_rate: u32) -> Result<[Coefficients; 3], Error> {
+        if sample_rate == 0 {
+            return Err(Error::InvalidSampleRate(sample_rate));
+        }
+
+        let [f1, f2, f3, f4] = A_WEIGHTING_POLES_HZ;
+        let pole = |freq_hz| bilinear_pole(freq_hz, sample_rate);
+
+        // The analog curve has four zeros at DC, which map to `z = 1`. It also
+        // has two more poles than zeros, so the bilinear transform adds two
+        // zeros at the Nyquist frequency, which map to `z = -1`.
+        let dc = Complex::new(1.0, 0.0);
+        let nyquist = Complex::new(-1.0, 0.0);
+
+        let mut passes = [
+            Coefficients::from_zpk([dc, dc], [pole(f1), pole(f1)], 1.0)?,
+            Coefficients::from_zpk([dc, dc], [pole(f2), pole(f3)], 1.0)?,
+            Coefficients::from_zpk([nyquist, nyquist], [pole(f4), pole(f4)], 1.0)?,
+        ];
+
+        let gain_1k = passes.iter().map(|c| c.magnitude_response(1000.0, sample_rate)).product::<f64>();
+
+        let first = &mut passes[0];
+        first.b0 /= gain_1k;
+        first.b1 /= gain_1k;
+        first.b2 /= gain_1k;
+
+        Ok(passes)
+    }
+
+    pub fn reset(&mut self) {
+        for pass in self.passes.iter_mut() {
+            pass.reset();
+        }
+    }
+
+    pub fn process(&mut self, input: F) -> F {
+        Processor::process(self, input)
+    }
+}
+
+impl<F, const N: usize> Processor for AWeightedFilter<F, N>
+where
+    F: Frame<N, Sample = f64>,
+{
+    type Input = F;
+    type Output = F;
+
+    fn process(&mut self, input: Self::Input) -> Self::Output {
+        self.passes.iter_mut().fold(input, |x, pass| pass.process(x))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::f64::consts::FRAC_1_SQRT_2;
+
+    use crate::util::Util;
+
+    use approx::assert_abs_diff_eq;
+    use proptest::prelude::*;
+    use static_assertions::assert_impl_all;
+
+    // Filtering must be able to move to, or be shared with, other threads,
+    // such as those of a thread pool.
+    assert_impl_all!(FilteredSamples<std::vec::IntoIter<[f64; 2]>, 2>: Send, Sync);
+
+    #[test]
+    fn coefficients() {
+        // ITU BS.1770 provides coefficients for both filters at a 48KHz
+        // sampling rate:
+        //     Shelving:
+        //         a1: -1.69065929318241
+        //         a2:  0.73248077421585
+        //         b1: -2.69169618940638
+        //         b2:  1.19839281085285
+        //         b0:  1.53512485958697
+        //     Highpass:
+        //         a1: -1.99004745483398
         //         a2:  0.99007225036621
         //         b0:  1.0
         //         b1: -2.0
@@ -114,7 +1726,7 @@ mod tests {
         // not exact. As a result, in all of these tests the hard-coded
         // coefficients @ 48KHz do not exactly match those in ITU BS.1770, and
         // that is intentional.
-        let expected = Params {
+        let expected = Coefficients {
             a1: -1.6906592931824103,
             a2:  0.7324807742158501,
             b0:  1.5351248595869702,
@@ -123,51 +1735,1186 @@ mod tests {
         };
         let produced = Kind::Shelving.coefficients(48000);
 
-        assert_eq!(expected, produced);
+        assert_eq!(expected, produced);
+
+        let expected = Coefficients {
+            a1: -1.6636551132560204,
+            a2:  0.7125954280732254,
+            b0:  1.5308412300503478,
+            b1: -2.6509799951547297,
+            b2:  1.169079079921587,
+        };
+        let produced = Kind::Shelving.coefficients(44100);
+
+        assert_eq!(expected, produced);
+
+        let expected = Coefficients {
+            a1: -0.2933807824149212,
+            a2:  0.18687510604540827,
+            b0:  1.3216235689299776,
+            b1: -0.7262554913156911,
+            b2:  0.2981262460162007,
+        };
+        let produced = Kind::Shelving.coefficients(8000);
+
+        assert_eq!(expected, produced);
+
+        let expected = Coefficients {
+            a1: -1.9222022306074886,
+            a2:  0.9251177351168259,
+            b0:  1.572227215091279,
+            b1: -3.0472830515615508,
+            b2:  1.4779713409796094,
+        };
+        let produced = Kind::Shelving.coefficients(192000);
+
+        assert_eq!(expected, produced);
+
+        let expected = Coefficients {
+            a1: -1.9900474548339797,
+            a2:  0.9900722503662099,
+            b0:  1.0,
+            b1: -2.0,
+            b2:  1.0,
+        };
+        let produced = Kind::HighPass.coefficients(48000);
+
+        assert_eq!(expected, produced);
+    }
+
+    #[test]
+    fn to_zpk() {
+        // The highpass pass has a double zero at DC.
+        let (zeros, poles, gain) = Kind::HighPass.coefficients(48000).to_zpk();
+
+        for z in zeros.iter() {
+            assert_abs_diff_eq!(z.re, 1.0);
+            assert_abs_diff_eq!(z.im, 0.0);
+        }
+
+        // The poles are a complex conjugate pair inside the unit circle, whose
+        // product is `a2`.
+        assert_abs_diff_eq!(poles[0].im, -poles[1].im);
+        assert_abs_diff_eq!((poles[0] * poles[1]).re, 0.9900722503662099, epsilon = 1e-12);
+        assert!(poles.iter().all(|p| p.norm() < 1.0));
+
+        assert_abs_diff_eq!(gain, 1.0);
+
+        // Expanding the zeros and poles should reproduce the coefficients.
+        let coeffs = Kind::Shelving.coefficients(48000);
+        let (zeros, poles, gain) = coeffs.to_zpk();
+
+        assert_abs_diff_eq!(gain, coeffs.b0);
+        assert_abs_diff_eq!(-gain * (zeros[0] + zeros[1]).re, coeffs.b1, epsilon = 1e-12);
+        assert_abs_diff_eq!(gain * (zeros[0] * zeros[1]).re, coeffs.b2, epsilon = 1e-12);
+        assert_abs_diff_eq!(-(poles[0] + poles[1]).re, coeffs.a1, epsilon = 1e-12);
+        assert_abs_diff_eq!((poles[0] * poles[1]).re, coeffs.a2, epsilon = 1e-12);
+
+        let filter = KWeightFilter::<[f64; 1], 1>::new(48000).unwrap();
+        let (zeros, poles, gain) = filter.to_zpk();
+
+        assert_eq!(zeros.len(), 4);
+        assert_eq!(poles.len(), 4);
+        assert_abs_diff_eq!(gain, coeffs.b0);
+
+        // Without a `b0` term, there is a zero at infinity, and the gain is
+        // `b1`. Without `b1` either, both zeros are at infinity.
+        let delayed = Coefficients::new(0.0, 0.5, 0.25, -0.5, 0.0);
+        let (zeros, poles, gain) = delayed.to_zpk();
+
+        assert_eq!(zeros, [Complex::new(-0.5, 0.0), Complex::new(f64::INFINITY, 0.0)]);
+        assert_abs_diff_eq!(gain, 0.5);
+        assert_eq!(Coefficients::from_zpk(zeros, poles, gain), Ok(delayed));
+
+        let delayed = Coefficients::new(0.0, 0.0, 0.25, -0.5, 0.0);
+        let (zeros, poles, gain) = delayed.to_zpk();
+
+        assert!(zeros.iter().all(|z| z.re == f64::INFINITY));
+        assert_abs_diff_eq!(gain, 0.25);
+        assert_eq!(Coefficients::from_zpk(zeros, poles, gain), Ok(delayed));
+    }
+
+    #[test]
+    fn from_zpk() {
+        for sample_rate in [8000, 44100, 48000, 192000].iter().copied() {
+            for kind in [Kind::Shelving, Kind::HighPass].iter() {
+                let expected = kind.coefficients(sample_rate);
+                let (zeros, poles, gain) = expected.to_zpk();
+                let produced = Coefficients::from_zpk(zeros, poles, gain).unwrap();
+
+                assert_abs_diff_eq!(expected.b0, produced.b0, epsilon = 1e-12);
+                assert_abs_diff_eq!(expected.b1, produced.b1, epsilon = 1e-12);
+                assert_abs_diff_eq!(expected.b2, produced.b2, epsilon = 1e-12);
+                assert_abs_diff_eq!(expected.a1, produced.a1, epsilon = 1e-12);
+                assert_abs_diff_eq!(expected.a2, produced.a2, epsilon = 1e-12);
+            }
+        }
+
+        let zeros = [Complex::new(1.0, 0.0), Complex::new(1.0, 0.0)];
+
+        // Poles outside of the unit circle.
+        let poles = [Complex::new(1.5, 0.0), Complex::new(0.5, 0.0)];
+        assert_eq!(Coefficients::from_zpk(zeros, poles, 1.0), Err(Error::UnstableFilter));
+
+        // Complex poles that are not a conjugate pair.
+        let poles = [Complex::new(0.5, 0.5), Complex::new(0.5, 0.5)];
+        assert_eq!(Coefficients::from_zpk(zeros, poles, 1.0), Err(Error::UnpairedComplexRoot));
+    }
+
+    #[test]
+    fn from_analog_poles_zeros() {
+        // The analog prototypes of the K-weighting passes, from their natural
+        // frequency, Q and, for the shelving pass, gains.
+        let prototype_poles = |f0: f64, q: f64| {
+            let w0 = 2.0 * PI * f0;
+            quadratic_roots(w0 / q, w0 * w0)
+        };
+
+        for sample_rate in [8000, 44100, 48000, 192000].iter().copied() {
+            let (f0, q) = (1681.974450955533, 0.7071752369554196);
+            let w0 = 2.0 * PI * f0;
+            let vh = 10.0f64.powf(3.999843853973347 / 20.0);
+            let vb = vh.powf(0.4996667741545416);
+
+            // The numerator is `vh * s^2 + vb * (w0 / q) * s + w0^2`.
+            let zeros = quadratic_roots(vb * w0 / (q * vh), w0 * w0 / vh);
+
+            let expected = Kind::shelving_coefficients(sample_rate);
+            let produced = Coefficients::from_analog_poles_zeros(prototype_poles(f0, q), zeros, vh, sample_rate).unwrap();
+
+            assert_abs_diff_eq!(expected.b0, produced.b0, epsilon = 1e-9);
+            assert_abs_diff_eq!(expected.b1, produced.b1, epsilon = 1e-9);
+            assert_abs_diff_eq!(expected.b2, produced.b2, epsilon = 1e-9);
+            assert_abs_diff_eq!(expected.a1, produced.a1, epsilon = 1e-9);
+            assert_abs_diff_eq!(expected.a2, produced.a2, epsilon = 1e-9);
+
+            // The highpass pass is not normalized to unity gain, so only the
+            // shape of its numerator is compared.
+            let poles = prototype_poles(38.13547087602444, 0.5003270373238773);
+            let zeros = [Complex::new(0.0, 0.0); 2];
+
+            let expected = Kind::highpass_coefficients(sample_rate);
+            let produced = Coefficients::from_analog_poles_zeros(poles, zeros, 1.0, sample_rate).unwrap();
+
+            assert_abs_diff_eq!(produced.b1 / produced.b0, -2.0, epsilon = 1e-9);
+            assert_abs_diff_eq!(produced.b2 / produced.b0, 1.0, epsilon = 1e-9);
+            assert_abs_diff_eq!(expected.a1, produced.a1, epsilon = 1e-9);
+            assert_abs_diff_eq!(expected.a2, produced.a2, epsilon = 1e-9);
+        }
+
+        let poles = [Complex::new(-100.0, 100.0), Complex::new(-100.0, 100.0)];
+        let zeros = [Complex::new(0.0, 0.0); 2];
+        assert_eq!(Coefficients::from_analog_poles_zeros(poles, zeros, 1.0, 48000), Err(Error::UnpairedComplexRoot));
+        assert_eq!(Coefficients::from_analog_poles_zeros(poles, zeros, 1.0, 0), Err(Error::InvalidSampleRate(0)));
+    }
+
+    #[test]
+    fn filter_designer() {
+        const SAMPLE_RATE: u32 = 48000;
+
+        // The highpass pass of the K-weighting filter has the same poles, but
+        // its numerator is not normalized, so it has slightly more than unity
+        // gain in its passband.
+        let expected = Kind::highpass_coefficients(SAMPLE_RATE);
+        let produced = FilterDesigner::highpass(38.13547087602444, 0.5003270373238773, SAMPLE_RATE).unwrap();
+
+        assert_eq!(produced.a1, expected.a1);
+        assert_eq!(produced.a2, expected.a2);
+        assert_abs_diff_eq!(produced.b1 / produced.b0, expected.b1 / expected.b0, epsilon = 1e-12);
+        assert_abs_diff_eq!(produced.b2 / produced.b0, expected.b2 / expected.b0, epsilon = 1e-12);
+        assert_abs_diff_eq!(produced.magnitude_response(20000.0, SAMPLE_RATE), 1.0, epsilon = 1e-6);
+
+        // The shelving pass of the K-weighting filter has the gain at `f0`
+        // very slightly off from half of the shelf gain, which makes about
+        // 0.001 dB of difference.
+        let expected = Kind::shelving_coefficients(SAMPLE_RATE);
+        let produced = FilterDesigner::shelving_high(1681.974450955533, 3.999843853973347, 0.7071752369554196, SAMPLE_RATE).unwrap();
+
+        assert_eq!(produced.a1, expected.a1);
+        assert_eq!(produced.a2, expected.a2);
+        for &freq_hz in [20.0, 997.0, 1681.974450955533, 5000.0, 20000.0].iter() {
+            assert_abs_diff_eq!(produced.magnitude_db(freq_hz, SAMPLE_RATE), expected.magnitude_db(freq_hz, SAMPLE_RATE), epsilon = 2e-3);
+        }
+
+        let lowpass = FilterDesigner::lowpass(1000.0, FRAC_1_SQRT_2, SAMPLE_RATE).unwrap();
+        assert_abs_diff_eq!(lowpass.magnitude_response(1.0, SAMPLE_RATE), 1.0, epsilon = 1e-6);
+        assert_abs_diff_eq!(lowpass.magnitude_db(1000.0, SAMPLE_RATE), -3.0103, epsilon = 1e-3);
+
+        let bandpass = FilterDesigner::bandpass(1000.0, 2.0, SAMPLE_RATE).unwrap();
+        assert_abs_diff_eq!(bandpass.magnitude_response(1000.0, SAMPLE_RATE), 1.0, epsilon = 1e-9);
+        assert!(bandpass.magnitude_response(100.0, SAMPLE_RATE) < 0.1);
+
+        assert_eq!(FilterDesigner::lowpass(24000.0, 1.0, SAMPLE_RATE), Err(Error::InvalidCutoff(24000.0)));
+        assert_eq!(FilterDesigner::highpass(100.0, 0.0, SAMPLE_RATE), Err(Error::InvalidQFactor(0.0)));
+        assert_eq!(FilterDesigner::bandpass(100.0, 1.0, 0), Err(Error::InvalidSampleRate(0)));
+    }
+
+    // Checks the shelving pass against scipy, by designing the same analog
+    // prototype and converting it with `bilinear_zpk` and `zpk2sos`. scipy
+    // does not pre-warp, so the pre-warping is folded into the sample rate
+    // passed to it. Needs Python 3 with scipy, so this only runs with the
+    // `test-scipy` feature.
+    #[cfg(feature = "test-scipy")]
+    #[test]
+    fn scipy_bilinear_zpk() {
+        use crate::test_util::TestUtil;
+
+        const SCRIPT: &str = "
+import numpy as np
+from scipy import signal
+
+f0, q, fs = 1681.974450955533, 0.7071752369554196, 48000
+vh = 10.0 ** (3.999843853973347 / 20.0)
+vb = vh ** 0.4996667741545416
+w0 = 2.0 * np.pi * f0
+
+zeros = np.roots([vh, vb * w0 / q, w0 * w0])
+poles = np.roots([1.0, w0 / q, w0 * w0])
+prewarped_fs = w0 / np.tan(w0 / (2.0 * fs)) / 2.0
+
+z, p, k = signal.bilinear_zpk(zeros, poles, vh, prewarped_fs)
+print(*(repr(float(x)) for x in signal.zpk2sos(z, p, k)[0]))
+";
+
+        assert!(TestUtil::check_scipy(), "python3 with scipy is needed for this test");
+
+        let sos = TestUtil::python_eval_floats(SCRIPT);
+        assert_eq!(sos.len(), 6);
+        assert_abs_diff_eq!(sos[3], 1.0, epsilon = 1e-12);
+
+        let expected = Coefficients::new(sos[0], sos[1], sos[2], sos[4], sos[5]);
+        let produced = Kind::Shelving.coefficients(48000);
+
+        assert_abs_diff_eq!(expected.b0, produced.b0, epsilon = 1e-9);
+        assert_abs_diff_eq!(expected.b1, produced.b1, epsilon = 1e-9);
+        assert_abs_diff_eq!(expected.b2, produced.b2, epsilon = 1e-9);
+        assert_abs_diff_eq!(expected.a1, produced.a1, epsilon = 1e-9);
+        assert_abs_diff_eq!(expected.a2, produced.a2, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn apply_zero_phase() {
+        let filter = KWeightFilter::<[f64; 1], 1>::new(48000).unwrap();
+
+        // A symmetric input produces a symmetric output, since there is no
+        // phase shift. The impulse is placed far enough from the edges of
+        // the signal that the filter has settled by the time it reaches them.
+        let mut signal = vec![[0.0]; 9601];
+        signal[4800] = [1.0];
+
+        let produced = filter.apply_zero_phase(&signal);
+
+        assert_eq!(produced.len(), signal.len());
+        for i in 0..2400 {
+            assert_abs_diff_eq!(produced[4800 - i][0], produced[4800 + i][0], epsilon = 1e-9);
+        }
+
+        // The filter used for the zero phase pass is independent of the
+        // state of this filter.
+        let mut used_filter = KWeightFilter::<[f64; 1], 1>::new(48000).unwrap();
+        used_filter.process([1.0]);
+
+        assert_eq!(used_filter.apply_zero_phase(&signal), produced);
+
+        assert!(filter.apply_zero_phase(&[]).is_empty());
+    }
+
+    #[test]
+    fn apply_with_gain() {
+        let signal = [[1.0, -0.5], [0.25, 0.0], [0.0, 0.75], [-1.0, 0.0]];
+
+        let mut filter = KWeightFilter::new(48000).unwrap();
+        let mut gain_filter = KWeightFilter::new(48000).unwrap();
+        let mut pregain_filter = KWeightFilter::new(48000).unwrap();
+
+        for &frame in signal.iter() {
+            let [l, r] = filter.process(frame);
+            let expected = [l * 0.5, r * 0.5];
+
+            assert_eq!(gain_filter.apply_with_gain(frame, 0.5), expected);
+
+            let produced = pregain_filter.apply_with_pregain(frame, 0.5);
+            assert_abs_diff_eq!(produced[0], expected[0], epsilon = 1e-12);
+            assert_abs_diff_eq!(produced[1], expected[1], epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn new_lowpass_butterworth() {
+        const SAMPLE_RATE: u32 = 48000;
+        const CUTOFF: f64 = 1000.0;
+
+        for order in 1..=4 {
+            let mut passes = KWeightFilter::<f64, 1>::new_lowpass_butterworth(CUTOFF, SAMPLE_RATE, order).unwrap();
+            assert_eq!(passes.len(), ((order + 1) / 2) as usize);
+
+            // The gain should be -3 dB at the cutoff frequency.
+            let mut peak = 0.0f64;
+            for i in 0..4800 {
+                let x = (2.0 * PI * CUTOFF * i as f64 / SAMPLE_RATE as f64).sin();
+                let y = passes.iter_mut().fold(x, |x, pass| pass.process(x));
+
+                if i >= 4320 {
+                    peak = peak.max(y.abs());
+                }
+            }
+
+            assert_abs_diff_eq!(peak, std::f64::consts::FRAC_1_SQRT_2, epsilon = 1e-3);
+        }
+
+        assert!(matches!(
+            KWeightFilter::<f64, 1>::new_lowpass_butterworth(CUTOFF, SAMPLE_RATE, 5),
+            Err(Error::InvalidFilterOrder(5)),
+        ));
+        assert!(matches!(
+            KWeightFilter::<f64, 1>::new_lowpass_butterworth(24000.0, SAMPLE_RATE, 2),
+            Err(Error::InvalidCutoff(_)),
+        ));
+        assert_eq!(KWeightFilter::<f64, 1>::new_for_true_peak(4, SAMPLE_RATE).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn invalid_sample_rate() {
+        assert!(matches!(KWeightFilter::<[f64; 1], 1>::new(0), Err(Error::InvalidSampleRate(0))));
+        assert!(matches!(FilteredSamples::new(std::iter::empty::<[f64; 1]>(), 0), Err(Error::InvalidSampleRate(0))));
+    }
+
+    #[test]
+    fn new_at_rate() {
+        type Filter = KWeightFilter<[f64; 1], 1>;
+
+        for &sample_rate in [8000, 44100, 48000, 192000, 384000].iter() {
+            assert!(Filter::new_at_rate(sample_rate).is_ok());
+        }
+
+        for &sample_rate in [0, 1000, 7999, 384001].iter() {
+            assert!(matches!(Filter::new_at_rate(sample_rate), Err(Error::InvalidSampleRate(r)) if r == sample_rate));
+        }
+
+        // Rates too low for a stable shelving pass are an error, and not a
+        // panic, even for `new`.
+        for &sample_rate in [1, 2, 1000, 3000].iter() {
+            assert!(matches!(Filter::new(sample_rate), Err(Error::UnstableFilter)));
+            assert!(matches!(FilteredSamples::new(std::iter::empty::<[f64; 1]>(), sample_rate), Err(Error::UnstableFilter)));
+        }
+        assert!(Filter::new(4000).is_ok());
+    }
+
+    #[test]
+    fn filtered_samples_double_ended() {
+        let signal = (0..1000).map(|i| [(i as f64 * 0.37).sin()]).collect::<Vec<_>>();
+
+        // Taking frames from the back filters the reversed signal.
+        let mut filter = KWeightFilter::<[f64; 1], 1>::new(48000).unwrap();
+        let expected = signal.iter().rev().map(|&x| filter.process(x)).collect::<Vec<_>>();
+
+        let produced = FilteredSamples::new(signal.iter().copied(), 48000).unwrap().rev().collect::<Vec<_>>();
+        assert_eq!(produced, expected);
+
+        // Each end keeps its own filter state.
+        let mut filter = KWeightFilter::<[f64; 1], 1>::new(48000).unwrap();
+        let expected_front = signal[..3].iter().map(|&x| filter.process(x)).collect::<Vec<_>>();
+
+        let mut filtered = FilteredSamples::new(signal.iter().copied(), 48000).unwrap();
+
+        assert_eq!(filtered.next(), Some(expected_front[0]));
+        assert_eq!(filtered.next_back(), Some(expected[0]));
+        assert_eq!(filtered.next(), Some(expected_front[1]));
+        assert_eq!(filtered.next_back(), Some(expected[1]));
+        assert_eq!(filtered.next(), Some(expected_front[2]));
+        assert_eq!(filtered.len(), signal.len() - 5);
+    }
+
+    #[test]
+    fn filtered_samples_fused() {
+        fn assert_fused<I: FusedIterator>(_: &I) {}
+
+        let mut filtered = FilteredSamples::new(vec![[1.0], [0.5]].into_iter(), 48000).unwrap();
+        assert_fused(&filtered);
+
+        assert!(filtered.next().is_some());
+        assert!(filtered.next().is_some());
+
+        for _ in 0..4 {
+            assert_eq!(filtered.next(), None);
+        }
+    }
+
+    #[test]
+    fn reset() {
+        let signal = [[1.0], [0.5], [-0.25], [0.0], [0.0]];
+
+        let mut fresh_filter = KWeightFilter::<[f64; 1], 1>::new(48000).unwrap();
+        let expected = signal.iter().map(|&x| fresh_filter.process(x)).collect::<Vec<_>>();
+
+        let mut used_filter = KWeightFilter::<[f64; 1], 1>::new(48000).unwrap();
+        used_filter.process([0.75]);
+        used_filter.process([-0.75]);
+        used_filter.reset();
+
+        let produced = signal.iter().map(|&x| used_filter.process(x)).collect::<Vec<_>>();
+        assert_eq!(produced, expected);
+
+        let mut pass = FilterPass::<[f64; 1], 1>::from(Kind::HighPass.coefficients(48000));
+        pass.process([1.0]);
+        pass.reset();
+        assert_eq!((pass.m1, pass.m2), ([0.0], [0.0]));
+    }
+
+    #[test]
+    fn new_custom() {
+        // A pass-through biquad leaves the signal untouched.
+        const IDENTITY: FilterPass<[f64; 2], 2> = FilterPass::new_custom(Coefficients::new(1.0, 0.0, 0.0, 0.0, 0.0));
+
+        let mut pass = IDENTITY;
+        let signal = [[1.0, -1.0], [0.5, 0.25], [-0.25, 0.0]];
+
+        let produced = signal.iter().map(|&x| pass.process(x)).collect::<Vec<_>>();
+        assert_eq!(produced, signal);
+
+        // A custom pass with the K-weighting coefficients behaves the same as
+        // the built-in one.
+        let mut custom = FilterPass::<[f64; 1], 1>::new_custom(Kind::shelving_coefficients(44100));
+        let mut builtin = FilterPass::<[f64; 1], 1>::from(Kind::Shelving.coefficients(44100));
 
-        let expected = Params {
-            a1: -1.6636551132560204,
-            a2:  0.7125954280732254,
-            b0:  1.5308412300503478,
-            b1: -2.6509799951547297,
-            b2:  1.169079079921587,
-        };
-        let produced = Kind::Shelving.coefficients(44100);
+        for &x in [[1.0], [0.5], [-0.25], [0.0]].iter() {
+            assert_eq!(custom.process(x), builtin.process(x));
+        }
+    }
 
-        assert_eq!(expected, produced);
+    #[test]
+    fn with_state() {
+        const SAMPLE_RATE: u32 = 48000;
 
-        let expected = Params {
-            a1: -0.2933807824149212,
-            a2:  0.18687510604540827,
-            b0:  1.3216235689299776,
-            b1: -0.7262554913156911,
-            b2:  0.2981262460162007,
-        };
-        let produced = Kind::Shelving.coefficients(8000);
+        let signal = stereo_signal();
 
-        assert_eq!(expected, produced);
+        let mut filter = KWeightFilter::new(SAMPLE_RATE).unwrap();
+        let expected = signal.iter().map(|&x| filter.process(x)).collect::<Vec<_>>();
 
-        let expected = Params {
-            a1: -1.9222022306074886,
-            a2:  0.9251177351168259,
-            b0:  1.572227215091279,
-            b1: -3.0472830515615508,
-            b2:  1.4779713409796094,
+        // Each chunk is filtered by a fresh filter picking up the state of the
+        // previous one.
+        let mut state = KWeightFilter::<[f64; 2], 2>::new(SAMPLE_RATE).unwrap().state();
+        let mut produced = Vec::new();
+
+        for chunk in signal.chunks(1000) {
+            let mut filter = KWeightFilter::with_state(SAMPLE_RATE, state).unwrap();
+            produced.extend(chunk.iter().map(|&x| filter.process(x)));
+            state = filter.state();
+        }
+
+        assert_eq!(produced, expected);
+        assert!(matches!(KWeightFilter::with_state(0, state), Err(Error::InvalidSampleRate(0))));
+
+        let coefficients = Kind::highpass_coefficients(SAMPLE_RATE);
+        let mut pass = FilterPass::<[f64; 2], 2>::new_custom(coefficients);
+        let (head, tail) = signal.split_at(1234);
+
+        let expected = signal.iter().map(|&x| pass.process(x)).collect::<Vec<_>>();
+
+        pass.reset();
+        let mut produced = head.iter().map(|&x| pass.process(x)).collect::<Vec<_>>();
+
+        let (m1, m2) = pass.state();
+        let mut resumed = FilterPass::with_initial_conditions(coefficients, m1, m2);
+        produced.extend(tail.iter().map(|&x| resumed.process(x)));
+
+        assert_eq!(produced, expected);
+    }
+
+    #[test]
+    fn into_parts() {
+        const SAMPLE_RATE: u32 = 48000;
+
+        let signal = stereo_signal();
+
+        let expected = FilteredSamples::new(signal.iter().copied(), SAMPLE_RATE).unwrap().collect::<Vec<_>>();
+
+        // Filters the first half, and then the second half with the state
+        // left over from the first.
+        let mut filtered = FilteredSamples::new(signal.iter().copied(), SAMPLE_RATE).unwrap();
+        let mut produced = filtered.by_ref().take(2400).collect::<Vec<_>>();
+
+        let (rest, filter) = filtered.into_parts();
+        produced.extend(FilteredSamples::from_parts(rest, filter));
+
+        assert_eq!(produced, expected);
+
+        // The inner iterator picks up from the first unfiltered frame.
+        let mut filtered = FilteredSamples::new(signal.iter().copied(), SAMPLE_RATE).unwrap();
+        filtered.by_ref().take(100).for_each(drop);
+
+        assert_eq!(filtered.into_inner().next(), Some(signal[100]));
+    }
+
+    #[test]
+    fn with_raw() {
+        let signal = stereo_signal();
+
+        let expected = FilteredSamples::new(signal.iter().copied(), 48000).unwrap().collect::<Vec<_>>();
+
+        let with_raw = FilteredSamplesWithRaw::new(signal.iter().copied(), 48000).unwrap();
+        assert_eq!(with_raw.len(), signal.len());
+
+        let (raw, filtered): (Vec<_>, Vec<_>) = with_raw.unzip();
+        assert_eq!(raw, signal);
+        assert_eq!(filtered, expected);
+
+        // Converting part way through keeps the filter state.
+        let mut filtered = FilteredSamples::new(signal.iter().copied(), 48000).unwrap();
+        filtered.by_ref().take(100).for_each(drop);
+
+        let produced = filtered.with_raw().collect::<Vec<_>>();
+        assert_eq!(produced[0], (signal[100], expected[100]));
+        assert_eq!(produced.len(), signal.len() - 100);
+    }
+
+    #[test]
+    fn skip_transient() {
+        let signal = vec![[0.5f64, -0.25]; 4800];
+
+        // Filtering a DC offset from rest rings before it settles.
+        let cold = FilteredSamples::new(signal.iter().copied(), 48000).unwrap().collect::<Vec<_>>();
+        assert!(cold[0][0] > 0.5);
+
+        // The pre-roll settles the filter on the DC offset, which it removes.
+        let warm = FilteredSamples::new(signal.iter().copied(), 48000).unwrap()
+            .skip_transient(2880)
+            .collect::<Vec<_>>()
+        ;
+
+        assert_eq!(warm.len(), signal.len());
+        for frame in warm.iter() {
+            assert!(frame[0].abs() < 0.5e-5 && frame[1].abs() < 0.25e-5);
+        }
+
+        let empty = FilteredSamples::new(core::iter::empty::<[f64; 1]>(), 48000).unwrap().skip_transient(100);
+        assert_eq!(empty.count(), 0);
+
+        // The frame used for the pre-roll is held back, and is still counted,
+        // yielded and returned by `into_inner`. The source does not need to
+        // be cloneable.
+        let mut frames = signal.iter().copied();
+        let warm = FilteredSamples::new(core::iter::from_fn(move || frames.next()), 48000).unwrap().skip_transient(2880);
+        assert_eq!(warm.size_hint().0, 1);
+        assert_eq!(warm.into_inner().count(), signal.len());
+
+        let warm = FilteredSamples::new(signal.iter().copied(), 48000).unwrap().skip_transient(2880);
+        assert_eq!(warm.len(), signal.len());
+
+        let mut warm = FilteredSamples::new(signal.iter().copied(), 48000).unwrap().skip_transient(2880);
+        let last = warm.by_ref().take(100).last().unwrap();
+        assert!(last[0].abs() < 0.5e-5);
+
+        let mut warm = FilteredSamples::new(signal[..1].iter().copied(), 48000).unwrap().skip_transient(2880);
+        assert_eq!(warm.len(), 1);
+        assert!(warm.next_back().is_some());
+        assert_eq!(warm.next(), None);
+
+        let with_raw = FilteredSamples::new(signal.iter().copied(), 48000).unwrap().skip_transient(2880).with_raw();
+        assert_eq!(with_raw.len(), signal.len());
+        assert_eq!(with_raw.map(|(raw, _)| raw).collect::<Vec<_>>(), signal);
+    }
+
+    #[test]
+    fn impulse_response() {
+        let response = KWeightFilter::impulse_response(48000, 48000).unwrap();
+        assert_eq!(response.len(), 48000);
+
+        // The first sample is the product of the direct gains of both passes.
+        let expected = Kind::shelving_coefficients(48000).b0 * Kind::highpass_coefficients(48000).b0;
+        assert_abs_diff_eq!(response[0][0], expected);
+
+        // The envelope of the response decays below -60 dB of the initial
+        // peak, and stays there.
+        let peak = response.iter().map(|[x]| x.abs()).fold(0.0, f64::max);
+        let last_loud = response.iter().rposition(|[x]| Util::dbfs(x.abs() / peak) > -60.0).unwrap();
+
+        assert!(last_loud < 4800, "last loud sample: {}", last_loud);
+
+        assert!(KWeightFilter::impulse_response(0, 48000).unwrap().is_empty());
+        assert!(matches!(KWeightFilter::impulse_response(10, 0), Err(Error::InvalidSampleRate(0))));
+    }
+
+    #[test]
+    fn step_response() {
+        let highpass = FilterPass::<[f64; 2], 2>::from(Kind::highpass_coefficients(48000));
+        let shelving = FilterPass::<[f64; 2], 2>::from(Kind::shelving_coefficients(48000));
+
+        // The first frame is the direct gain of the pass.
+        let response = highpass.step_response(48000);
+        assert_eq!(response.len(), 48000);
+        assert_eq!(response[0], [highpass.coefficients().b0; 2]);
+
+        // The highpass pass rejects DC, so its step response decays to zero,
+        // while the shelving pass passes DC unchanged.
+        let [x, _] = response[47999];
+        assert_abs_diff_eq!(x, 0.0, epsilon = 1e-6);
+
+        let [x, y] = shelving.step_response(48000)[47999];
+        let dc_gain = shelving.coefficients().magnitude_response(0.0, 48000);
+        assert_abs_diff_eq!(dc_gain, 1.0, epsilon = 1e-9);
+        assert_abs_diff_eq!(x, dc_gain, epsilon = 1e-9);
+        assert_eq!(x, y);
+
+        assert!(shelving.step_response(0).is_empty());
+        assert_eq!(shelving.state(), ([0.0; 2], [0.0; 2]));
+    }
+
+    #[test]
+    fn from_interleaved_slice() {
+        let frames = stereo_signal().iter().map(|&[l, r]| [l as f32, r as f32]).collect::<Vec<_>>();
+
+        let interleaved = frames.iter().flatten().copied().collect::<Vec<f32>>();
+        let expected = FilteredSamples::new(frames.iter().map(|&[l, r]| [l as f64, r as f64]), 48000).unwrap()
+            .collect::<Vec<_>>()
+        ;
+
+        let produced = FilteredSamples::<_, 2>::from_interleaved_slice(&interleaved, 48000).unwrap();
+        assert_eq!(produced.len(), frames.len());
+        assert_eq!(produced.collect::<Vec<_>>(), expected);
+
+        assert!(matches!(
+            FilteredSamples::<_, 0>::from_interleaved_slice(&interleaved, 48000),
+            Err(Error::InvalidChannelCount(0)),
+        ));
+        assert!(matches!(
+            FilteredSamples::<_, 2>::from_interleaved_slice(&interleaved, 0),
+            Err(Error::InvalidSampleRate(0)),
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn from_reader() {
+        let interleaved = (0..9600).map(|i| (i as f32 * 0.37).sin() * 0.5).collect::<Vec<_>>();
+        let bytes = interleaved.iter().flat_map(|x| x.to_le_bytes()).collect::<Vec<_>>();
+
+        let expected = FilteredSamples::<_, 2>::from_interleaved_slice(&interleaved, 48000).unwrap()
+            .collect::<Vec<_>>()
+        ;
+
+        let produced = FilteredSamples::<_, 2>::from_reader(&bytes[..], 48000, 32).unwrap();
+        assert_eq!(produced.collect::<Vec<_>>(), expected);
+
+        assert!(matches!(
+            FilteredSamples::<_, 2>::from_reader(&bytes[..], 48000, 8),
+            Err(Error::UnsupportedFormat),
+        ));
+        assert!(matches!(
+            FilteredSamples::<_, 0>::from_reader(&bytes[..], 48000, 16),
+            Err(Error::InvalidChannelCount(0)),
+        ));
+    }
+
+    #[test]
+    fn from_planar_slices() {
+        use crate::gated_loudness::{Loudness, LoudnessResult};
+        use crate::gating::GatedPowerBlocks;
+
+        let left = (0..48000 * 3).map(|i| (i as f32 * 0.13).sin()).collect::<Vec<_>>();
+        let right = (0..48000 * 3).map(|i| 0.3 * (i as f32 * 0.029).cos()).collect::<Vec<_>>();
+
+        let interleaved = left.iter().zip(&right).flat_map(|(&l, &r)| [l, r]).collect::<Vec<_>>();
+
+        fn loudness<I: Iterator<Item = [f64; 2]>>(filtered: FilteredSamples<I, 2>) -> LoudnessResult {
+            let mut loudness = Loudness::new([1.0, 1.0]);
+            for block_powers in GatedPowerBlocks::new(filtered, 48000).unwrap() {
+                loudness.push(block_powers);
+            }
+            loudness.calculate().unwrap()
+        }
+
+        let expected = loudness(FilteredSamples::from_interleaved_slice(&interleaved, 48000).unwrap());
+        let produced = FilteredSamples::from_planar_slices([&left[..], &right[..]], 48000).unwrap();
+        assert_eq!(loudness(produced), expected);
+
+        assert!(matches!(
+            FilteredSamples::from_planar_slices([&left[..], &right[1..]], 48000),
+            Err(Error::MismatchedChannelLengths(144000, 143999)),
+        ));
+    }
+
+    #[test]
+    fn take_while_loud() {
+        use crate::gated_loudness::Loudness;
+        use crate::gating::GatedPowerBlocks;
+        use crate::test_util::TestUtil;
+
+        const SAMPLE_RATE: u32 = 48000;
+
+        // Three seconds of tone followed by a minute of silence.
+        let signal = TestUtil::sine_997(SAMPLE_RATE, SAMPLE_RATE as usize * 3)
+            .chain(std::iter::repeat(0.0).take(SAMPLE_RATE as usize * 60))
+            .map(|x| [x, x])
+            .collect::<Vec<_>>()
+        ;
+
+        let filtered = || FilteredSamples::new(signal.iter().copied(), SAMPLE_RATE).unwrap();
+        assert_eq!(filtered().sample_rate(), SAMPLE_RATE);
+
+        // The filter rings for a little while after the tone ends, so the
+        // first block after it may not count as silent.
+        let taken = filtered().take_while_loud([1.0, 1.0], -70.0, 2).collect::<Vec<_>>();
+        assert!(taken.len() >= SAMPLE_RATE as usize * 3 + 19200 * 2);
+        assert!(taken.len() <= SAMPLE_RATE as usize * 3 + 19200 * 3);
+        assert_eq!(taken[..], filtered().take(taken.len()).collect::<Vec<_>>()[..]);
+
+        // The skipped silence does not change the loudness of the signal.
+        let loudness = |blocks: &mut dyn Iterator<Item = [f64; 2]>| {
+            let mut loudness = Loudness::new([1.0, 1.0]);
+            for block_powers in blocks {
+                loudness.push(block_powers);
+            }
+            loudness.calculate().unwrap().integrated
         };
-        let produced = Kind::Shelving.coefficients(192000);
 
-        assert_eq!(expected, produced);
+        let expected = loudness(&mut GatedPowerBlocks::new(filtered(), SAMPLE_RATE).unwrap());
+        let truncated = FilteredSamples::new(signal[..taken.len()].iter().copied(), SAMPLE_RATE).unwrap();
+        let produced = loudness(&mut GatedPowerBlocks::new(truncated, SAMPLE_RATE).unwrap());
+        assert_abs_diff_eq!(produced, expected, epsilon = 1e-9);
 
-        let expected = Params {
-            a1: -1.9900474548339797,
-            a2:  0.9900722503662099,
-            b0:  1.0,
-            b1: -2.0,
-            b2:  1.0,
+        // With no run length, the whole signal is yielded.
+        assert_eq!(filtered().take_while_loud([1.0, 1.0], -70.0, 0).count(), signal.len());
+
+        // Silence is judged by loudness, with the channel weights, and not by
+        // the mean power of the channels. A steady block with a power of
+        // 0.9e-7 in both channels is below -70 dBFS, but is about -68.1 LUFS.
+        let powers = [0.9e-7f64, 0.9e-7];
+        assert!(10.0 * powers[0].log10() < -70.0);
+        assert!(Util::loudness(powers, [1.0, 1.0]) > -70.0);
+
+        // A tone that is only above the gate when both channels are counted
+        // is kept with both weights, but is cut after the first two blocks if
+        // only one channel counts.
+        let quiet = signal.iter().map(|&[x, y]| [x * 3.8e-4, y * 3.8e-4]).collect::<Vec<_>>();
+        let quiet_filtered = || FilteredSamples::new(quiet.iter().copied(), SAMPLE_RATE).unwrap();
+
+        let quiet_loudness = loudness(&mut GatedPowerBlocks::new(quiet_filtered(), SAMPLE_RATE).unwrap());
+        assert!(quiet_loudness > -70.0 && quiet_loudness - 3.0103 < -70.0);
+
+        let taken = quiet_filtered().take_while_loud([1.0, 1.0], -70.0, 2).count();
+        assert!(taken >= SAMPLE_RATE as usize * 3);
+        assert!(quiet_filtered().take_while_loud([1.0, 0.0], -70.0, 2).count() <= 19200 * 2);
+    }
+
+    #[test]
+    fn is_stable() {
+        assert!(Coefficients::IDENTITY.is_stable());
+        assert!(Kind::shelving_coefficients(48000).is_stable());
+        assert!(Kind::highpass_coefficients(48000).is_stable());
+
+        // Poles on the unit circle are not strictly inside it.
+        assert!(!Coefficients::new(1.0, 0.0, 0.0, 0.0, 1.0).is_stable());
+        assert!(!Coefficients::new(1.0, 0.0, 0.0, -2.0, 1.0).is_stable());
+        assert!(!Coefficients::new(1.0, 0.0, 0.0, 0.0, -1.5).is_stable());
+        assert!(!Coefficients::new(1.0, 0.0, 0.0, 1.5, 0.25).is_stable());
+
+        Kind::shelving_coefficients(48000).assert_stable();
+    }
+
+    #[test]
+    #[should_panic(expected = "unstable biquad coefficients")]
+    fn assert_stable() {
+        Coefficients::new(1.0, 0.0, 0.0, 1.5, 0.25).assert_stable();
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "unstable biquad coefficients")]
+    fn new_custom_unstable() {
+        FilterPass::<[f64; 1], 1>::new_custom(Coefficients::new(1.0, 0.0, 0.0, 0.0, 1.0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let coefficients = Kind::shelving_coefficients(44100);
+
+        let json = serde_json::to_string(&coefficients).unwrap();
+        let produced: Coefficients = serde_json::from_str(&json).unwrap();
+
+        let to_bits = |c: &Coefficients| [c.b0, c.b1, c.b2, c.a1, c.a2].map(f64::to_bits);
+        assert_eq!(to_bits(&produced), to_bits(&coefficients));
+
+        // A pass checkpointed mid-signal resumes with the same state.
+        let mut pass = FilterPass::<[f64; 2], 2>::new_custom(coefficients);
+        pass.process([1.0, -0.5]);
+        pass.process([0.25, 0.125]);
+
+        let json = serde_json::to_string(&pass).unwrap();
+        let mut resumed: FilterPass<[f64; 2], 2> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(to_bits(&resumed.coefficients), to_bits(&pass.coefficients));
+        assert_eq!(resumed.m1.map(f64::to_bits), pass.m1.map(f64::to_bits));
+        assert_eq!(resumed.m2.map(f64::to_bits), pass.m2.map(f64::to_bits));
+
+        let [x, y] = resumed.process([-0.75, 0.5]);
+        let [ex, ey] = pass.process([-0.75, 0.5]);
+        assert_eq!((x.to_bits(), y.to_bits()), (ex.to_bits(), ey.to_bits()));
+    }
+
+    #[test]
+    fn a_weighting() {
+        const SAMPLE_RATE: u32 = 48000;
+
+        // Reference values from IEC 61672-1, in dB. These are given for the
+        // nominal band frequencies, so they only match to within a rounding.
+        let inputs_and_expected = [
+            (31.5, -39.4),
+            (100.0, -19.1),
+            (250.0, -8.6),
+            (1000.0, 0.0),
+            (2000.0, 1.2),
+            (4000.0, 1.0),
+        ];
+
+        let coeffs = AWeightedFilter::<[f64; 1], 1>::coefficients(SAMPLE_RATE).unwrap();
+
+        for &(freq_hz, expected) in inputs_and_expected.iter() {
+            let gain = coeffs.iter().map(|c| c.magnitude_response(freq_hz, SAMPLE_RATE)).product::<f64>();
+            assert_abs_diff_eq!(Util::dbfs(gain), expected, epsilon = 0.2);
+        }
+
+        // A 1 kHz sine passes through with the same amplitude, once the filter
+        // has settled.
+        let mut filter = AWeightedFilter::<[f64; 1], 1>::new(SAMPLE_RATE).unwrap();
+        let produced = (0..SAMPLE_RATE as usize)
+            .map(|i| filter.process([(2.0 * PI * 1000.0 * i as f64 / SAMPLE_RATE as f64).sin()])[0])
+            .skip(SAMPLE_RATE as usize / 2)
+            .fold(0.0, |peak: f64, x| peak.max(x.abs()))
+        ;
+
+        assert_abs_diff_eq!(produced, 1.0, epsilon = 1e-3);
+
+        assert!(matches!(AWeightedFilter::<[f64; 1], 1>::new(0), Err(Error::InvalidSampleRate(0))));
+    }
+
+    #[test]
+    fn itu_468() {
+        // Reference values from ITU-R BS.468-4, in dB.
+        let inputs_and_expected = [
+            (31.5, -29.9),
+            (100.0, -19.8),
+            (400.0, -7.8),
+            (1000.0, 0.0),
+            (2000.0, 5.6),
+            (4000.0, 10.5),
+            (6300.0, 12.2),
+            (8000.0, 11.4),
+        ];
+
+        for &sample_rate in [44100, 48000].iter() {
+            let coeffs = coefficients_468(sample_rate).unwrap();
+
+            for &(freq_hz, expected) in inputs_and_expected.iter() {
+                let gain = coeffs.iter().map(|c| c.magnitude_response(freq_hz, sample_rate)).product::<f64>();
+
+                let epsilon = if freq_hz > 6300.0 { 0.5 } else { 0.2 };
+                assert_abs_diff_eq!(Util::dbfs(gain), expected, epsilon = epsilon);
+            }
+        }
+
+        // The filtered samples match running the filter directly.
+        let signal = (0..1000).map(|i| [(i as f64 * 0.37).sin(), (i as f64 * 0.11).cos()]).collect::<Vec<_>>();
+
+        let mut filter = Itu468Filter::<[f64; 2], 2>::new(48000).unwrap();
+        let expected = signal.iter().map(|&x| filter.process(x)).collect::<Vec<_>>();
+
+        let produced = Itu468FilteredSamples::new(signal.iter().copied(), 48000).unwrap().collect::<Vec<_>>();
+        assert_eq!(produced, expected);
+
+        assert!(matches!(coefficients_468(0), Err(Error::InvalidSampleRate(0))));
+        assert!(coefficients_468(8000).unwrap().iter().all(Coefficients::is_stable));
+    }
+
+    #[test]
+    fn z_weighted_samples() {
+        let signal = (0..1000).map(|i| [(i as f64 * 0.37).sin(), (i as f64 * 0.11).cos()]).collect::<Vec<_>>();
+
+        let weighted = ZWeightedSamples::new(signal.iter().copied());
+        assert_eq!(weighted.len(), signal.len());
+
+        let produced = weighted.collect::<Vec<_>>();
+        assert_eq!(produced, signal);
+    }
+
+    #[test]
+    fn frequency_response() {
+        // The K-weighting filter boosts a 997 Hz tone by the 0.691 dB that is
+        // subtracted back out when calculating loudness. This only holds near
+        // 48 kHz, which the spec coefficients were designed for.
+        for &sample_rate in [44100, 48000].iter() {
+            let filter = KWeightFilter::<[f64; 1], 1>::new(sample_rate).unwrap();
+            assert_eq!(filter.sample_rate(), sample_rate);
+
+            let gain = filter.frequency_response(997.0).norm();
+
+            assert_abs_diff_eq!(Util::dbfs(gain), 0.691, epsilon = 0.01);
+        }
+
+        // The highpass pass blocks DC and passes high frequencies with no
+        // phase shift at the Nyquist frequency.
+        let highpass = Kind::highpass_coefficients(48000);
+
+        assert_abs_diff_eq!(highpass.magnitude_response(0.0, 48000), 0.0);
+        assert_abs_diff_eq!(highpass.magnitude_db(24000.0, 48000), 0.0, epsilon = 0.1);
+        assert_abs_diff_eq!(highpass.phase_radians(24000.0, 48000).sin(), 0.0, epsilon = 1e-9);
+
+        // The shelving pass boosts high frequencies by about 4 dB.
+        let shelving = Kind::shelving_coefficients(48000);
+        assert_abs_diff_eq!(shelving.magnitude_db(20000.0, 48000), 4.0, epsilon = 0.1);
+    }
+
+    #[test]
+    fn gain_at_1khz() {
+        const SAMPLE_RATE: u32 = 48000;
+
+        let shelving = Kind::shelving_coefficients(SAMPLE_RATE);
+        let highpass = Kind::highpass_coefficients(SAMPLE_RATE);
+
+        assert_eq!(shelving.gain_db_at(1000.0, SAMPLE_RATE), Util::dbfs(shelving.gain_at_1khz(SAMPLE_RATE)));
+        assert_abs_diff_eq!(Coefficients::IDENTITY.gain_at_1khz(SAMPLE_RATE), 1.0, epsilon = 1e-12);
+
+        // The shelf has started to boost at 1 kHz, well above the highpass,
+        // which is nearly flat there.
+        let half = Coefficients::new(0.5, 0.0, 0.0, 0.0, 0.0);
+        assert_eq!(shelving.cmp_gain_at_1khz(&highpass, SAMPLE_RATE), Some(Ordering::Greater));
+        assert_eq!(half.cmp_gain_at_1khz(&highpass, SAMPLE_RATE), Some(Ordering::Less));
+
+        let mut sections = [shelving, half, highpass];
+        sections.sort_by(|a, b| a.cmp_gain_at_1khz(b, SAMPLE_RATE).unwrap());
+        assert_eq!(sections, [half, highpass, shelving]);
+    }
+
+    #[test]
+    fn cascade() {
+        const SAMPLE_RATE: u32 = 48000;
+
+        let shelving = Kind::shelving_coefficients(SAMPLE_RATE);
+        let highpass = Kind::highpass_coefficients(SAMPLE_RATE);
+
+        let sections = shelving.cascade(&highpass);
+        assert_eq!(sections, vec![shelving, highpass]);
+
+        let filter = KWeightFilter::<[f64; 1], 1>::new(SAMPLE_RATE).unwrap();
+
+        for &freq_hz in [0.0, 20.0, 997.0, 10000.0].iter() {
+            let expected = sections.iter().map(|c| c.magnitude_response(freq_hz, SAMPLE_RATE)).product::<f64>();
+
+            assert_abs_diff_eq!(filter.combined_frequency_response(freq_hz), expected, epsilon = 1e-12);
+        }
+
+        // The highpass pass has a double zero at DC, which blocks it entirely,
+        // while the shelving pass alone has unity gain there.
+        assert_abs_diff_eq!(filter.combined_frequency_response(0.0), 0.0);
+        assert_abs_diff_eq!(shelving.magnitude_response(0.0, SAMPLE_RATE), 1.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn group_delay() {
+        const SAMPLE_RATE: u32 = 48000;
+
+        // Compares against a numerical derivative of the phase response.
+        let numerical = |coefficients: &Coefficients, freq_hz: f64| {
+            let d_hz = 0.01;
+            let d_phase = coefficients.phase_radians(freq_hz + d_hz, SAMPLE_RATE)
+                - coefficients.phase_radians(freq_hz - d_hz, SAMPLE_RATE)
+            ;
+            let d_omega = 2.0 * PI * 2.0 * d_hz / SAMPLE_RATE as f64;
+
+            -d_phase / d_omega
         };
-        let produced = Kind::HighPass.coefficients(48000);
 
-        assert_eq!(expected, produced);
+        let highpass = FilterPass::<[f64; 1], 1>::new_custom(Kind::highpass_coefficients(SAMPLE_RATE));
+        let delay = highpass.group_delay(100.0, SAMPLE_RATE);
+
+        assert_abs_diff_eq!(delay, numerical(&Kind::highpass_coefficients(SAMPLE_RATE), 100.0), epsilon = 1e-3);
+
+        // The highpass pass delays 100 Hz by about 1.06 ms.
+        assert_abs_diff_eq!(delay, 50.87, epsilon = 0.01);
+
+        let filter = KWeightFilter::<[f64; 1], 1>::new(SAMPLE_RATE).unwrap();
+        for &freq_hz in [100.0, 1000.0, 5000.0].iter() {
+            let expected =
+                numerical(&Kind::shelving_coefficients(SAMPLE_RATE), freq_hz)
+                + numerical(&Kind::highpass_coefficients(SAMPLE_RATE), freq_hz)
+            ;
+
+            assert_abs_diff_eq!(filter.group_delay(freq_hz), expected, epsilon = 1e-3);
+        }
     }
-}
 
+    #[test]
+    fn filtered_samples_fold() {
+        let signal = stereo_signal();
+
+        // A `for` loop calls `next` for each frame.
+        let mut by_next = Vec::new();
+        for frame in FilteredSamples::new(signal.iter().copied(), 48000).unwrap() {
+            by_next.push(frame);
+        }
+
+        let by_fold = FilteredSamples::new(signal.iter().copied(), 48000).unwrap()
+            .fold(Vec::new(), |mut acc, frame| { acc.push(frame); acc })
+        ;
+
+        assert_eq!(by_fold, by_next);
+
+        // Folding after some frames have been taken continues from the
+        // current filter state.
+        let mut filtered = FilteredSamples::new(signal.iter().copied(), 48000).unwrap();
+        let partial = filtered.by_ref().take(100).collect::<Vec<_>>();
+        let partial = filtered.fold(partial, |mut acc, frame| { acc.push(frame); acc });
+
+        assert_eq!(partial, by_next);
+    }
+
+    #[test]
+    fn process_signal() {
+        let signal = stereo_signal();
+
+        let expected = FilteredSamples::new(signal.iter().copied(), 48000).unwrap().collect::<Vec<_>>();
+
+        assert_eq!(super::process_signal(signal.iter().copied(), 48000).unwrap(), expected);
+        assert_eq!(FilteredSamples::new(signal.iter().copied(), 48000).unwrap().collect_vec(), expected);
+        assert!(matches!(super::process_signal(signal.iter().copied(), 0), Err(Error::InvalidSampleRate(0))));
+
+        let boxed = FilteredSamples::new(signal.iter().copied(), 48000).unwrap().into_boxed_slice();
+        assert_eq!(&boxed[..], &expected[..]);
+
+        // Only the remaining frames are collected.
+        let mut filtered = FilteredSamples::new(signal.iter().copied(), 48000).unwrap();
+        filtered.by_ref().take(100).for_each(drop);
+
+        assert_eq!(&filtered.into_boxed_slice()[..], &expected[100..]);
+    }
+
+    /// A short stereo signal, whose right channel is an inverted and
+    /// attenuated copy of its left channel.
+    fn stereo_signal() -> Vec<[f64; 2]> {
+        (0..4800).map(|i| {
+            let x = (i as f64 * 0.37).sin();
+            [x, -0.5 * x]
+        }).collect()
+    }
+
+    /// Generates biquad coefficients with arbitrary zeros, and a pair of
+    /// conjugate poles strictly inside the unit circle.
+    fn stable_coefficients() -> impl Strategy<Value = Coefficients> {
+        ([-2.0f64..2.0, -2.0f64..2.0, -2.0f64..2.0], 0.0f64..0.99, 0.0f64..PI)
+            .prop_map(|(b, radius, angle)| {
+                Coefficients::new(b[0], b[1], b[2], -2.0 * radius * angle.cos(), radius * radius)
+            })
+    }
+
+    fn filter_signal(coefficients: Coefficients, signal: &[f64]) -> Vec<f64> {
+        let mut pass = FilterPass::<[f64; 1], 1>::new_custom(coefficients);
+
+        signal.iter().map(|&x| pass.process([x])[0]).collect()
+    }
+
+    #[test]
+    fn apply_block() {
+        let signal = (0..1000).map(|i| [(i as f64 * 0.37).sin(), (i as f64 * 0.11).cos()]).collect::<Vec<_>>();
+        let coefficients = Kind::shelving_coefficients(48000);
+
+        let mut pass = FilterPass::<[f64; 2], 2>::new_custom(coefficients);
+        let expected = signal.iter().map(|&x| pass.process(x)).collect::<Vec<_>>();
+
+        // Blocks continue on from the state left by the previous one.
+        let mut pass = FilterPass::new_custom(coefficients);
+        let mut produced = vec![[0.0; 2]; signal.len()];
+        for (input, output) in signal.chunks(300).zip(produced.chunks_mut(300)) {
+            pass.apply_block(input, output);
+        }
+
+        assert_eq!(produced, expected);
+    }
+
+    #[test]
+    fn k_weight_filter_apply_block() {
+        let signal = (0..1000).map(|i| [(i as f64 * 0.37).sin()]).collect::<Vec<_>>();
+
+        let expected = FilteredSamples::new(signal.iter().copied(), 48000).unwrap().collect::<Vec<_>>();
+
+        let mut filter = KWeightFilter::new(48000).unwrap();
+        let mut produced = vec![[0.0]; signal.len()];
+        for (input, output) in signal.chunks(300).zip(produced.chunks_mut(300)) {
+            filter.apply_block(input, output);
+        }
+
+        assert_eq!(produced, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "input and output blocks must have the same length")]
+    fn apply_block_mismatched() {
+        let mut pass = FilterPass::<[f64; 1], 1>::new_custom(Coefficients::IDENTITY);
+        pass.apply_block(&[[0.0]; 4], &mut [[0.0]; 3]);
+    }
+
+    proptest! {
+        #[test]
+        fn filter_pass_is_linear(
+            coefficients in stable_coefficients(),
+            x in prop::collection::vec(-1.0f64..1.0, 1..256),
+            y in prop::collection::vec(-1.0f64..1.0, 1..256),
+            gains in [-4.0f64..4.0, -4.0f64..4.0],
+        ) {
+            let (a, b) = (gains[0], gains[1]);
+            let len = x.len().min(y.len());
+            let (x, y) = (&x[..len], &y[..len]);
+
+            let mixed = x.iter().zip(y).map(|(x, y)| a * x + b * y).collect::<Vec<_>>();
+
+            let produced = filter_signal(coefficients, &mixed);
+            let fx = filter_signal(coefficients, x);
+            let fy = filter_signal(coefficients, y);
+
+            for ((p, fx), fy) in produced.iter().zip(&fx).zip(&fy) {
+                let expected = a * fx + b * fy;
+                let scale = (a * fx).abs() + (b * fy).abs();
+
+                prop_assert!((p - expected).abs() <= 1.0e-9 * scale.max(1.0));
+            }
+        }
+
+        #[test]
+        fn filter_pass_is_time_invariant(
+            coefficients in stable_coefficients(),
+            x in prop::collection::vec(-1.0f64..1.0, 1..256),
+            shift in 0usize..64,
+        ) {
+            // Starting from rest, a run of silence leaves the state at rest,
+            // so there is no startup transient.
+            let shifted = core::iter::repeat(0.0).take(shift).chain(x.iter().copied()).collect::<Vec<_>>();
+
+            let expected = filter_signal(coefficients, &x);
+            let produced = filter_signal(coefficients, &shifted);
+
+            prop_assert!(produced[..shift].iter().all(|&y| y == 0.0));
+            prop_assert_eq!(&produced[shift..], &expected[..]);
+        }
+
+        #[test]
+        fn filter_pass_df1_matches_df2(
+            coefficients in stable_coefficients(),
+            freq in 0.0f64..0.5,
+        ) {
+            let mut df1 = FilterPassDf1::<[f64; 2], 2>::new_custom(coefficients);
+            let mut df2 = FilterPass::<[f64; 2], 2>::new_custom(coefficients);
+
+            for i in 0..256 {
+                let x = (2.0 * PI * freq * i as f64).sin();
+                let frame = [x, if i == 0 { 1.0 } else { 0.0 }];
+
+                let expected = df2.process(frame);
+                let produced = df1.process(frame);
+
+                for (p, e) in produced.iter().zip(expected.iter()) {
+                    prop_assert!((p - e).abs() <= 1.0e-9 * e.abs().max(1.0));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,44 @@
+//! Float methods for `no_std` builds, where the inherent methods of `f64` that
+//! need platform support are not available. These are backed by `libm`, and
+//! are named the same as the inherent methods, so that code using them does
+//! not need to change between `std` and `no_std` builds.
+
+pub(crate) trait F64Ext {
+    fn abs(self) -> f64;
+    fn cos(self) -> f64;
+    fn log10(self) -> f64;
+    fn powf(self, n: f64) -> f64;
+    fn round(self) -> f64;
+    fn sqrt(self) -> f64;
+    fn tan(self) -> f64;
+}
+
+impl F64Ext for f64 {
+    fn abs(self) -> f64 {
+        libm::fabs(self)
+    }
+
+    fn cos(self) -> f64 {
+        libm::cos(self)
+    }
+
+    fn log10(self) -> f64 {
+        libm::log10(self)
+    }
+
+    fn powf(self, n: f64) -> f64 {
+        libm::pow(self, n)
+    }
+
+    fn round(self) -> f64 {
+        libm::round(self)
+    }
+
+    fn sqrt(self) -> f64 {
+        libm::sqrt(self)
+    }
+
+    fn tan(self) -> f64 {
+        libm::tan(self)
+    }
+}
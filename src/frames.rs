@@ -0,0 +1,364 @@
+//! Iterators that build frames out of common buffer layouts, for feeding
+//! audio from other libraries into the filters and meters of this crate.
+
+use core::iter::FusedIterator;
+use core::slice::ChunksExact;
+#[cfg(feature = "std")]
+use std::io::{BufReader, ErrorKind, Read};
+
+use crate::error::Error;
+
+/// Iterator over the frames of an interleaved buffer of `f32` samples, where
+/// the samples of each frame are stored together, one per channel. This is
+/// the layout used by most audio libraries, such as `cpal`, `hound` and
+/// `rodio`. The frames are read straight out of the buffer, without copying
+/// it. Any samples at the end that do not fill a whole frame are ignored.
+#[derive(Debug, Clone)]
+pub struct InterleavedFrames<'a, const N: usize> {
+    chunks: ChunksExact<'a, f32>,
+}
+
+impl<'a, const N: usize> InterleavedFrames<'a, N> {
+    /// Panics if `N` is 0.
+    pub fn new(samples: &'a [f32]) -> Self {
+        Self {
+            chunks: samples.chunks_exact(N),
+        }
+    }
+}
+
+impl<'a, const N: usize> Iterator for InterleavedFrames<'a, N> {
+    type Item = [f64; N];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk = self.chunks.next()?;
+
+        let mut frame = [0.0; N];
+        for (x, &s) in frame.iter_mut().zip(chunk) {
+            *x = s as f64;
+        }
+
+        Some(frame)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chunks.size_hint()
+    }
+}
+
+impl<'a, const N: usize> ExactSizeIterator for InterleavedFrames<'a, N> {}
+
+impl<'a, const N: usize> FusedIterator for InterleavedFrames<'a, N> {}
+
+/// Iterator over the frames of a planar buffer of `f32` samples, where each
+/// channel is stored in its own slice. This is the layout used by many
+/// professional audio APIs, such as JACK and PortAudio in non-interleaved
+/// mode. Each frame is gathered from the same position in every slice.
+#[derive(Debug, Clone)]
+pub struct PlanarFrames<'a, const N: usize> {
+    channels: [&'a [f32]; N],
+    index: usize,
+    len: usize,
+}
+
+impl<'a, const N: usize> PlanarFrames<'a, N> {
+    /// Returns `Error::MismatchedChannelLengths` if the slices do not all
+    /// have the same length.
+    pub fn new(channels: [&'a [f32]; N]) -> Result<Self, Error> {
+        let len = channels.first().map_or(0, |c| c.len());
+
+        if let Some(c) = channels.iter().find(|c| c.len() != len) {
+            return Err(Error::MismatchedChannelLengths(len, c.len()));
+        }
+
+        Ok(Self {
+            channels,
+            index: 0,
+            len,
+        })
+    }
+}
+
+impl<'a, const N: usize> Iterator for PlanarFrames<'a, N> {
+    type Item = [f64; N];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.len {
+            return None;
+        }
+
+        let mut frame = [0.0; N];
+        for (x, channel) in frame.iter_mut().zip(self.channels.iter()) {
+            *x = channel[self.index] as f64;
+        }
+
+        self.index += 1;
+
+        Some(frame)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, const N: usize> ExactSizeIterator for PlanarFrames<'a, N> {}
+
+impl<'a, const N: usize> FusedIterator for PlanarFrames<'a, N> {}
+
+/// A raw PCM sample format, as read by `PcmFrames`. These are the formats
+/// written by tools such as `ffmpeg -f s16le`, `-f s24le` and `-f f32le`.
+#[cfg(feature = "std")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PcmFormat {
+    /// Signed 16-bit little-endian integers.
+    S16Le,
+    /// Signed 24-bit little-endian integers, packed into 3 bytes each.
+    S24Le,
+    /// 32-bit little-endian floats.
+    F32Le,
+}
+
+#[cfg(feature = "std")]
+impl PcmFormat {
+    /// Picks a format from its bit depth. 16 and 24 bits are integer formats,
+    /// and 32 bits is the float format. Any other bit depth returns
+    /// `Error::UnsupportedFormat`.
+    pub fn from_bit_depth(bit_depth: u32) -> Result<Self, Error> {
+        match bit_depth {
+            16 => Ok(Self::S16Le),
+            24 => Ok(Self::S24Le),
+            32 => Ok(Self::F32Le),
+            _ => Err(Error::UnsupportedFormat),
+        }
+    }
+
+    pub const fn bytes_per_sample(&self) -> usize {
+        match self {
+            Self::S16Le => 2,
+            Self::S24Le => 3,
+            Self::F32Le => 4,
+        }
+    }
+
+    fn decode(&self, bytes: [u8; 4]) -> f64 {
+        match self {
+            Self::S16Le => i16::from_le_bytes([bytes[0], bytes[1]]) as f64 / 32768.0,
+            Self::S24Le => (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as f64 / 8388608.0,
+            Self::F32Le => f32::from_le_bytes(bytes) as f64,
+        }
+    }
+}
+
+/// Iterator over the frames of raw interleaved PCM audio read from a reader,
+/// such as standard input with audio piped from `ffmpeg`. The reader is
+/// buffered internally. Iteration stops at the end of the input, ignoring any
+/// trailing partial frame, or at the first I/O error, which is then kept and
+/// can be checked with `error`.
+#[cfg(feature = "std")]
+pub struct PcmFrames<R: Read, const N: usize> {
+    reader: BufReader<R>,
+    format: PcmFormat,
+    error: Option<Error>,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read, const N: usize> PcmFrames<R, N> {
+    pub fn new(reader: R, format: PcmFormat) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            format,
+            error: None,
+        }
+    }
+
+    /// The I/O error that stopped iteration, if any.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read, const N: usize> Iterator for PcmFrames<R, N> {
+    type Item = [f64; N];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error.is_some() {
+            return None;
+        }
+
+        let len = self.format.bytes_per_sample();
+
+        let mut frame = [0.0; N];
+        for x in frame.iter_mut() {
+            let mut bytes = [0u8; 4];
+
+            match self.reader.read_exact(&mut bytes[..len]) {
+                Ok(()) => *x = self.format.decode(bytes),
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return None,
+                Err(e) => {
+                    self.error = Some(e.into());
+                    return None;
+                },
+            }
+        }
+
+        Some(frame)
+    }
+}
+
+/// Iterator over the frames of a WAV file decoded by `hound`. Samples can be
+/// PCM of 8 to 32 bits, or 32-bit float, and are scaled to `[-1.0, 1.0)`. Like
+/// `PcmFrames`, iteration stops at the end of the file, ignoring any trailing
+/// partial frame, or at the first decoding error, which is then kept and can
+/// be checked with `error`.
+#[cfg(all(feature = "std", feature = "hound"))]
+pub struct WavFrames<R: Read, const N: usize> {
+    samples: WavSamples<R>,
+    error: Option<Error>,
+}
+
+#[cfg(all(feature = "std", feature = "hound"))]
+enum WavSamples<R: Read> {
+    Float(hound::WavIntoSamples<R, f32>),
+    // Integer samples, along with the scale that maps them to `[-1.0, 1.0)`.
+    Int(hound::WavIntoSamples<R, i32>, f64),
+}
+
+#[cfg(all(feature = "std", feature = "hound"))]
+impl<R: Read, const N: usize> WavFrames<R, N> {
+    /// Returns `Error::InvalidChannelCount` if the file does not have `N`
+    /// channels, or `Error::UnsupportedFormat` if its samples are in a format
+    /// other than those listed above.
+    pub fn new(reader: hound::WavReader<R>) -> Result<Self, Error> {
+        let spec = reader.spec();
+
+        if spec.channels as usize != N {
+            return Err(Error::InvalidChannelCount(spec.channels as usize));
+        }
+
+        let samples = match (spec.sample_format, spec.bits_per_sample) {
+            (hound::SampleFormat::Float, 32) => WavSamples::Float(reader.into_samples()),
+            (hound::SampleFormat::Int, 8..=32) => {
+                let scale = (1u64 << (spec.bits_per_sample - 1)) as f64;
+
+                WavSamples::Int(reader.into_samples(), scale)
+            },
+            _ => return Err(Error::UnsupportedFormat),
+        };
+
+        Ok(Self { samples, error: None })
+    }
+
+    /// The decoding error that stopped iteration, if any.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+}
+
+#[cfg(all(feature = "std", feature = "hound"))]
+impl<R: Read, const N: usize> Iterator for WavFrames<R, N> {
+    type Item = [f64; N];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error.is_some() {
+            return None;
+        }
+
+        let mut frame = [0.0; N];
+        for x in frame.iter_mut() {
+            let sample = match &mut self.samples {
+                WavSamples::Float(samples) => samples.next()?.map(|s| s as f64),
+                WavSamples::Int(samples, scale) => samples.next()?.map(|s| s as f64 / *scale),
+            };
+
+            match sample {
+                Ok(s) => *x = s,
+                Err(e) => {
+                    self.error = Some(e.into());
+                    return None;
+                },
+            }
+        }
+
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interleaved_frames() {
+        let samples = [0.5f32, -0.5, 0.25, -0.25, 1.0];
+
+        let frames = InterleavedFrames::<2>::new(&samples);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames.collect::<Vec<_>>(), [[0.5, -0.5], [0.25, -0.25]]);
+
+        assert_eq!(InterleavedFrames::<1>::new(&samples).count(), 5);
+        assert_eq!(InterleavedFrames::<3>::new(&[]).next(), None);
+    }
+
+    #[test]
+    fn planar_frames() {
+        let left = [0.5f32, 0.25];
+        let right = [-0.5f32, -0.25];
+
+        let frames = PlanarFrames::new([&left[..], &right[..]]).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames.collect::<Vec<_>>(), [[0.5, -0.5], [0.25, -0.25]]);
+
+        assert_eq!(
+            PlanarFrames::new([&left[..], &right[..1]]).err(),
+            Some(Error::MismatchedChannelLengths(2, 1)),
+        );
+        assert_eq!(PlanarFrames::<0>::new([]).unwrap().next(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn pcm_frames() {
+        let s16 = [0x00, 0x40, 0x00, 0x80, 0xFF, 0x7F];
+        let frames = PcmFrames::<_, 2>::new(&s16[..], PcmFormat::S16Le).collect::<Vec<_>>();
+        assert_eq!(frames, [[0.5, -1.0]]);
+
+        let s24 = [0x00, 0x00, 0xC0, 0x00, 0x00, 0x20];
+        let frames = PcmFrames::<_, 1>::new(&s24[..], PcmFormat::S24Le).collect::<Vec<_>>();
+        assert_eq!(frames, [[-0.5], [0.25]]);
+
+        let f32le = [0.75f32, -0.125].iter().flat_map(|x| x.to_le_bytes()).collect::<Vec<_>>();
+        let frames = PcmFrames::<_, 1>::new(&f32le[..], PcmFormat::F32Le).collect::<Vec<_>>();
+        assert_eq!(frames, [[0.75], [-0.125]]);
+
+        assert_eq!(PcmFormat::from_bit_depth(24), Ok(PcmFormat::S24Le));
+        assert_eq!(PcmFormat::from_bit_depth(8), Err(Error::UnsupportedFormat));
+    }
+
+    #[cfg(all(feature = "std", feature = "hound"))]
+    #[test]
+    fn wav_frames() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 48000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+
+        let mut wav = std::io::Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut wav, spec).unwrap();
+        for &x in [16384i16, -32768, 8192, -8192, 1].iter() {
+            writer.write_sample(x).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let reader = || hound::WavReader::new(&wav.get_ref()[..]).unwrap();
+
+        let frames = WavFrames::<_, 2>::new(reader()).unwrap();
+        assert_eq!(frames.collect::<Vec<_>>(), [[0.5, -1.0], [0.25, -0.25]]);
+
+        assert_eq!(WavFrames::<_, 1>::new(reader()).err(), Some(Error::InvalidChannelCount(2)));
+    }
+}
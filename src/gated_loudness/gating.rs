@@ -16,6 +16,23 @@ pub enum Gating {
     Custom { gate_len_ms: u64, delta_len_ms: u64 },
 }
 
+impl Gating {
+    /// Calculates the gate length and the delta length, in frames, for this
+    /// gating at a given sample rate.
+    pub fn frame_lens(&self, sample_rate: u32) -> (usize, usize) {
+        let (gate_len_ms, delta_len_ms) = match self {
+            Self::Momentary => (MOMENTARY_GATE_MS, MOMENTARY_DELTA_MS),
+            Self::Shortterm => (SHORTTERM_GATE_MS, SHORTTERM_DELTA_MS),
+            Self::Custom { gate_len_ms: g, delta_len_ms: d } => (*g, *d),
+        };
+
+        let gate_len = Util::ms_to_samples(gate_len_ms, sample_rate) as usize;
+        let delta_len = Util::ms_to_samples(delta_len_ms, sample_rate) as usize;
+
+        (gate_len, delta_len)
+    }
+}
+
 pub struct GatedPowers<F, const N: usize>
 where
     F: Frame<N>,
@@ -32,22 +49,15 @@ where
     F::Sample: FloatSample,
 {
     pub fn new(sample_rate: u32, gating: Gating) -> Self {
-        let (gate_len_ms, delta_len_ms) = match gating {
-            Gating::Momentary => (MOMENTARY_GATE_MS, MOMENTARY_DELTA_MS),
-            Gating::Shortterm => (SHORTTERM_GATE_MS, SHORTTERM_DELTA_MS),
-            Gating::Custom { gate_len_ms: g, delta_len_ms: d } => (g, d),
-        };
-
-        // Calculate the gate length, in frames.
-        // This will in turn determine the length of the mean squares buffer.
-        let gate_buffer_len = Util::ms_to_samples(gate_len_ms, sample_rate) as usize;
-
-        // Number of frames to add at a time for each iteration after the first.
-        // This is the number of steps to advance the mean squares iterator for
-        // each iteration (i.e. the "step-by" amount).
-        let frames_per_delta = Util::ms_to_samples(delta_len_ms, sample_rate) as usize;
-
-        assert!(frames_per_delta > 0);
+        // The gate length, in frames, determines the length of the mean
+        // squares buffer.
+        // The delta length, in frames, is the number of frames to add at a
+        // time for each iteration after the first. This is the number of
+        // steps to advance the mean squares iterator for each iteration (i.e.
+        // the "step-by" amount). As with `GatingConfig`, this is always at
+        // least one frame, even at sample rates too low to fit one in a delta.
+        let (gate_buffer_len, frames_per_delta) = gating.frame_lens(sample_rate);
+        let frames_per_delta = frames_per_delta.max(1);
 
         let buffer = vec![Frame::EQUILIBRIUM; gate_buffer_len];
 
@@ -64,6 +74,11 @@ where
         self.ms_state.reset();
     }
 
+    /// The number of frames between the starts of consecutive gates.
+    pub fn delta(&self) -> usize {
+        self.delta
+    }
+
     pub fn momentary(sample_rate: u32) -> Self {
         Self::new(sample_rate, Gating::Momentary)
     }
@@ -1,17 +1,355 @@
-use sampara::{Frame, Calculator};
-use sampara::stats::CumulativeMean;
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter, LowerExp, Result as FmtResult};
+#[cfg(feature = "serde_json")]
+use std::io::Write;
+#[cfg(feature = "hound")]
+use std::io::{BufRead, BufReader, Read};
+use std::time::{Duration, Instant};
 
+use sampara::{Frame, Calculator, Signal};
+#[cfg(feature = "hound")]
+use sampara::signal;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::dither::TpdfDither;
+use crate::error::Error;
+use crate::filter::KWeightFilter;
+#[cfg(feature = "hound")]
+use crate::frames::{PcmFormat, PcmFrames, WavFrames};
+use crate::gated_loudness::{GatedPowers, Gating};
+use crate::lra::{LoudnessRange, LRA_DELTA_MS, LRA_GATE_MS};
+use crate::resample::SampleRateConverter;
+use crate::signal_ext::SignalExt;
+use crate::stats::Stats;
+use crate::timeline::LoudnessTimeline;
+use crate::true_peak::TruePeakMeter;
+use crate::unit::LoudnessValue;
 use crate::util::Util;
 
-const ABS_LOUDNESS_THRESH: f64 = -70.0;
+/// The absolute gating threshold introduced in ITU-R BS.1770-2, and still in
+/// use as of BS.1770-4.
+pub const ABSOLUTE_LOUDNESS_THRESHOLD_BS1770_4: f64 = -70.0;
+
+// The sample rate recommended by the EBU for loudness measurements.
+const RESAMPLED_RATE: u32 = 48000;
+
+// The maximum short-term loudness of EBU R128 reports is taken over blocks of
+// 3 seconds, updated at the 10 Hz rate recommended for meters. The loudness
+// range uses the less frequent blocks of `lra` instead.
+const SHORT_TERM_GATE_MS: u64 = 3000;
+const SHORT_TERM_DELTA_MS: u64 = 100;
+
+// Fixed seed for dithering, so that repeated measurements agree.
+const DITHER_SEED: u64 = 0x5EED;
+
+// Number of gate blocks summed by each parallel task. This is about 100
+// seconds of momentary gate blocks.
+#[cfg(feature = "rayon")]
+const PAR_CHUNK_LEN: usize = 1024;
+
+/// Timing information collected while measuring loudness, useful for finding
+/// bottlenecks in processing pipelines.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ProcessingMetrics {
+    pub filter_ns_per_frame: f64,
+    pub gating_ns: f64,
+    pub stats_ns: f64,
+    pub total_ns: f64,
+    pub frames_processed: usize,
+}
+
+/// Statistics about the gate blocks that passed one of the two gating passes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GatePassStats {
+    /// The number of gate blocks that exceeded the threshold.
+    pub num_blocks: usize,
+    /// The loudness of the average power of the blocks that exceeded the
+    /// threshold.
+    pub mean_loudness_lufs: f64,
+    /// The loudness threshold used for this pass.
+    pub threshold_lufs: f64,
+}
+
+/// The result of a gated loudness calculation.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LoudnessResult {
+    /// The integrated loudness, in LUFS.
+    pub integrated: f64,
+    /// Statistics about the absolute gating pass.
+    pub absolute_pass: GatePassStats,
+    /// Statistics about the relative gating pass.
+    pub relative_pass: GatePassStats,
+    /// The highest absolute sample value across all channels, in dBFS. This
+    /// is only available when the result was measured from a signal.
+    pub max_sample_peak_dbfs: Option<f64>,
+}
+
+impl LoudnessResult {
+    /// The loudness of all blocks that passed the absolute gate, in LUFS.
+    pub fn absolute_gated(&self) -> f64 {
+        self.absolute_pass.mean_loudness_lufs
+    }
+
+    /// The threshold of the relative gate, in LUFS.
+    pub fn relative_threshold(&self) -> f64 {
+        self.relative_pass.threshold_lufs
+    }
+
+    /// The program loudness to peak ratio (PLR), in LU. This is `None` if the
+    /// sample peak was not measured along with the loudness.
+    pub fn plr(&self) -> Option<f64> {
+        self.max_sample_peak_dbfs.map(|peak_dbfs| self.plr_with_peak(peak_dbfs))
+    }
+
+    /// The program loudness to peak ratio (PLR), in LU, using a peak level
+    /// that was measured separately.
+    pub fn plr_with_peak(&self, peak_dbfs: f64) -> f64 {
+        peak_dbfs - self.integrated
+    }
+
+    /// The gain, in dB, that brings the integrated loudness to a target
+    /// loudness, in LUFS.
+    pub fn normalization_gain_db(&self, target: f64) -> f64 {
+        Util::normalization_gain_db(self.integrated, target)
+    }
+}
+
+impl Display for LoudnessResult {
+    /// Formats a summary of the measurement, such as "Integrated: -23.1 LUFS |
+    /// Absolute gate: -24.5 LUFS | Relative threshold: -34.5 LUFS". Values
+    /// have one decimal place, unless a precision is given.
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let precision = f.precision().unwrap_or(1);
+
+        write!(
+            f, "Integrated: {:.*} | Absolute gate: {:.*} | Relative threshold: {:.*}",
+            precision, LoudnessValue::lufs(self.integrated),
+            precision, LoudnessValue::lufs(self.absolute_gated()),
+            precision, LoudnessValue::lufs(self.relative_threshold()),
+        )
+    }
+}
+
+impl LowerExp for LoudnessResult {
+    /// Formats the same summary as `Display`, with values in scientific
+    /// notation, such as "Integrated: -2.31e1 LUFS | ...".
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let values = [
+            ("Integrated", self.integrated),
+            ("Absolute gate", self.absolute_gated()),
+            ("Relative threshold", self.relative_threshold()),
+        ];
+
+        for (i, &(label, value)) in values.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" | ")?;
+            }
+
+            match f.precision() {
+                Some(precision) => write!(f, "{}: {:.*e} LUFS", label, precision, value)?,
+                None => write!(f, "{}: {:e} LUFS", label, value)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Counts of the gate blocks seen by a loudness calculation, and how many of
+/// them passed each gate. These are useful for diagnosing signals that do not
+/// have a measurable loudness, such as silent ones, where no blocks pass the
+/// absolute gate.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct LoudnessDiagnostics {
+    /// The number of gate blocks pushed, including silent ones.
+    pub total_blocks: usize,
+    /// The number of gate blocks above the absolute gate.
+    pub blocks_above_absolute: usize,
+    /// The number of gate blocks above both the absolute and relative gates.
+    pub blocks_above_relative: usize,
+}
+
+/// The results of measuring both the loudness and the true peak of a signal.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LoudnessAndTruePeakResult {
+    pub loudness: LoudnessResult,
+    /// The highest true peak across all channels, in dBTP.
+    pub true_peak_dbtp: f64,
+}
+
+/// All of the measurements required to be reported by EBU R128.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EbuR128Report {
+    pub integrated_lufs: f64,
+    /// The loudness range, or 0.0 if the signal is too short to have any
+    /// short-term blocks.
+    pub lra_lu: f64,
+    pub max_true_peak_dbtp: f64,
+    /// The highest short-term loudness, or negative infinity if the signal is
+    /// too short to have any short-term blocks.
+    pub max_short_term_lufs: f64,
+}
+
+impl EbuR128Report {
+    /// The integrated loudness targeted by EBU R128 for broadcast, in LUFS.
+    pub const BROADCAST_TARGET_LUFS: f64 = -23.0;
+
+    /// The tolerance around the broadcast target, in LU. R128 allows this
+    /// wider tolerance for programmes where an exact level is not practical,
+    /// such as live ones.
+    pub const BROADCAST_TOLERANCE_LU: f64 = 1.0;
+
+    /// The integrated loudness that streaming services such as Spotify and
+    /// YouTube normalize to, in LUFS.
+    pub const STREAMING_TARGET_LUFS: f64 = -14.0;
+
+    /// The tolerance around the streaming target, in LU.
+    pub const STREAMING_TOLERANCE_LU: f64 = 1.0;
+
+    /// The highest true peak allowed for both broadcast and streaming, in
+    /// dBTP.
+    pub const MAX_TRUE_PEAK_DBTP: f64 = -1.0;
+
+    fn is_compliant(&self, target_lufs: f64, tolerance_lu: f64) -> bool {
+        (self.integrated_lufs - target_lufs).abs() <= tolerance_lu
+            && self.max_true_peak_dbtp <= Self::MAX_TRUE_PEAK_DBTP
+    }
+
+    /// Checks if the integrated loudness is within 1 LU of -14 LUFS, and the
+    /// true peak is at most -1 dBTP, inclusive.
+    pub fn is_compliant_for_streaming(&self) -> bool {
+        self.is_compliant(Self::STREAMING_TARGET_LUFS, Self::STREAMING_TOLERANCE_LU)
+    }
+
+    /// Checks if the integrated loudness is within 1 LU of -23 LUFS, and the
+    /// true peak is at most -1 dBTP, inclusive.
+    pub fn is_compliant_for_broadcast(&self) -> bool {
+        self.is_compliant(Self::BROADCAST_TARGET_LUFS, Self::BROADCAST_TOLERANCE_LU)
+    }
+
+    /// Formats all of the measurements, one per line, followed by whether the
+    /// signal passes the broadcast and streaming checks.
+    pub fn compliance_summary(&self) -> String {
+        let pass_fail = |passed: bool| if passed { "PASS" } else { "FAIL" };
 
+        format!(
+            "Integrated loudness: {:.1}\n\
+             Loudness range: {:.1} LU\n\
+             Maximum true peak: {:.1} dBTP\n\
+             Maximum short-term loudness: {:.1}\n\
+             Broadcast (EBU R128): {}\n\
+             Streaming: {}\n",
+            LoudnessValue::lufs(self.integrated_lufs),
+            self.lra_lu,
+            self.max_true_peak_dbtp,
+            LoudnessValue::lufs(self.max_short_term_lufs),
+            pass_fail(self.is_compliant_for_broadcast()),
+            pass_fail(self.is_compliant_for_streaming()),
+        )
+    }
+}
+
+/// The peak and RMS levels of a signal, without K-weighting, and the ratio
+/// between them.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CrestFactorResult {
+    pub crest_factor_db: f64,
+    /// The highest absolute sample value across all channels, in dBFS.
+    pub peak_dbfs: f64,
+    /// The RMS level across all channels, in dBFS.
+    pub rms_dbfs: f64,
+}
+
+/// The kind of absolute gate used to discard silent gate blocks.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AbsoluteGateKind {
+    /// The absolute gate of -70 LUFS, as specified in BS.1770-4.
+    Bs17704,
+    /// A custom absolute gate, in LUFS. This is useful for matching older
+    /// equipment, which may report against a -60 LUFS gate.
+    Legacy(f64),
+}
+
+impl AbsoluteGateKind {
+    /// The loudness threshold of this gate, in LUFS.
+    pub fn threshold(&self) -> f64 {
+        match self {
+            Self::Bs17704 => ABSOLUTE_LOUDNESS_THRESHOLD_BS1770_4,
+            Self::Legacy(t) => *t,
+        }
+    }
+}
+
+impl Default for AbsoluteGateKind {
+    fn default() -> Self {
+        Self::Bs17704
+    }
+}
+
+/// Configuration of the gates used when calculating integrated loudness.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct GateConfig {
+    pub absolute_gate: AbsoluteGateKind,
+}
+
+/// A function that calculates the per-channel power of a gate block of
+/// K-weighted frames. The standard calculation is the mean square of each
+/// channel, as done by `Util::mean_square`.
+pub trait BlockPowerFn<const N: usize>: Fn(&[[f64; N]]) -> [f64; N] {}
+
+impl<P, const N: usize> BlockPowerFn<N> for P
+where
+    P: Fn(&[[f64; N]]) -> [f64; N],
+{}
+
+/// The correlation between each pair of channels of a signal, as calculated
+/// by `Loudness::measure_with_correlation`, indexed by channel.
+pub type CorrelationMatrix<const N: usize> = [[f64; N]; N];
+
+/// A function that weights the importance of a gate block, given its index
+/// and its loudness. A weight of 1.0 for every block gives the standard
+/// BS.1770 integrated loudness.
+pub trait GateBlockWeighter: Fn(usize, f64) -> f64 {}
+
+impl<W> GateBlockWeighter for W
+where
+    W: Fn(usize, f64) -> f64,
+{}
+
+/// Number of absolutely-loud gate blocks to store inline before spilling to
+/// the heap, when the `smallvec` feature is enabled. This covers a little over
+/// 6 seconds of momentary gate blocks, so short clips such as UI sounds never
+/// allocate, while a `Loudness` stays small enough to move around cheaply.
+#[cfg(feature = "smallvec")]
+const INLINE_LOUD_FRAMES: usize = 64;
+
+#[cfg(feature = "smallvec")]
+type LoudFrames<F> = smallvec::SmallVec<[(f64, F, f64); INLINE_LOUD_FRAMES]>;
+
+#[cfg(not(feature = "smallvec"))]
+type LoudFrames<F> = Vec<(f64, F, f64)>;
+
+/// Calculates gated loudness from the per-channel powers of gate blocks,
+/// using the two gating passes of ITU BS.1770.
+///
+/// The relative gate depends on the loudness of all of the blocks above the
+/// absolute gate, so it can only be applied once every block has been seen.
+/// To make that possible, each block above the absolute gate is kept as its
+/// loudness and per-channel powers. No samples are kept, so memory use grows
+/// with the number of blocks, about 10 per second for momentary gating, and
+/// not with the number of samples.
 pub struct Loudness<F, const N: usize>
 where
     F: Frame<N, Sample = f64>,
 {
-    abs_averager: CumulativeMean<F, N>,
-    abs_loud_frames: Vec<(f64, F)>,
+    abs_averager: Stats<F, N>,
+    abs_loud_frames: LoudFrames<F>,
     g_weights: F,
+    gate_config: GateConfig,
+    num_blocks: usize,
 }
 
 impl<F, const N: usize> Loudness<F, N>
@@ -19,22 +357,41 @@ where
     F: Frame<N, Sample = f64>,
 {
     pub fn new(g_weights: F) -> Self {
+        Self::with_gate_config(g_weights, GateConfig::default())
+    }
+
+    pub fn with_gate_config(g_weights: F, gate_config: GateConfig) -> Self {
         Self {
-            abs_averager: CumulativeMean::default(),
-            abs_loud_frames: Vec::new(),
+            abs_averager: Stats::new(),
+            abs_loud_frames: LoudFrames::new(),
             g_weights,
+            gate_config,
+            num_blocks: 0,
         }
     }
 
     pub fn push(&mut self, gated_powers: F) {
+        self.push_weighted(gated_powers, 1.0)
+    }
+
+    /// Adds the powers of a gate block that counts for `weight` blocks
+    /// towards the absolute and relative gated means.
+    pub fn push_weighted(&mut self, gated_powers: F, weight: f64) {
         let frame_loudness = Util::loudness(gated_powers, self.g_weights);
 
+        self.num_blocks += 1;
+
         // If the frame loudness is greater than the absolute loudness
         // threshold (i.e. it is "not silence"), save the frame and its
         // loudness.
-        if frame_loudness > ABS_LOUDNESS_THRESH {
-            self.abs_averager.advance(gated_powers);
-            self.abs_loud_frames.push((frame_loudness, gated_powers))
+        let above_absolute = frame_loudness > self.gate_config.absolute_gate.threshold();
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(block_loudness = frame_loudness, above_absolute = above_absolute, "absolute gate");
+
+        if above_absolute {
+            self.abs_averager.add_weighted(gated_powers, weight);
+            self.abs_loud_frames.push((frame_loudness, gated_powers, weight))
         }
     }
 
@@ -42,50 +399,676 @@ where
         self.abs_averager.is_empty()
     }
 
+    /// The number of gate blocks pushed so far, including silent ones.
+    pub fn num_gates_processed(&self) -> usize {
+        self.num_blocks
+    }
+
+    /// The number of gate blocks pushed so far that are above the absolute
+    /// gate.
+    pub fn num_gates_above_threshold(&self) -> usize {
+        self.abs_loud_frames.len()
+    }
+
+    /// Counts the gate blocks pushed so far, and how many of them pass each
+    /// gate. Finding the number of blocks above the relative gate requires
+    /// calculating the loudness, so this is as expensive as `calculate`.
+    pub fn diagnostics(&self) -> LoudnessDiagnostics {
+        LoudnessDiagnostics {
+            total_blocks: self.num_gates_processed(),
+            blocks_above_absolute: self.num_gates_above_threshold(),
+            blocks_above_relative: self.calculate().map_or(0, |r| r.relative_pass.num_blocks),
+        }
+    }
+
+    /// Clears all pushed blocks, keeping the allocated storage for reuse.
     pub fn reset(&mut self) {
-        *self = Self::new(self.g_weights)
+        self.abs_averager = Stats::new();
+        self.abs_loud_frames.clear();
+        self.num_blocks = 0;
     }
 
-    pub fn calculate(self) -> Option<f64> {
-        let Self { abs_averager, abs_loud_frames, g_weights, .. } = self;
+    /// Measures the integrated loudness of a signal, using momentary gating.
+    /// The sample peak of the signal is also recorded in the result.
+    pub fn measure<S>(signal: S, sample_rate: u32, g_weights: F) -> Result<Option<LoudnessResult>, Error>
+    where
+        S: Signal<N, Frame = F>,
+    {
+        let k_weighter = KWeightFilter::new(sample_rate)?;
+        let power_gater = GatedPowers::momentary(sample_rate);
 
-        println!("Num gates processed: {}", abs_averager.count());
+        let mut peak = 0.0f64;
+
+        let result = signal
+            .tee(|frame| peak = peak.max(Util::frame_peak(frame)))
+            .process(k_weighter)
+            .process_lazy(power_gater)
+            .calculate(Self::new(g_weights))
+        ;
+
+        Ok(result.map(|r| LoudnessResult {
+            max_sample_peak_dbfs: Some(Util::dbfs(peak)),
+            ..r
+        }))
+    }
+
+    /// Measures the integrated loudness of a signal, after resampling it to
+    /// 48 kHz if it is at a different sample rate.
+    pub fn measure_resampled<S>(signal: S, input_rate: u32, g_weights: F) -> Result<Option<LoudnessResult>, Error>
+    where
+        S: Signal<N, Frame = F>,
+    {
+        if input_rate == RESAMPLED_RATE {
+            Self::measure(signal, input_rate, g_weights)
+        }
+        else {
+            let resampled = SampleRateConverter::new(signal, input_rate, RESAMPLED_RATE)?;
+
+            Self::measure(resampled, RESAMPLED_RATE, g_weights)
+        }
+    }
+
+    /// Measures the integrated loudness of a signal, after adding TPDF dither
+    /// at the level of one LSB of the given bit depth. This avoids
+    /// quantization noise from inflating the measured loudness of very quiet
+    /// passages, such as fade-outs.
+    pub fn measure_with_dithering<S>(signal: S, sample_rate: u32, g_weights: F, bit_depth: u32) -> Result<Option<LoudnessResult>, Error>
+    where
+        S: Signal<N, Frame = F>,
+    {
+        let dithered_signal = TpdfDither::for_bit_depth(signal, bit_depth, DITHER_SEED);
+
+        Self::measure(dithered_signal, sample_rate, g_weights)
+    }
+
+    /// Measures both the integrated loudness and the true peak of a signal, in
+    /// a single pass over the signal.
+    pub fn measure_with_true_peak<S>(signal: S, sample_rate: u32, g_weights: F) -> Result<Option<LoudnessAndTruePeakResult>, Error>
+    where
+        S: Signal<N, Frame = F>,
+    {
+        let mut true_peak_meter = TruePeakMeter::new();
+
+        let loudness = Self::measure(
+            signal.tee(|frame| true_peak_meter.push_frame(frame)),
+            sample_rate,
+            g_weights,
+        )?;
+
+        Ok(loudness.map(|loudness| LoudnessAndTruePeakResult {
+            loudness,
+            true_peak_dbtp: true_peak_meter.max_dbtp(),
+        }))
+    }
+
+    /// Measures the integrated loudness of a signal, while also calculating
+    /// its crest factor from the sample peak and the unweighted RMS level.
+    pub fn measure_with_crest_factor<S>(signal: S, sample_rate: u32, g_weights: F) -> Result<Option<(LoudnessResult, CrestFactorResult)>, Error>
+    where
+        S: Signal<N, Frame = F>,
+    {
+        let mut peak = 0.0f64;
+        let mut sum_sq = 0.0;
+        let mut num_samples = 0usize;
+
+        let loudness = Self::measure(
+            signal.tee(|frame| {
+                peak = peak.max(Util::frame_peak(frame));
+                sum_sq += frame.channels().map(|x| x * x).sum::<f64>();
+                num_samples += N;
+            }),
+            sample_rate,
+            g_weights,
+        )?;
+
+        let loudness = match loudness {
+            Some(l) => l,
+            None => return Ok(None),
+        };
+
+        let peak_dbfs = Util::dbfs(peak);
+        let rms_dbfs = 10.0 * (sum_sq / num_samples as f64).log10();
+
+        let crest_factor = CrestFactorResult {
+            crest_factor_db: peak_dbfs - rms_dbfs,
+            peak_dbfs,
+            rms_dbfs,
+        };
+
+        Ok(Some((loudness, crest_factor)))
+    }
+
+    /// Measures the integrated loudness, loudness range, maximum true peak,
+    /// and maximum short-term loudness of a signal, in a single pass over the
+    /// signal.
+    pub fn measure_ebu_r128_full<S>(signal: S, sample_rate: u32, g_weights: F) -> Result<Option<EbuR128Report>, Error>
+    where
+        S: Signal<N, Frame = F>,
+    {
+        let mut k_weighter = KWeightFilter::new(sample_rate)?;
+
+        let mut true_peak_meter = TruePeakMeter::new();
+        let mut signal = signal.tee(|frame| true_peak_meter.push_frame(frame));
+
+        let mut momentary_gater = GatedPowers::momentary(sample_rate);
+        let mut short_term_gater = GatedPowers::custom(sample_rate, SHORT_TERM_GATE_MS, SHORT_TERM_DELTA_MS);
+        let mut lra_gater = GatedPowers::custom(sample_rate, LRA_GATE_MS, LRA_DELTA_MS);
+
+        let mut loudness = Self::new(g_weights);
+        let mut loudness_range = LoudnessRange::new();
+        let mut max_short_term_lufs = f64::NEG_INFINITY;
+
+        while let Some(frame) = signal.next() {
+            let filtered = k_weighter.process(frame);
+
+            if let Some(gated_powers) = momentary_gater.process(filtered) {
+                loudness.push(gated_powers);
+            }
+
+            if let Some(gated_powers) = short_term_gater.process(filtered) {
+                max_short_term_lufs = max_short_term_lufs.max(Util::loudness(gated_powers, g_weights));
+            }
+
+            if let Some(gated_powers) = lra_gater.process(filtered) {
+                loudness_range.push(Util::loudness(gated_powers, g_weights));
+            }
+        }
+
+        let report = loudness.calculate().map(|result| EbuR128Report {
+            integrated_lufs: result.integrated,
+            lra_lu: loudness_range.calculate().unwrap_or(0.0),
+            max_true_peak_dbtp: true_peak_meter.max_dbtp(),
+            max_short_term_lufs,
+        });
+
+        Ok(report)
+    }
+
+    /// Measures the integrated loudness of a signal, while also timing each
+    /// stage of the processing.
+    pub fn measure_with_metrics<S>(signal: S, sample_rate: u32, g_weights: F) -> Result<(Option<LoudnessResult>, ProcessingMetrics), Error>
+    where
+        S: Signal<N, Frame = F>,
+    {
+        let total_start = Instant::now();
+
+        let mut signal = signal;
+
+        let mut k_weighter = KWeightFilter::new(sample_rate)?;
+        let mut power_gater = GatedPowers::momentary(sample_rate);
+        let mut loudness = Self::new(g_weights);
+
+        // Frames are timed in chunks of one gate delta in length, so that the
+        // overhead of reading the clock does not dominate the measurements.
+        let chunk_len = power_gater.delta();
+        let mut chunk = Vec::with_capacity(chunk_len);
+
+        let mut filter_time = Duration::ZERO;
+        let mut gating_time = Duration::ZERO;
+        let mut frames_processed = 0;
+
+        loop {
+            chunk.clear();
+            chunk.extend(std::iter::from_fn(|| signal.next()).take(chunk_len));
+
+            if chunk.is_empty() {
+                break;
+            }
+
+            frames_processed += chunk.len();
+
+            let start = Instant::now();
+            for frame in chunk.iter_mut() {
+                *frame = k_weighter.process(*frame);
+            }
+            filter_time += start.elapsed();
+
+            let start = Instant::now();
+            for frame in chunk.iter() {
+                if let Some(gated_powers) = power_gater.process(*frame) {
+                    loudness.push(gated_powers);
+                }
+            }
+            gating_time += start.elapsed();
+        }
+
+        let start = Instant::now();
+        let result = loudness.calculate();
+        let stats_time = start.elapsed();
+
+        let filter_ns = filter_time.as_nanos() as f64;
+        let filter_ns_per_frame =
+            if frames_processed == 0 { 0.0 }
+            else { filter_ns / frames_processed as f64 }
+        ;
+
+        let metrics = ProcessingMetrics {
+            filter_ns_per_frame,
+            gating_ns: gating_time.as_nanos() as f64,
+            stats_ns: stats_time.as_nanos() as f64,
+            total_ns: total_start.elapsed().as_nanos() as f64,
+            frames_processed,
+        };
+
+        Ok((result, metrics))
+    }
+
+    /// Calculates the integrated loudness of a sequence of gated powers.
+    /// Returns `Error::InsufficientLoudContent` if no gate blocks were above
+    /// the absolute gate, such as when the sequence is empty or silent.
+    pub fn from_gated_powers_checked<I>(gated_powers: I, g_weights: F) -> Result<LoudnessResult, Error>
+    where
+        I: IntoIterator<Item = F>,
+    {
+        let mut loudness = Self::new(g_weights);
+
+        for block_powers in gated_powers {
+            loudness.push(block_powers);
+        }
+
+        loudness.calculate().ok_or(Error::InsufficientLoudContent)
+    }
+
+    /// Calculates the integrated loudness of a sequence of gated powers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no gate blocks were above the absolute gate. Use
+    /// `from_gated_powers_checked` if this can happen.
+    pub fn from_gated_powers<I>(gated_powers: I, g_weights: F) -> LoudnessResult
+    where
+        I: IntoIterator<Item = F>,
+    {
+        match Self::from_gated_powers_checked(gated_powers, g_weights) {
+            Ok(result) => result,
+            Err(err) => panic!("cannot calculate loudness of gated powers: {}", err),
+        }
+    }
+
+    /// Calculates the integrated loudness of a sequence of gated powers, with
+    /// each gate block weighted by its index and loudness. This allows for
+    /// variants of integrated loudness that, for example, downweight the intro
+    /// and outro of a program.
+    pub fn from_weighted_gated_powers<I, W>(gated_powers: I, g_weights: F, block_weighter: W) -> Option<LoudnessResult>
+    where
+        I: IntoIterator<Item = F>,
+        W: GateBlockWeighter,
+    {
+        let mut loudness = Self::new(g_weights);
+
+        for (block_index, block_powers) in gated_powers.into_iter().enumerate() {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!("gate_block", index = block_index).entered();
+
+            let weight = block_weighter(block_index, Util::loudness(block_powers, g_weights));
+
+            #[cfg(feature = "tracing")]
+            tracing::trace!(weight = weight, "block weight");
+
+            loudness.push_weighted(block_powers, weight);
+        }
+
+        loudness.calculate()
+    }
+
+    /// Calculates the integrated loudness of a sequence of gated powers, while
+    /// writing newline-delimited JSON to a writer. One object is written for
+    /// each gate block above the absolute gate, noting whether it also passed
+    /// the relative gate, followed by a final object with the integrated
+    /// loudness (or `null` if every block was gated out).
+    #[cfg(feature = "serde_json")]
+    pub fn stream_to_writer<I, W>(gated_powers: I, g_weights: F, writer: W) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = F>,
+        W: Write,
+    {
+        let mut writer = writer;
+
+        let mut loudness = Self::new(g_weights);
+        let abs_thresh = loudness.gate_config.absolute_gate.threshold();
+
+        // The relative gate is not known until all blocks have been seen, so
+        // the absolutely-loud blocks need to be held on to until then.
+        let mut abs_loud_blocks = Vec::new();
+
+        for (block_index, block_powers) in gated_powers.into_iter().enumerate() {
+            let block_loudness = Util::loudness(block_powers, g_weights);
+
+            if block_loudness > abs_thresh {
+                abs_loud_blocks.push((block_index, block_loudness));
+            }
+
+            loudness.push(block_powers);
+        }
+
+        let result = loudness.calculate();
+
+        let rel_thresh = result.map_or(f64::INFINITY, |r| r.relative_pass.threshold_lufs);
+
+        for (block_index, block_loudness) in abs_loud_blocks {
+            let gate = if block_loudness > rel_thresh { "relative" } else { "absolute" };
+
+            let line = serde_json::json!({
+                "block_index": block_index,
+                "lufs": block_loudness,
+                "gate": gate,
+            });
+
+            serde_json::to_writer(&mut writer, &line).map_err(std::io::Error::from)?;
+            writer.write_all(b"\n")?;
+        }
+
+        let line = serde_json::json!({
+            "type": "result",
+            "integrated_lufs": result.map(|r| r.integrated),
+        });
+
+        serde_json::to_writer(&mut writer, &line).map_err(std::io::Error::from)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Calculates the gated loudness of the blocks pushed so far. This does
+    /// not consume the blocks, so more can be pushed afterwards.
+    pub fn calculate(&self) -> Option<LoudnessResult> {
+        let Self { abs_averager, abs_loud_frames, g_weights, gate_config, .. } = self;
+        let g_weights = *g_weights;
 
         // This performs the calculation done in equation #5 in the ITU BS.1770
         // tech spec. This is the loudness of the average of the per-channel
         // power of frames that were marked as "loud" (i.e. frames with
         // loudness above the absolute loudness threshold) during the initial
         // pass.
-        let abs_avg_gated_power = abs_averager.try_current()?;
+        let abs_avg_gated_power = abs_averager.mean()?;
         let abs_loudness = Util::loudness(abs_avg_gated_power, g_weights);
-        println!("Absolute loudness: {} LKFS", abs_loudness);
+
+        let absolute_pass = GatePassStats {
+            num_blocks: abs_averager.count(),
+            mean_loudness_lufs: abs_loudness,
+            threshold_lufs: gate_config.absolute_gate.threshold(),
+        };
 
         // This performs the calculation done in equation #6 in the ITU BS.1770
         // tech spec. The relative loudness threshold is the absolute loudness
         // minus 10.0.
         let rel_loudness_thresh = abs_loudness - 10.0;
-        println!("Relative threshold: {} LKFS", rel_loudness_thresh);
 
         // This performs the calculation done in equation #7 in the ITU BS.1770
         // tech spec. From the collection of saved frames that were marked as
         // "absolutely loud", only those that exceed the relative loudness
         // threshold need to be selected and averaged.
-        let mut rel_averager = CumulativeMean::default();
+        let mut rel_averager = Stats::new();
 
-        for (frame_loudness, channel_powers) in abs_loud_frames {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            num_blocks = absolute_pass.num_blocks,
+            absolute_gated_lufs = abs_loudness,
+            relative_threshold_lufs = rel_loudness_thresh,
+            "absolute gating pass"
+        );
+
+        for &(frame_loudness, channel_powers, weight) in abs_loud_frames.iter() {
             // These frames are already known to be above the absolute loudness
             // threshold. However, for this calculation they also need to be
             // above the relative loudness threshold.
-            if frame_loudness > rel_loudness_thresh {
-                rel_averager.advance(channel_powers)
+            let above_relative = frame_loudness > rel_loudness_thresh;
+
+            #[cfg(feature = "tracing")]
+            tracing::trace!(block_loudness = frame_loudness, above_relative = above_relative, "relative gate");
+
+            if above_relative {
+                rel_averager.add_weighted(channel_powers, weight)
             }
         }
 
-        let rel_avg_gated_power = rel_averager.try_current()?;
+        let rel_avg_gated_power = rel_averager.mean()?;
         let rel_loudness = Util::loudness(rel_avg_gated_power, g_weights);
-        println!("Relative loudness: {} LKFS", rel_loudness);
 
-        Some(rel_loudness)
+        let relative_pass = GatePassStats {
+            num_blocks: rel_averager.count(),
+            mean_loudness_lufs: rel_loudness,
+            threshold_lufs: rel_loudness_thresh,
+        };
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            num_blocks = relative_pass.num_blocks,
+            integrated_lufs = rel_loudness,
+            "relative gating pass"
+        );
+
+        Some(LoudnessResult {
+            integrated: relative_pass.mean_loudness_lufs,
+            absolute_pass,
+            relative_pass,
+            max_sample_peak_dbfs: None,
+        })
+    }
+}
+
+impl<const N: usize> Loudness<[f64; N], N> {
+    /// Calculates the integrated loudness from block powers that were already
+    /// measured elsewhere, such as by a hardware codec that reports the power
+    /// of each block, skipping filtering entirely.
+    ///
+    /// Each item is the power of one gate block per channel, as the mean
+    /// square of the K-weighted samples, normalized so that full scale is
+    /// 1.0. The gates of BS.1770 assume 400 ms blocks with 75% overlap, so
+    /// powers of other block lengths give a loudness that does not follow
+    /// the spec. Returns `Error::InsufficientLoudContent` if no blocks were
+    /// above the absolute gate.
+    pub fn from_block_powers<I>(block_powers: I, channel_weights: [f64; N]) -> Result<LoudnessResult, Error>
+    where
+        I: IntoIterator<Item = [f64; N]>,
+    {
+        Self::from_gated_powers_checked(block_powers, channel_weights)
+    }
+
+    /// Calculates the loudness range (LRA) of EBU Tech 3342, in LU, from the
+    /// block powers stored in a timeline, without processing the signal
+    /// again. Tech 3342 uses 3 second blocks that overlap by 75%, so the
+    /// timeline should be made from `GatedPowerBlocks` with that config to
+    /// give a compliant result. Returns 0.0 if fewer than two blocks pass
+    /// the gates, as there is no range to speak of.
+    pub fn loudness_range_from_timeline(timeline: &LoudnessTimeline<[f64; N]>, channel_weights: [f64; N]) -> f64 {
+        let block_powers = timeline.blocks().iter().map(|block| block.power);
+
+        LoudnessRange::from_gated_powers(block_powers, channel_weights).unwrap_or(0.0)
+    }
+
+    /// Measures the integrated loudness of a signal, using momentary gating,
+    /// with a custom function to calculate the power of each gate block. This
+    /// is useful for signals where the mean square is not an appropriate
+    /// measure of power, such as companded audio. Returns
+    /// `Error::InvalidSampleRate` if the sample rate is so low that a gate
+    /// block or the step between blocks would not contain any frames.
+    pub fn from_signal_with_power_fn<S, P>(signal: S, sample_rate: u32, g_weights: [f64; N], power_fn: P) -> Result<Option<LoudnessResult>, Error>
+    where
+        S: Signal<N, Frame = [f64; N]>,
+        P: BlockPowerFn<N>,
+    {
+        let mut signal = signal;
+
+        let (gate_len, delta_len) = Gating::Momentary.frame_lens(sample_rate);
+
+        if gate_len == 0 || delta_len == 0 {
+            return Err(Error::InvalidSampleRate(sample_rate));
+        }
+
+        let mut k_weighter = KWeightFilter::new(sample_rate)?;
+        let mut loudness = Self::new(g_weights);
+
+        let mut block = VecDeque::with_capacity(gate_len + 1);
+
+        // Number of frames that have been added since the last gate block.
+        let mut since_last = 0;
+
+        while let Some(frame) = signal.next() {
+            block.push_back(k_weighter.process(frame));
+
+            if block.len() > gate_len {
+                block.pop_front();
+            }
+
+            if block.len() < gate_len {
+                continue;
+            }
+
+            // The first full block is always emitted, then every delta after.
+            if since_last % delta_len == 0 {
+                loudness.push(power_fn(block.make_contiguous()));
+                since_last = 0;
+            }
+
+            since_last += 1;
+        }
+
+        Ok(loudness.calculate())
+    }
+
+    /// Measures the integrated loudness of a signal, using momentary gating,
+    /// while also calculating the correlation matrix between its channels over
+    /// the whole signal. A value of -1.0 between two channels indicates that
+    /// they are out of phase, which can cause a misleading loudness reading.
+    pub fn measure_with_correlation<S>(signal: S, sample_rate: u32, g_weights: [f64; N]) -> Result<Option<(LoudnessResult, CorrelationMatrix<N>)>, Error>
+    where
+        S: Signal<N, Frame = [f64; N]>,
+    {
+        let mut signal = signal;
+
+        let mut k_weighter = KWeightFilter::new(sample_rate)?;
+        let mut power_gater = GatedPowers::momentary(sample_rate);
+        let mut loudness = Self::new(g_weights);
+
+        // Sums of the products of each pair of channels.
+        let mut cross_sums = [[0.0; N]; N];
+
+        while let Some(frame) = signal.next() {
+            for (i, row) in cross_sums.iter_mut().enumerate() {
+                for (j, sum) in row.iter_mut().enumerate() {
+                    *sum += frame[i] * frame[j];
+                }
+            }
+
+            if let Some(gated_powers) = power_gater.process(k_weighter.process(frame)) {
+                loudness.push(gated_powers);
+            }
+        }
+
+        let mut correlation: CorrelationMatrix<N> = [[0.0; N]; N];
+
+        for (i, row) in correlation.iter_mut().enumerate() {
+            for (j, corr) in row.iter_mut().enumerate() {
+                let norm = (cross_sums[i][i] * cross_sums[j][j]).sqrt();
+
+                // Silent channels are treated as uncorrelated.
+                if norm > 0.0 {
+                    *corr = cross_sums[i][j] / norm;
+                }
+            }
+        }
+
+        Ok(loudness.calculate().map(|result| (result, correlation)))
+    }
+}
+
+#[cfg(feature = "hound")]
+impl<const N: usize> Loudness<[f64; N], N> {
+    /// Measures the integrated loudness of audio piped into standard input,
+    /// such as from `sox input.wav -p`. See `measure_from_wav_reader`.
+    pub fn measure_from_stdin_wav(sample_rate: u32, g_weights: [f64; N]) -> Result<Option<LoudnessResult>, Error> {
+        Self::measure_from_wav_reader(std::io::stdin(), sample_rate, g_weights)
+    }
+
+    /// Measures the integrated loudness of audio read from a reader. If the
+    /// input starts with `RIFF`, it is decoded as a WAV file, and its header
+    /// takes precedence over the given sample rate. Otherwise, it is read as
+    /// raw interleaved 16-bit little-endian PCM at the given sample rate.
+    pub fn measure_from_wav_reader<R>(reader: R, sample_rate: u32, g_weights: [f64; N]) -> Result<Option<LoudnessResult>, Error>
+    where
+        R: Read,
+    {
+        let mut reader = BufReader::new(reader);
+
+        if reader.fill_buf()?.starts_with(b"RIFF") {
+            let wav_reader = hound::WavReader::new(reader)?;
+            let sample_rate = wav_reader.spec().sample_rate;
+
+            let mut frames = WavFrames::<_, N>::new(wav_reader)?;
+            let result = Self::measure(signal::from_frames(&mut frames), sample_rate, g_weights);
+
+            frames.error().cloned().map_or(result, Err)
+        }
+        else {
+            let mut frames = PcmFrames::<_, N>::new(reader, PcmFormat::S16Le);
+            let result = Self::measure(signal::from_frames(&mut frames), sample_rate, g_weights);
+
+            frames.error().cloned().map_or(result, Err)
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<F, const N: usize> Loudness<F, N>
+where
+    F: Frame<N, Sample = f64> + Send + Sync,
+{
+    /// Calculates the gated loudness of a sequence of gate block powers, with
+    /// the same result as pushing each block and then calling `calculate`.
+    /// The blocks are split into chunks that are summed in parallel, and the
+    /// partial sums are then merged. The relative gate still depends on the
+    /// result of the absolute gate, so the two passes are run one after the
+    /// other.
+    pub fn from_gated_powers_par(gated_powers: &[F], g_weights: F) -> Option<LoudnessResult> {
+        let abs_loudness_thresh = GateConfig::default().absolute_gate.threshold();
+
+        // Averages the powers of all blocks louder than a threshold.
+        let gated_averager = |loudness_thresh: f64| {
+            gated_powers
+                .par_chunks(PAR_CHUNK_LEN)
+                .map(|chunk| {
+                    let mut averager = Stats::new();
+
+                    for &block_powers in chunk {
+                        if Util::loudness(block_powers, g_weights) > loudness_thresh {
+                            averager.add(block_powers);
+                        }
+                    }
+
+                    averager
+                })
+                .reduce(Stats::new, |mut a, b| { a.merge(&b); a })
+        };
+
+        let abs_averager = gated_averager(abs_loudness_thresh);
+        let abs_loudness = Util::loudness(abs_averager.mean()?, g_weights);
+
+        let absolute_pass = GatePassStats {
+            num_blocks: abs_averager.count(),
+            mean_loudness_lufs: abs_loudness,
+            threshold_lufs: abs_loudness_thresh,
+        };
+
+        // Blocks need to be above both thresholds to be included in the
+        // relative pass.
+        let rel_loudness_thresh = abs_loudness - 10.0;
+        let rel_averager = gated_averager(rel_loudness_thresh.max(abs_loudness_thresh));
+        let rel_loudness = Util::loudness(rel_averager.mean()?, g_weights);
+
+        let relative_pass = GatePassStats {
+            num_blocks: rel_averager.count(),
+            mean_loudness_lufs: rel_loudness,
+            threshold_lufs: rel_loudness_thresh,
+        };
+
+        Some(LoudnessResult {
+            integrated: relative_pass.mean_loudness_lufs,
+            absolute_pass,
+            relative_pass,
+            max_sample_peak_dbfs: None,
+        })
     }
 }
 
@@ -94,17 +1077,608 @@ where
     F: Frame<N, Sample = f64>,
 {
     type Input = F;
-    type Output = Option<f64>;
+    type Output = Option<LoudnessResult>;
 
     fn push(&mut self, gated_powers: Self::Input) {
         self.push(gated_powers)
     }
 
     fn calculate(self) -> Self::Output {
-        self.calculate()
+        Loudness::calculate(&self)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    use sampara::signal;
+    use sampara::wavegen::{Sine, Phase};
+
+    use crate::test_util::TestUtil;
+
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn measure_with_dithering() {
+        const SAMPLE_RATE: f64 = 48000.0;
+
+        let phase = Phase::fixed_hz(SAMPLE_RATE, [997.0]);
+        let signal = phase.gen_wave(Sine).take((SAMPLE_RATE as usize) * 2);
+
+        // Dither at 16-bit has a negligible effect on a full scale sine.
+        let loudness = Loudness::measure_with_dithering(signal, SAMPLE_RATE as u32, [1.0], 16).unwrap().unwrap().integrated;
+
+        assert_abs_diff_eq!(loudness, -3.010251969611668, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn measure_resampled() {
+        for &sample_rate in [22050.0, 44100.0, 48000.0].iter() {
+            let phase = Phase::fixed_hz(sample_rate, [997.0]);
+            let signal = phase.gen_wave(Sine).take((sample_rate as usize) * 2);
+
+            let loudness = Loudness::measure_resampled(signal, sample_rate as u32, [1.0])
+                .unwrap()
+                .unwrap()
+                .integrated;
+
+            assert_abs_diff_eq!(loudness, -3.010251969611668, epsilon = 1e-2);
+        }
+
+        let phase = Phase::fixed_hz(48000.0, [997.0]);
+        let signal = phase.gen_wave(Sine).take(96000);
+
+        assert_eq!(Loudness::measure_resampled(signal, 0, [1.0]), Err(Error::InvalidSampleRate(0)));
+    }
+
+    #[test]
+    fn from_signal_with_power_fn() {
+        const SAMPLE_RATE: f64 = 48000.0;
+
+        let gen_signal = || {
+            let phase = Phase::fixed_hz(SAMPLE_RATE, [997.0, 2000.0]);
+            phase.gen_wave(Sine).take((SAMPLE_RATE as usize) * 2)
+        };
+
+        let expected = Loudness::measure(gen_signal(), SAMPLE_RATE as u32, [1.0, 1.0]).unwrap().unwrap();
+        let produced = Loudness::from_signal_with_power_fn(
+            gen_signal(), SAMPLE_RATE as u32, [1.0, 1.0], Util::mean_square,
+        ).unwrap().unwrap();
+
+        assert_eq!(expected.relative_pass.num_blocks, produced.relative_pass.num_blocks);
+        assert_abs_diff_eq!(expected.integrated, produced.integrated, epsilon = 1e-9);
+
+        // Doubling the power raises the loudness by about 3 dB.
+        let produced = Loudness::from_signal_with_power_fn(
+            gen_signal(), SAMPLE_RATE as u32, [1.0, 1.0],
+            |block: &[[f64; 2]]| {
+                let [l, r] = Util::mean_square(block);
+                [2.0 * l, 2.0 * r]
+            },
+        ).unwrap().unwrap();
+
+        assert_abs_diff_eq!(expected.integrated + 10.0 * 2.0f64.log10(), produced.integrated, epsilon = 1e-9);
+
+        // At 2 Hz, a block has a frame, but the 100 ms step between blocks
+        // does not.
+        let produced = Loudness::from_signal_with_power_fn(
+            signal::from_frames(std::iter::repeat([0.5, 0.5]).take(10)), 2, [1.0, 1.0], Util::mean_square,
+        );
+
+        assert_eq!(produced, Err(Error::InvalidSampleRate(2)));
+    }
+
+    #[test]
+    fn measure_with_correlation() {
+        const SAMPLE_RATE: f64 = 48000.0;
+
+        let frames = TestUtil::sine_997(SAMPLE_RATE as u32, (SAMPLE_RATE as usize) * 2).map(|x| [x, -x, 0.0]);
+
+        let (result, correlation): (_, CorrelationMatrix<3>) = Loudness::measure_with_correlation(
+            signal::from_frames(frames), SAMPLE_RATE as u32, [1.0, 1.0, 1.0],
+        ).unwrap().unwrap();
+
+        // The phase inversion does not affect the loudness, which is that of
+        // two full-scale 997 Hz sine channels.
+        assert_abs_diff_eq!(result.integrated, 0.0, epsilon = 1e-2);
+
+        assert_abs_diff_eq!(correlation[0][0], 1.0, epsilon = 1e-9);
+        assert_abs_diff_eq!(correlation[1][1], 1.0, epsilon = 1e-9);
+        assert_abs_diff_eq!(correlation[0][1], -1.0, epsilon = 1e-9);
+        assert_abs_diff_eq!(correlation[1][0], -1.0, epsilon = 1e-9);
+        assert_eq!(correlation[2], [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn absolute_gate_kind() {
+        // A block at -65 LUFS passes the BS.1770-4 gate, but not a legacy
+        // -60 LUFS gate.
+        let quiet = 10.0f64.powf((-65.0 + 0.691) / 10.0);
+        let loud = 10.0f64.powf((-20.0 + 0.691) / 10.0);
+
+        let calculate = |absolute_gate| {
+            let mut loudness = Loudness::with_gate_config([1.0], GateConfig { absolute_gate });
+            loudness.push([quiet]);
+            loudness.push([loud]);
+            loudness.calculate().unwrap()
+        };
+
+        let result = calculate(AbsoluteGateKind::Bs17704);
+        assert_eq!(result.absolute_pass.num_blocks, 2);
+        assert_eq!(result.absolute_pass.threshold_lufs, ABSOLUTE_LOUDNESS_THRESHOLD_BS1770_4);
+
+        let result = calculate(AbsoluteGateKind::Legacy(-60.0));
+        assert_eq!(result.absolute_pass.num_blocks, 1);
+        assert_eq!(result.absolute_pass.threshold_lufs, -60.0);
+        assert_abs_diff_eq!(result.integrated, -20.0, epsilon = 1e-9);
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn stream_to_writer() {
+        let gated_powers = [[0.5], [1.0e-5], [0.0], [0.25]];
+        let lufs = Util::lufs;
+
+        let parse = |line: &str| -> serde_json::Value { serde_json::from_str(line).unwrap() };
+
+        let mut output = Vec::new();
+        Loudness::stream_to_writer(gated_powers.iter().copied(), [1.0], &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().map(parse).collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+
+        let expected = [(0u64, lufs(0.5), "relative"), (1, lufs(1.0e-5), "absolute"), (3, lufs(0.25), "relative")];
+        for (line, &(block_index, block_lufs, gate)) in lines.iter().zip(expected.iter()) {
+            assert_eq!(line["block_index"], block_index);
+            assert_eq!(line["lufs"], block_lufs);
+            assert_eq!(line["gate"], gate);
+        }
+
+        assert_eq!(lines[3]["type"], "result");
+        assert_eq!(lines[3]["integrated_lufs"], lufs(0.375));
+
+        let mut output = Vec::new();
+        Loudness::stream_to_writer(std::iter::empty::<[f64; 1]>(), [1.0], &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.ends_with('\n'));
+
+        let line = parse(output.trim_end());
+        assert_eq!(line["type"], "result");
+        assert!(line["integrated_lufs"].is_null());
+    }
+
+    #[test]
+    fn measure_with_true_peak() {
+        const SAMPLE_RATE: f64 = 48000.0;
+
+        let gen_signal = || {
+            let phase = Phase::fixed_hz(SAMPLE_RATE, [997.0, 997.0]);
+            phase.gen_wave(Sine).take((SAMPLE_RATE as usize) * 2)
+        };
+
+        let expected = Loudness::measure(gen_signal(), SAMPLE_RATE as u32, [1.0, 1.0]).unwrap().unwrap();
+        let produced = Loudness::measure_with_true_peak(gen_signal(), SAMPLE_RATE as u32, [1.0, 1.0]).unwrap().unwrap();
+
+        assert_eq!(produced.loudness, expected);
+        assert_abs_diff_eq!(produced.true_peak_dbtp, 0.0, epsilon = 0.05);
+    }
+
+    #[cfg(feature = "hound")]
+    #[test]
+    fn measure_from_wav_reader() {
+        const SAMPLE_RATE: u32 = 48000;
+        const LEN: usize = SAMPLE_RATE as usize * 2;
+
+        let samples = TestUtil::sine_997_i16(SAMPLE_RATE, LEN);
+
+        let expected = Loudness::measure(
+            signal::from_frames(samples.iter().map(|&x| [x as f64 / 32768.0; 2])),
+            SAMPLE_RATE, [1.0, 1.0],
+        ).unwrap().unwrap();
+
+        // Raw PCM.
+        let raw = samples.iter().flat_map(|x| [x.to_le_bytes(), x.to_le_bytes()]).flatten().collect::<Vec<_>>();
+        let produced = Loudness::measure_from_wav_reader(&raw[..], SAMPLE_RATE, [1.0, 1.0]).unwrap().unwrap();
+        assert_eq!(produced, expected);
+
+        // WAV, with a sample rate in the header that overrides the given one.
+        let wav = TestUtil::sine_997_wav(SAMPLE_RATE, LEN, &[1, 1]);
+
+        let produced = Loudness::measure_from_wav_reader(&wav[..], 44100, [1.0, 1.0]).unwrap().unwrap();
+        assert_eq!(produced, expected);
+
+        let produced = Loudness::<[f64; 1], 1>::measure_from_wav_reader(&wav[..], 44100, [1.0]);
+        assert_eq!(produced, Err(Error::InvalidChannelCount(2)));
+    }
+
+    #[test]
+    fn measure_ebu_r128_full() {
+        const SAMPLE_RATE: f64 = 48000.0;
+
+        // A steady sine has no loudness range, and its short-term loudness is
+        // the same as its integrated loudness.
+        let phase = Phase::fixed_hz(SAMPLE_RATE, [997.0, 997.0]);
+        let signal = phase.gen_wave(Sine).take((SAMPLE_RATE as usize) * 10);
+
+        let report = Loudness::measure_ebu_r128_full(signal, SAMPLE_RATE as u32, [1.0, 1.0]).unwrap().unwrap();
+
+        assert_abs_diff_eq!(report.integrated_lufs, 0.0, epsilon = 1e-2);
+        assert_abs_diff_eq!(report.lra_lu, 0.0, epsilon = 1e-2);
+        assert_abs_diff_eq!(report.max_true_peak_dbtp, 0.0, epsilon = 0.05);
+        assert_abs_diff_eq!(report.max_short_term_lufs, report.integrated_lufs, epsilon = 1e-2);
+
+        // The loudness range uses the same blocks as `LoudnessRange`.
+        let frames = TestUtil::alternating_997(SAMPLE_RATE as u32, SAMPLE_RATE as usize * 30);
+
+        let report = Loudness::measure_ebu_r128_full(signal::from_frames(frames.iter().copied()), SAMPLE_RATE as u32, [1.0, 1.0])
+            .unwrap().unwrap()
+        ;
+        let expected = LoudnessRange::measure(signal::from_frames(frames.iter().copied()), SAMPLE_RATE as u32, [1.0, 1.0])
+            .unwrap().unwrap()
+        ;
+
+        assert_abs_diff_eq!(report.lra_lu, expected, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn ebu_r128_report_compliance() {
+        let report = |integrated_lufs, max_true_peak_dbtp| EbuR128Report {
+            integrated_lufs,
+            lra_lu: 5.25,
+            max_true_peak_dbtp,
+            max_short_term_lufs: integrated_lufs + 4.0,
+        };
+
+        assert!(report(-23.0, -1.0).is_compliant_for_broadcast());
+        assert!(report(-22.0, -3.0).is_compliant_for_broadcast());
+        assert!(!report(-21.5, -3.0).is_compliant_for_broadcast());
+        assert!(!report(-23.0, -0.5).is_compliant_for_broadcast());
+        assert!(!report(-23.0, -1.0).is_compliant_for_streaming());
+
+        assert!(report(-14.0, -1.0).is_compliant_for_streaming());
+        assert!(!report(-14.0, 0.0).is_compliant_for_streaming());
+        assert!(!report(-16.0, -2.0).is_compliant_for_streaming());
+
+        assert_eq!(
+            report(-23.04, -1.5).compliance_summary(),
+            "\
+                Integrated loudness: -23.0 LUFS\n\
+                Loudness range: 5.2 LU\n\
+                Maximum true peak: -1.5 dBTP\n\
+                Maximum short-term loudness: -19.0 LUFS\n\
+                Broadcast (EBU R128): PASS\n\
+                Streaming: FAIL\n\
+            ",
+        );
+    }
+
+    #[test]
+    fn loudness_result_display() {
+        let result = LoudnessResult {
+            integrated: -23.06,
+            absolute_pass: GatePassStats {
+                num_blocks: 10,
+                mean_loudness_lufs: -24.5,
+                threshold_lufs: -70.0,
+            },
+            relative_pass: GatePassStats {
+                num_blocks: 8,
+                mean_loudness_lufs: -23.06,
+                threshold_lufs: -34.5,
+            },
+            max_sample_peak_dbfs: None,
+        };
+
+        assert_eq!(
+            result.to_string(),
+            "Integrated: -23.1 LUFS | Absolute gate: -24.5 LUFS | Relative threshold: -34.5 LUFS",
+        );
+        assert_eq!(
+            format!("{:.2}", result),
+            "Integrated: -23.06 LUFS | Absolute gate: -24.50 LUFS | Relative threshold: -34.50 LUFS",
+        );
+        assert_eq!(
+            format!("{:e}", result),
+            "Integrated: -2.306e1 LUFS | Absolute gate: -2.45e1 LUFS | Relative threshold: -3.45e1 LUFS",
+        );
+        assert_eq!(
+            format!("{:.0e}", result),
+            "Integrated: -2e1 LUFS | Absolute gate: -2e1 LUFS | Relative threshold: -3e1 LUFS",
+        );
+    }
+
+    #[test]
+    fn from_gated_powers_checked() {
+        let gated_powers = [[0.5], [0.25], [0.0], [1e-9]];
+
+        let expected = {
+            let mut loudness = Loudness::new([1.0]);
+            for &p in gated_powers.iter() {
+                loudness.push(p);
+            }
+            loudness.calculate().unwrap()
+        };
+
+        assert_eq!(Loudness::from_gated_powers_checked(gated_powers.iter().copied(), [1.0]), Ok(expected));
+        assert_eq!(Loudness::from_gated_powers(gated_powers.iter().copied(), [1.0]), expected);
+
+        // Both empty and silent sequences have no blocks above the absolute gate.
+        assert_eq!(
+            Loudness::from_gated_powers_checked(std::iter::empty::<[f64; 1]>(), [1.0]),
+            Err(Error::InsufficientLoudContent),
+        );
+        assert_eq!(
+            Loudness::from_gated_powers_checked(gated_powers[2..].iter().copied(), [1.0]),
+            Err(Error::InsufficientLoudContent),
+        );
+    }
+
+    #[test]
+    fn from_block_powers() {
+        const SAMPLE_RATE: f64 = 48000.0;
+
+        // Powers measured by an external system give the same result as
+        // measuring the signal.
+        let phase = Phase::fixed_hz(SAMPLE_RATE, [997.0, 0.0]);
+        let signal = phase.gen_wave(Sine).take((SAMPLE_RATE as usize) * 2);
+
+        let block_powers = signal
+            .process(KWeightFilter::new(SAMPLE_RATE as u32).unwrap())
+            .process_lazy(GatedPowers::momentary(SAMPLE_RATE as u32))
+            .into_iter()
+            .collect::<Vec<_>>()
+        ;
+
+        let expected = Loudness::measure(
+            Phase::fixed_hz(SAMPLE_RATE, [997.0, 0.0]).gen_wave(Sine).take((SAMPLE_RATE as usize) * 2),
+            SAMPLE_RATE as u32, [1.0, 1.0],
+        ).unwrap().unwrap();
+
+        let produced = Loudness::from_block_powers(block_powers, [1.0, 1.0]).unwrap();
+        assert_eq!(produced.integrated, expected.integrated);
+
+        assert_eq!(Loudness::from_block_powers(vec![[0.0, 0.0]; 10], [1.0, 1.0]), Err(Error::InsufficientLoudContent));
+    }
+
+    #[test]
+    fn loudness_range_from_timeline() {
+        use crate::filter::FilteredSamples;
+        use crate::gating::{GatedPowerBlocks, GatingConfig};
+
+        const SAMPLE_RATE: u32 = 48000;
+
+        let frames = TestUtil::alternating_997(SAMPLE_RATE, SAMPLE_RATE as usize * 30);
+
+        let short_term = GatingConfig {
+            block_duration_ms: LRA_GATE_MS as u32,
+            overlap_fraction: 1.0 - LRA_DELTA_MS as f64 / LRA_GATE_MS as f64,
+        };
+
+        let filtered = FilteredSamples::new(frames.iter().copied(), SAMPLE_RATE).unwrap();
+        let blocks = GatedPowerBlocks::with_config(filtered, SAMPLE_RATE, short_term).unwrap();
+        let timeline = LoudnessTimeline::from_blocks(blocks.with_timestamps([1.0, 1.0]), SAMPLE_RATE);
+
+        let expected = LoudnessRange::measure(signal::from_frames(frames.iter().copied()), SAMPLE_RATE, [1.0, 1.0])
+            .unwrap().unwrap()
+        ;
+        let produced = Loudness::loudness_range_from_timeline(&timeline, [1.0, 1.0]);
+
+        assert_abs_diff_eq!(produced, expected, epsilon = 1e-9);
+        assert!(produced > 15.0);
+
+        assert_eq!(Loudness::loudness_range_from_timeline(&LoudnessTimeline::new(SAMPLE_RATE), [1.0, 1.0]), 0.0);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn loudness_range_from_timeline_is_non_negative(
+            powers in proptest::collection::vec(0.0f64..1.0, 0..200),
+        ) {
+            use crate::gating::TimestampedGateBlock;
+
+            let blocks = powers.iter().enumerate().map(|(k, &power)| TimestampedGateBlock {
+                start_sample: k as u64 * 36000,
+                end_sample: k as u64 * 36000 + 144000,
+                power: [power],
+                loudness: Util::loudness([power], [1.0]),
+                passed_absolute: false,
+                passed_relative: false,
+            });
+            let timeline = LoudnessTimeline::from_blocks(blocks, 48000);
+
+            proptest::prop_assert!(Loudness::loudness_range_from_timeline(&timeline, [1.0]) >= 0.0);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot calculate loudness of gated powers")]
+    fn from_gated_powers_silent() {
+        Loudness::from_gated_powers([[0.0], [0.0]], [1.0]);
+    }
+
+    #[test]
+    fn from_weighted_gated_powers() {
+        let gated_powers = [[0.5], [0.25], [0.125], [0.25]];
+
+        let expected = {
+            let mut loudness = Loudness::new([1.0]);
+            for &p in gated_powers.iter() {
+                loudness.push(p);
+            }
+            loudness.calculate().unwrap()
+        };
+
+        let produced = Loudness::from_weighted_gated_powers(gated_powers.iter().copied(), [1.0], |_, _| 1.0).unwrap();
+        assert_eq!(produced.relative_pass.num_blocks, expected.relative_pass.num_blocks);
+        assert_abs_diff_eq!(produced.integrated, expected.integrated, epsilon = 1e-12);
+
+        // Ignoring the first and last blocks leaves the middle two.
+        let produced = Loudness::from_weighted_gated_powers(
+            gated_powers.iter().copied(), [1.0],
+            |i, _| if i == 0 || i == gated_powers.len() - 1 { 0.0 } else { 1.0 },
+        ).unwrap();
+        assert_abs_diff_eq!(produced.integrated, Util::lufs(0.1875), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn measure_with_crest_factor() {
+        const SAMPLE_RATE: f64 = 48000.0;
+
+        let phase = Phase::fixed_hz(SAMPLE_RATE, [997.0, 997.0]);
+        let signal = phase.gen_wave(Sine).take((SAMPLE_RATE as usize) * 2);
+
+        let (loudness, crest_factor) = Loudness::measure_with_crest_factor(signal, SAMPLE_RATE as u32, [1.0, 1.0]).unwrap().unwrap();
+
+        // A full-scale sine has a crest factor of sqrt(2), or about 3 dB.
+        assert_abs_diff_eq!(loudness.integrated, 0.0, epsilon = 1e-2);
+        assert_abs_diff_eq!(crest_factor.peak_dbfs, 0.0, epsilon = 1e-3);
+        assert_abs_diff_eq!(crest_factor.rms_dbfs, -3.0103, epsilon = 1e-3);
+        assert_abs_diff_eq!(crest_factor.crest_factor_db, 3.0103, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn plr() {
+        const SAMPLE_RATE: f64 = 48000.0;
+
+        let phase = Phase::fixed_hz(SAMPLE_RATE, [997.0, 997.0]);
+        let signal = phase.gen_wave(Sine).take((SAMPLE_RATE as usize) * 2);
+
+        let result = Loudness::measure(signal, SAMPLE_RATE as u32, [1.0, 1.0]).unwrap().unwrap();
+
+        assert_abs_diff_eq!(result.max_sample_peak_dbfs.unwrap(), 0.0, epsilon = 1e-3);
+        assert_abs_diff_eq!(result.plr().unwrap(), -result.integrated, epsilon = 1e-3);
+        assert_abs_diff_eq!(result.plr_with_peak(-1.0), -1.0 - result.integrated);
+
+        let mut loudness = Loudness::new([1.0]);
+        loudness.push([0.5]);
+        assert_eq!(loudness.calculate().unwrap().plr(), None);
+    }
+
+    #[test]
+    fn normalization_gain_db() {
+        const SAMPLE_RATE: f64 = 48000.0;
+        const TARGET: f64 = -23.0;
+
+        let signal = || Phase::fixed_hz(SAMPLE_RATE, [997.0, 997.0]).gen_wave(Sine).take((SAMPLE_RATE as usize) * 2);
+
+        let measured = Loudness::measure(signal(), SAMPLE_RATE as u32, [1.0, 1.0]).unwrap().unwrap();
+        let gain_db = measured.normalization_gain_db(TARGET);
+
+        assert_abs_diff_eq!(gain_db, TARGET - measured.integrated);
+
+        // Applying the gain brings the signal to the target loudness.
+        let normalized = Loudness::measure(signal().gain_db(gain_db), SAMPLE_RATE as u32, [1.0, 1.0]).unwrap().unwrap();
+
+        assert_abs_diff_eq!(normalized.integrated, TARGET, epsilon = 1e-9);
+        assert_abs_diff_eq!(normalized.max_sample_peak_dbfs.unwrap(), measured.max_sample_peak_dbfs.unwrap() + gain_db, epsilon = 1e-9);
+
+        assert_eq!(Util::apply_gain_db([0.5, -0.25], 0.0), [0.5, -0.25]);
+        assert_abs_diff_eq!(Util::apply_gain_db([0.5], -20.0)[0], 0.05, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn measure_with_metrics() {
+        const SAMPLE_RATE: f64 = 48000.0;
+
+        let phase = Phase::fixed_hz(SAMPLE_RATE, [997.0]);
+        let signal = phase.gen_wave(Sine).take((SAMPLE_RATE as usize) * 2);
+
+        let (loudness, metrics) = Loudness::measure_with_metrics(signal, SAMPLE_RATE as u32, [1.0]).unwrap();
+
+        assert_abs_diff_eq!(loudness.unwrap().integrated, -3.010251969611668, epsilon = 1e-9);
+
+        assert_eq!(metrics.frames_processed, (SAMPLE_RATE as usize) * 2);
+        assert!(metrics.filter_ns_per_frame >= 0.0);
+        assert!(metrics.total_ns >= metrics.gating_ns + metrics.stats_ns);
+    }
+
+    #[test]
+    fn gate_pass_stats() {
+        let mut loudness = Loudness::new([1.0]);
+
+        // Silent blocks are below the absolute threshold.
+        for _ in 0..4 {
+            loudness.push([0.0]);
+        }
+
+        // Quiet blocks pass the absolute threshold, but not the relative one.
+        for _ in 0..2 {
+            loudness.push([1.0e-4]);
+        }
+
+        // Loud blocks pass both thresholds.
+        for _ in 0..8 {
+            loudness.push([0.5]);
+        }
+
+        let result = loudness.calculate().unwrap();
+
+        let abs_mean_power = (2.0 * 1.0e-4 + 8.0 * 0.5) / 10.0;
+
+        assert_eq!(result.absolute_pass.num_blocks, 10);
+        assert_abs_diff_eq!(result.absolute_pass.threshold_lufs, -70.0);
+        assert_abs_diff_eq!(result.absolute_pass.mean_loudness_lufs, Util::lufs(abs_mean_power), epsilon = 1e-9);
+
+        assert_eq!(result.relative_pass.num_blocks, 8);
+        assert_abs_diff_eq!(result.relative_pass.threshold_lufs, Util::lufs(abs_mean_power) - 10.0, epsilon = 1e-9);
+        assert_abs_diff_eq!(result.relative_pass.mean_loudness_lufs, Util::lufs(0.5), epsilon = 1e-9);
+
+        assert_abs_diff_eq!(result.integrated, result.relative_pass.mean_loudness_lufs);
+        assert_abs_diff_eq!(result.absolute_gated(), Util::lufs(abs_mean_power), epsilon = 1e-9);
+        assert_abs_diff_eq!(result.relative_threshold(), result.absolute_gated() - 10.0);
+
+        assert_eq!(Loudness::<[f64; 1], 1>::new([1.0]).calculate(), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let mut loudness = Loudness::new([1.0]);
+
+        for &power in [0.0, 1.0e-4, 0.5, 0.3, 0.123456789].iter() {
+            loudness.push([power]);
+        }
+
+        let result = loudness.calculate().unwrap();
+
+        let json = serde_json::to_string(&result).unwrap();
+        let produced: LoudnessResult = serde_json::from_str(&json).unwrap();
+
+        let to_bits = |r: &LoudnessResult| (
+            r.integrated.to_bits(),
+            [r.absolute_pass, r.relative_pass].map(|p| (p.num_blocks, p.mean_loudness_lufs.to_bits(), p.threshold_lufs.to_bits())),
+            r.max_sample_peak_dbfs.map(f64::to_bits),
+        );
+
+        assert_eq!(to_bits(&produced), to_bits(&result));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn from_gated_powers_par() {
+        // Enough blocks to be split over several chunks, with some below each
+        // of the gates.
+        let blocks = (0..5000).map(|i| {
+            let x = (i as f64 * 0.731).sin().abs();
+            [x * x * 0.5, x * 1.0e-8]
+        }).collect::<Vec<_>>();
+
+        let mut loudness = Loudness::new([1.0, 1.41]);
+        for &block in blocks.iter() {
+            loudness.push(block);
+        }
+
+        let expected = loudness.calculate().unwrap();
+        let produced = Loudness::from_gated_powers_par(&blocks, [1.0, 1.41]).unwrap();
+
+        assert_eq!(produced.absolute_pass.num_blocks, expected.absolute_pass.num_blocks);
+        assert_eq!(produced.relative_pass.num_blocks, expected.relative_pass.num_blocks);
+        assert_abs_diff_eq!(produced.integrated, expected.integrated, epsilon = 1e-9);
+        assert_abs_diff_eq!(produced.relative_threshold(), expected.relative_threshold(), epsilon = 1e-9);
+
+        assert_eq!(Loudness::from_gated_powers_par(&[[0.0, 0.0]], [1.0, 1.0]), None);
+    }
 }
@@ -51,7 +51,7 @@ where
     F: Frame<N, Sample = f64>,
 {
     type Input = F;
-    type Output = Option<f64>;
+    type Output = Option<LoudnessResult>;
 
     fn push(&mut self, input: Self::Input) {
         if let Some(gp) = self.gated_powers.process(input) {
@@ -0,0 +1,370 @@
+//! Slicing of K-weighted frames into overlapping gating blocks, according to
+//! BS.1770-4.
+
+use std::collections::VecDeque;
+
+use sampara::Frame;
+
+use crate::error::Error;
+use crate::filter::FilteredSamples;
+use crate::gated_loudness::{Loudness, ABSOLUTE_LOUDNESS_THRESHOLD_BS1770_4};
+use crate::meter::WindowedPowers;
+use crate::util::Util;
+
+/// The length and overlap of gating blocks.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GatingConfig {
+    /// The length of each block, in milliseconds.
+    pub block_duration_ms: u32,
+    /// The fraction of each block that overlaps with the next one, in the
+    /// range `[0.0, 1.0)`.
+    pub overlap_fraction: f64,
+}
+
+impl GatingConfig {
+    /// The gating blocks used for integrated loudness in BS.1770-4, which are
+    /// 400 ms long and overlap by 75%.
+    pub const fn bs1770() -> Self {
+        Self {
+            block_duration_ms: 400,
+            overlap_fraction: 0.75,
+        }
+    }
+
+    /// Calculates the block length and the step between consecutive blocks,
+    /// in frames, for a given sample rate.
+    pub fn frame_lens(&self, sample_rate: u32) -> Result<(usize, usize), Error> {
+        if !(0.0..1.0).contains(&self.overlap_fraction) {
+            return Err(Error::InvalidOverlap(self.overlap_fraction));
+        }
+
+        let block_len = Util::ms_to_samples(self.block_duration_ms as u64, sample_rate) as usize;
+
+        if block_len == 0 {
+            return Err(Error::InvalidBlockDuration(self.block_duration_ms));
+        }
+
+        // Always step by at least one frame, even for very short blocks.
+        let step_len = ((block_len as f64 * (1.0 - self.overlap_fraction)).round() as usize).max(1);
+
+        Ok((block_len, step_len))
+    }
+}
+
+impl Default for GatingConfig {
+    fn default() -> Self {
+        Self::bs1770()
+    }
+}
+
+/// Iterator adapter that yields the per-channel mean square power of each
+/// gating block of a K-weighted signal. By default, blocks are 400 ms long
+/// and overlap by 75%, so the first block is yielded after 400 ms, and then
+/// one block for every 100 ms after that.
+pub struct GatedPowerBlocks<I, const N: usize>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    powers: WindowedPowers<FilteredSamples<I, N>, N>,
+    block_len: usize,
+    step_len: usize,
+}
+
+impl<I, const N: usize> GatedPowerBlocks<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    /// Creates blocks with the BS.1770-4 length and overlap. Returns
+    /// `Error::InvalidBlockDuration` if the sample rate is so low that a block
+    /// would not contain any frames.
+    pub fn new(samples: FilteredSamples<I, N>, sample_rate: u32) -> Result<Self, Error> {
+        Self::with_config(samples, sample_rate, GatingConfig::bs1770())
+    }
+
+    /// Creates blocks with a custom length and overlap, such as the ones used
+    /// for EBU R128 short-term loudness.
+    pub fn with_config(samples: FilteredSamples<I, N>, sample_rate: u32, config: GatingConfig) -> Result<Self, Error> {
+        let (block_len, step_len) = config.frame_lens(sample_rate)?;
+
+        Ok(Self {
+            powers: WindowedPowers::new(samples, block_len, step_len),
+            block_len,
+            step_len,
+        })
+    }
+
+    /// Tags each block with its position in the signal, its loudness, and
+    /// whether it passed each of the BS.1770 gates, for plotting a loudness
+    /// timeline. The relative gate depends on the loudness of every block, so
+    /// the first call to `next` reads all of the remaining blocks.
+    pub fn with_timestamps(self, g_weights: I::Item) -> TimestampedGateBlocks<I, N> {
+        TimestampedGateBlocks {
+            blocks: Some(self),
+            g_weights,
+            gated: VecDeque::new(),
+        }
+    }
+}
+
+impl<I, const N: usize> Iterator for GatedPowerBlocks<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.powers.next()
+    }
+
+    /// If the inner iterator knows exactly how many frames are left, this
+    /// is exact: for `n` frames, the first block is yielded after `block_len`
+    /// frames, and then one more block for every `step_len` frames after
+    /// that, giving `(n - block_len) / step_len + 1` blocks.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.powers.size_hint()
+    }
+}
+
+impl<I, const N: usize> ExactSizeIterator for GatedPowerBlocks<I, N>
+where
+    I: ExactSizeIterator,
+    I::Item: Frame<N, Sample = f64>,
+{}
+
+/// A gating block, along with where it lies in the signal and how it was
+/// gated.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TimestampedGateBlock<F> {
+    /// The index of the first frame of the block, from the start of the
+    /// signal.
+    pub start_sample: u64,
+    /// The index of the frame just after the end of the block.
+    pub end_sample: u64,
+    /// The per-channel mean square power of the block.
+    pub power: F,
+    /// The loudness of the block, in LUFS.
+    pub loudness: f64,
+    /// Whether the block is louder than the absolute gate at -70 LUFS.
+    pub passed_absolute: bool,
+    /// Whether the block passed both the absolute and relative gates, and so
+    /// counts towards the integrated loudness.
+    pub passed_relative: bool,
+}
+
+/// Iterator adapter that yields the gating blocks of a `GatedPowerBlocks`,
+/// tagged with their positions and gating results. See
+/// `GatedPowerBlocks::with_timestamps`.
+pub struct TimestampedGateBlocks<I, const N: usize>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    // The blocks that have not been read yet, which are all read at once.
+    blocks: Option<GatedPowerBlocks<I, N>>,
+    g_weights: I::Item,
+    gated: VecDeque<TimestampedGateBlock<I::Item>>,
+}
+
+impl<I, const N: usize> TimestampedGateBlocks<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    fn gate(&mut self, blocks: GatedPowerBlocks<I, N>) {
+        let (block_len, step_len) = (blocks.block_len as u64, blocks.step_len as u64);
+        let powers = blocks.collect::<Vec<_>>();
+
+        let mut loudness = Loudness::new(self.g_weights);
+        for &power in powers.iter() {
+            loudness.push(power);
+        }
+
+        let relative_threshold = loudness.calculate().map(|r| r.relative_threshold());
+
+        for (k, power) in powers.into_iter().enumerate() {
+            let start_sample = k as u64 * step_len;
+            let block_loudness = Util::loudness(power, self.g_weights);
+
+            let passed_absolute = block_loudness > ABSOLUTE_LOUDNESS_THRESHOLD_BS1770_4;
+            let passed_relative = passed_absolute && matches!(relative_threshold, Some(t) if block_loudness > t);
+
+            self.gated.push_back(TimestampedGateBlock {
+                start_sample,
+                end_sample: start_sample + block_len,
+                power,
+                loudness: block_loudness,
+                passed_absolute,
+                passed_relative,
+            });
+        }
+    }
+}
+
+impl<I, const N: usize> Iterator for TimestampedGateBlocks<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    type Item = TimestampedGateBlock<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(blocks) = self.blocks.take() {
+            self.gate(blocks);
+        }
+
+        self.gated.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.blocks {
+            Some(blocks) => blocks.size_hint(),
+            None => (self.gated.len(), Some(self.gated.len())),
+        }
+    }
+}
+
+impl<I, const N: usize> ExactSizeIterator for TimestampedGateBlocks<I, N>
+where
+    I: ExactSizeIterator,
+    I::Item: Frame<N, Sample = f64>,
+{}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::gated_loudness::Loudness;
+    use crate::test_util::TestUtil;
+
+    use approx::assert_abs_diff_eq;
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    #[test]
+    fn gated_power_blocks() {
+        let sine = TestUtil::sine_997(SAMPLE_RATE, SAMPLE_RATE as usize * 2).map(|x| [x, x]);
+
+        let filtered = FilteredSamples::new(sine, SAMPLE_RATE).unwrap();
+        let blocks = GatedPowerBlocks::new(filtered, SAMPLE_RATE).unwrap();
+
+        // One block at 400 ms, and then one for each 100 ms after.
+        assert_eq!(blocks.size_hint(), (17, Some(17)));
+
+        let mut loudness = Loudness::new([1.0, 1.0]);
+        let mut num_blocks = 0;
+
+        for block_powers in blocks {
+            loudness.push(block_powers);
+            num_blocks += 1;
+        }
+
+        assert_eq!(num_blocks, 17);
+        assert_abs_diff_eq!(loudness.calculate().unwrap().integrated, 0.0, epsilon = 1e-2);
+
+        // The sample rate of the blocks is separate from that of the filter,
+        // and at a rate this low a block has no frames.
+        let filtered = FilteredSamples::new(std::iter::empty::<[f64; 1]>(), SAMPLE_RATE).unwrap();
+        assert!(matches!(GatedPowerBlocks::new(filtered, 1), Err(Error::InvalidBlockDuration(400))));
+    }
+
+    #[test]
+    fn exact_size() {
+        let samples = |len| FilteredSamples::new(std::iter::repeat([0.5]).take(len), SAMPLE_RATE).unwrap();
+
+        // 19200 frames per block, and 4800 frames per step.
+        for &(len, expected) in [(0, 0), (19199, 0), (19200, 1), (23999, 1), (24000, 2), (96000, 17)].iter() {
+            let mut blocks = GatedPowerBlocks::new(samples(len), SAMPLE_RATE).unwrap();
+            assert_eq!(blocks.len(), expected);
+
+            // The length counts down as blocks are taken.
+            if expected > 0 {
+                blocks.next();
+                assert_eq!(blocks.len(), expected - 1);
+            }
+
+            let collected = blocks.collect::<Vec<_>>();
+            assert_eq!(collected.len(), expected.saturating_sub(1));
+        }
+    }
+
+    #[test]
+    fn with_timestamps() {
+        // A loud second, a second that is quiet enough to be gated out by
+        // the relative gate, and then a second of silence.
+        let signal = TestUtil::sine_997(SAMPLE_RATE, SAMPLE_RATE as usize * 3).enumerate().map(|(i, x)| {
+            let amplitude = [0.5, 0.01, 0.0][i / SAMPLE_RATE as usize];
+            [amplitude * x]
+        }).collect::<Vec<_>>();
+
+        let blocks = || GatedPowerBlocks::new(FilteredSamples::new(signal.iter().copied(), SAMPLE_RATE).unwrap(), SAMPLE_RATE).unwrap();
+
+        let timestamped = blocks().with_timestamps([1.0]);
+        assert_eq!(timestamped.len(), 27);
+
+        let timestamped = timestamped.collect::<Vec<_>>();
+        let powers = blocks().collect::<Vec<_>>();
+
+        for (k, (block, &power)) in timestamped.iter().zip(powers.iter()).enumerate() {
+            assert_eq!(block.start_sample, k as u64 * 4800);
+            assert_eq!(block.end_sample, k as u64 * 4800 + 19200);
+            assert_eq!(block.power, power);
+            assert_eq!(block.loudness, Util::loudness(power, [1.0]));
+            assert!(block.passed_absolute || !block.passed_relative);
+        }
+
+        // The loud blocks pass both gates, the quiet ones only the absolute
+        // gate, and the silent ones neither.
+        let first = timestamped.first().unwrap();
+        assert!(first.passed_absolute && first.passed_relative);
+
+        let quiet = timestamped.iter().find(|b| b.start_sample == SAMPLE_RATE as u64 + 4800).unwrap();
+        assert!(quiet.passed_absolute && !quiet.passed_relative);
+
+        let last = timestamped.last().unwrap();
+        assert!(!last.passed_absolute && !last.passed_relative);
+
+        // The blocks that passed both gates are the ones counted by the
+        // integrated loudness.
+        let mut loudness = Loudness::new([1.0]);
+        for &power in powers.iter() {
+            loudness.push(power);
+        }
+
+        let num_passed = timestamped.iter().filter(|b| b.passed_relative).count();
+        assert_eq!(num_passed, loudness.calculate().unwrap().relative_pass.num_blocks);
+    }
+
+    #[test]
+    fn with_config() {
+        let samples = || FilteredSamples::new(std::iter::repeat([0.5]).take(SAMPLE_RATE as usize * 10), SAMPLE_RATE).unwrap();
+
+        // Short-term blocks are 3 seconds long, and yielded once every second.
+        let short_term = GatingConfig { block_duration_ms: 3000, overlap_fraction: 2.0 / 3.0 };
+        let blocks = GatedPowerBlocks::with_config(samples(), SAMPLE_RATE, short_term).unwrap();
+        assert_eq!(blocks.count(), 8);
+
+        // Blocks that do not overlap.
+        let disjoint = GatingConfig { block_duration_ms: 1000, overlap_fraction: 0.0 };
+        let blocks = GatedPowerBlocks::with_config(samples(), SAMPLE_RATE, disjoint).unwrap();
+        assert_eq!(blocks.count(), 10);
+
+        assert_eq!(GatingConfig::default().frame_lens(SAMPLE_RATE), Ok((19200, 4800)));
+
+        for &overlap_fraction in [1.0, -0.25, f64::NAN].iter() {
+            let config = GatingConfig { block_duration_ms: 400, overlap_fraction };
+            assert!(matches!(
+                GatedPowerBlocks::with_config(samples(), SAMPLE_RATE, config),
+                Err(Error::InvalidOverlap(_)),
+            ));
+        }
+
+        let config = GatingConfig { block_duration_ms: 0, overlap_fraction: 0.75 };
+        assert!(matches!(
+            GatedPowerBlocks::with_config(samples(), SAMPLE_RATE, config),
+            Err(Error::InvalidBlockDuration(0)),
+        ));
+    }
+}
@@ -1,17 +1,119 @@
-#![feature(array_methods, array_zip, bool_to_option, box_into_inner, option_result_contains)]
-
+//! Loudness measurement according to ITU-R BS.1770 and EBU R128.
+//!
+//! The crate can be built without the standard library by disabling default
+//! features, in which case float math is provided by `libm`. What is
+//! available depends on the enabled features:
+//!
+//! * `default-features = false`: the filter core (`Coefficients`,
+//!   `FilterDesigner`, `FilterPass`, `FilterPassDf1`, `KWeightFilter`,
+//!   `AWeightedFilter`, and the `FilteredSamples` and `ZWeightedSamples`
+//!   adapters), `ChannelWeights`, `Stats`, `RunningPeak`, `SignalExt`,
+//!   `Util`, the `InterleavedFrames` and `PlanarFrames` buffer adapters, and
+//!   the `LoudnessUnit` and `LoudnessValue` types.
+//! * `default-features = false` plus `alloc`: all of the above, plus the
+//!   Butterworth, zero-phase and zero-pole-gain helpers of `KWeightFilter`,
+//!   the ITU-R 468 filter, and the true peak meter.
+//! * `std` (default): everything, including gated loudness, loudness range,
+//!   measurements, album loudness, pipelines, loudness timelines, ATSC A/85
+//!   and ReplayGain policies, resampling, dithering, and reading raw PCM
+//!   audio with `PcmFrames`.
+//!
+//! Gated loudness only needs an allocator in principle, but `GatedPowers` and
+//! `Loudness` are still part of the `std` tier rather than `alloc`, as the
+//! `gated_loudness` module also holds the I/O and timing helpers.
+//!
+//! The optional `simd` feature (which implies `std`) adds `filter_simd`, a
+//! fast path for filtering mono signals on x86-64 CPUs with AVX and FMA.
+//!
+//! The optional `async` feature (which also implies `std`) adds `stream`,
+//! with K-weighting and loudness measurement for `futures_core::Stream`s of
+//! frames.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![feature(array_methods, array_zip, bool_to_option, box_into_inner, const_fn_floating_point_arithmetic, option_result_contains)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod album;
+#[cfg(feature = "std")]
+pub mod atsc;
+pub mod channel;
+#[cfg(feature = "std")]
+pub mod dither;
+pub mod error;
 pub mod filter;
+#[cfg(feature = "simd")]
+pub mod filter_simd;
+pub mod frames;
 pub mod util;
+#[cfg(feature = "std")]
 pub mod gated_loudness;
+#[cfg(feature = "std")]
+pub mod gating;
+#[cfg(feature = "std")]
+pub mod lra;
+#[cfg(feature = "std")]
+pub mod measurement;
+#[cfg(feature = "std")]
+pub mod meter;
 pub mod peak;
+#[cfg(feature = "std")]
 pub mod pipeline;
-
+#[cfg(feature = "std")]
+pub mod replay_gain;
+#[cfg(feature = "std")]
+pub mod resample;
+pub mod signal_ext;
+pub mod stats;
+#[cfg(feature = "async")]
+pub mod stream;
+#[cfg(feature = "std")]
+pub mod timeline;
+#[cfg(feature = "alloc")]
+pub mod true_peak;
+pub mod unit;
+
+#[cfg(not(any(feature = "std", test)))]
+pub(crate) mod float;
+
+#[cfg(feature = "std")]
 pub(crate) mod test_util;
 
-pub use filter::KWeightFilter;
+#[cfg(feature = "std")]
+pub use album::{AlbumLoudness, AlbumLoudnessAccumulator};
+#[cfg(feature = "std")]
+pub use atsc::AtscA85;
+pub use channel::{ChannelLayout, ChannelWeights};
+pub use error::Error;
+pub use filter::{AWeightedFilter, Coefficients, FilterDesigner, FilteredSamples, FilteredSamplesWithRaw, KWeightFilter, ZWeightedSamples};
+pub use frames::{InterleavedFrames, PlanarFrames};
+#[cfg(feature = "std")]
+pub use frames::{PcmFormat, PcmFrames};
+#[cfg(all(feature = "std", feature = "hound"))]
+pub use frames::WavFrames;
+#[cfg(feature = "alloc")]
+pub use filter::{process_signal, Itu468Filter, Itu468FilteredSamples};
+#[cfg(feature = "std")]
 pub use gated_loudness::{GatedPowers, Loudness, Gating};
-
-#[cfg(test)]
+#[cfg(feature = "std")]
+pub use gating::{GatedPowerBlocks, GatingConfig, TimestampedGateBlock};
+#[cfg(feature = "std")]
+pub use measurement::{Measurement, MeasurementAccumulator};
+#[cfg(feature = "std")]
+pub use replay_gain::{ReplayGain, ReplayGainTags};
+pub use signal_ext::SignalExt;
+pub use stats::Stats;
+#[cfg(feature = "async")]
+pub use stream::{AsyncFilteredSamples, AsyncMeasurementAccumulator};
+#[cfg(feature = "std")]
+pub use timeline::LoudnessTimeline;
+#[cfg(feature = "alloc")]
+pub use true_peak::{TruePeakMeter, TruePeakSamples};
+pub use unit::{LoudnessUnit, LoudnessValue};
+
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
 
@@ -36,13 +138,13 @@ mod tests {
         let phase = Phase::fixed_hz(SAMPLE_RATE, SINE_HZS);
         let signal = phase.gen_wave(Sine).take((SAMPLE_RATE as usize) * 2);
 
-        let k_weighter = KWeightFilter::new(SAMPLE_RATE as u32);
+        let k_weighter = KWeightFilter::new(SAMPLE_RATE as u32).unwrap();
         let power_gater = GatedPowers::momentary(SAMPLE_RATE as u32);
 
         let filtered_signal = signal.process(k_weighter);
         let gated_signal = filtered_signal.process_lazy(power_gater);
 
-        let loudness = gated_signal.calculate(Loudness::new(G_WEIGHTS)).unwrap();
+        let loudness = gated_signal.calculate(Loudness::new(G_WEIGHTS)).unwrap().integrated;
 
         assert_abs_diff_eq!(loudness, -3.010251969611668, epsilon = 1e-9);
     }
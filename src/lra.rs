@@ -0,0 +1,164 @@
+//! Loudness range (LRA) measurement, according to EBU Tech 3342.
+
+use sampara::{Frame, Signal};
+
+use crate::error::Error;
+use crate::filter::KWeightFilter;
+use crate::gated_loudness::GatedPowers;
+use crate::util::Util;
+
+/// Length of the short-term blocks used for loudness range, in milliseconds.
+pub const LRA_GATE_MS: u64 = 3000;
+
+/// Step between consecutive short-term blocks, in milliseconds. This gives a
+/// 75% overlap between blocks.
+pub const LRA_DELTA_MS: u64 = 750;
+
+/// Short-term blocks at or below this loudness are considered silence.
+const ABS_LOUDNESS_THRESH: f64 = -70.0;
+
+/// Offset of the relative gate from the mean loudness of the absolutely-gated
+/// blocks, in LU.
+const REL_GATE_OFFSET: f64 = -20.0;
+
+const LOW_PERCENTILE: f64 = 0.10;
+const HIGH_PERCENTILE: f64 = 0.95;
+
+/// Collects the loudness of short-term (3 second) blocks and calculates the
+/// loudness range, which is the spread between the 10th and 95th percentiles
+/// of the loudness distribution after gating.
+#[derive(Debug, Clone, Default)]
+pub struct LoudnessRange {
+    abs_loud_blocks: Vec<f64>,
+}
+
+impl LoudnessRange {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the loudness of a short-term block, in LUFS.
+    pub fn push(&mut self, short_term_loudness: f64) {
+        if short_term_loudness > ABS_LOUDNESS_THRESH {
+            self.abs_loud_blocks.push(short_term_loudness);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.abs_loud_blocks.clear();
+    }
+
+    /// Measures the loudness range of a signal, using short-term blocks of
+    /// `LRA_GATE_MS` every `LRA_DELTA_MS`.
+    pub fn measure<S, F, const N: usize>(signal: S, sample_rate: u32, g_weights: F) -> Result<Option<f64>, Error>
+    where
+        S: Signal<N, Frame = F>,
+        F: Frame<N, Sample = f64>,
+    {
+        let k_weighter = KWeightFilter::new(sample_rate)?;
+        let power_gater = GatedPowers::custom(sample_rate, LRA_GATE_MS, LRA_DELTA_MS);
+
+        let mut signal = signal.process(k_weighter).process_lazy(power_gater);
+
+        Ok(Self::from_gated_powers(std::iter::from_fn(|| signal.next()), g_weights))
+    }
+
+    /// Calculates the loudness range from the powers of a sequence of
+    /// short-term blocks.
+    pub fn from_gated_powers<I, F, const N: usize>(gated_powers: I, g_weights: F) -> Option<f64>
+    where
+        I: IntoIterator<Item = F>,
+        F: Frame<N, Sample = f64>,
+    {
+        let mut loudness_range = Self::new();
+
+        for block_powers in gated_powers {
+            loudness_range.push(Util::loudness(block_powers, g_weights));
+        }
+
+        loudness_range.calculate()
+    }
+
+    /// Calculates the loudness range, in LU. Returns `None` if fewer than two
+    /// blocks have passed both gates, as there is no range to speak of.
+    pub fn calculate(&self) -> Option<f64> {
+        if self.abs_loud_blocks.is_empty() {
+            return None;
+        }
+
+        // The relative threshold is based on the mean power of the blocks, not
+        // the mean of their loudness values.
+        let mean_power =
+            self.abs_loud_blocks.iter().map(|l| 10.0f64.powf(l / 10.0)).sum::<f64>()
+            / self.abs_loud_blocks.len() as f64
+        ;
+        let rel_loudness_thresh = 10.0 * mean_power.log10() + REL_GATE_OFFSET;
+
+        let mut rel_loud_blocks = self.abs_loud_blocks
+            .iter()
+            .copied()
+            .filter(|&l| l > rel_loudness_thresh)
+            .collect::<Vec<_>>()
+        ;
+
+        if rel_loud_blocks.len() < 2 {
+            return None;
+        }
+
+        rel_loud_blocks.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let percentile = |p: f64| {
+            let i = ((rel_loud_blocks.len() - 1) as f64 * p).round() as usize;
+            rel_loud_blocks[i]
+        };
+
+        Some(percentile(HIGH_PERCENTILE) - percentile(LOW_PERCENTILE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sampara::signal;
+
+    use crate::test_util::TestUtil;
+
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn loudness_range() {
+        let mut lra = LoudnessRange::new();
+        assert_eq!(lra.calculate(), None);
+
+        // Blocks evenly spread from -30 to -10 LUFS, plus some that fall
+        // below the relative or absolute gates.
+        for i in 0..=200 {
+            lra.push(-30.0 + i as f64 * 0.1);
+        }
+        lra.push(-45.0);
+        lra.push(-80.0);
+
+        assert_abs_diff_eq!(lra.calculate().unwrap(), 17.0, epsilon = 1e-9);
+
+        lra.reset();
+        lra.push(-23.0);
+        assert_eq!(lra.calculate(), None);
+    }
+
+    #[test]
+    fn measure() {
+        const SAMPLE_RATE: u32 = 48000;
+
+        // Ten seconds at -20 dBFS followed by ten seconds at -40 dBFS.
+        let frames = TestUtil::sine_997(SAMPLE_RATE, SAMPLE_RATE as usize * 20).enumerate().map(|(i, x)| {
+            let amp = if i < SAMPLE_RATE as usize * 10 { 0.1 } else { 0.01 };
+            [amp * x; 2]
+        });
+
+        let produced = LoudnessRange::measure(signal::from_frames(frames), SAMPLE_RATE, [1.0, 1.0]).unwrap().unwrap();
+        assert_abs_diff_eq!(produced, 20.0, epsilon = 0.1);
+
+        assert_eq!(LoudnessRange::from_gated_powers(vec![[0.5, 0.5]], [1.0, 1.0]), None);
+    }
+}
@@ -0,0 +1,499 @@
+//! A high level API for measuring the integrated loudness of a signal, which
+//! wires up K-weighting, gating and loudness calculation.
+
+#[cfg(feature = "hound")]
+use std::io::Read;
+#[cfg(feature = "hound")]
+use std::path::Path;
+
+#[cfg(feature = "hound")]
+use crate::channel::ChannelWeights;
+use crate::error::Error;
+use crate::filter::KWeightFilter;
+#[cfg(feature = "hound")]
+use crate::frames::WavFrames;
+use crate::gated_loudness::{GatedPowers, GatePassStats, Loudness, LoudnessDiagnostics, LoudnessResult};
+use crate::gated_loudness::ABSOLUTE_LOUDNESS_THRESHOLD_BS1770_4;
+use crate::gating::GatingConfig;
+
+// The weights of 7.1 audio in the channel order of WAV files (channel mask
+// 0x63F): FL, FR, FC, LFE, BL, BR, SL, SR. This differs from the order of
+// `ChannelWeights::atmos_7_1`, which puts the side pair before the back pair.
+#[cfg(feature = "hound")]
+const WAV_7_1_WEIGHTS: [f64; 8] = [1.0, 1.0, 1.0, 0.0, 1.0, 1.0, 1.41, 1.41];
+
+/// Marker for a `Measurement` setting that has not been provided yet.
+#[derive(Debug, Copy, Clone)]
+pub struct Unset;
+
+/// Marker for a `Measurement` whose sample rate has been provided.
+#[derive(Debug, Copy, Clone)]
+pub struct SampleRate(u32);
+
+/// Marker for a `Measurement` whose channel weights have been provided.
+#[derive(Debug, Copy, Clone)]
+pub struct Weights<const N: usize>([f64; N]);
+
+/// Measures the integrated loudness of a stream of frames incrementally. The
+/// filter and gating state is kept between pushes, so frames can be pushed
+/// one at a time or in chunks of any size, and the loudness of the frames
+/// pushed so far can be calculated at any point.
+pub struct MeasurementAccumulator<const N: usize> {
+    k_filter: KWeightFilter<[f64; N], N>,
+    gated_powers: GatedPowers<[f64; N], N>,
+    loudness: Loudness<[f64; N], N>,
+}
+
+impl<const N: usize> MeasurementAccumulator<N> {
+    /// Returns `Error::InvalidSampleRate` if the sample rate is so low that a
+    /// gate block would not contain any frames.
+    pub fn new(sample_rate: u32, g_weights: [f64; N]) -> Result<Self, Error> {
+        GatingConfig::bs1770().frame_lens(sample_rate).map_err(|_| Error::InvalidSampleRate(sample_rate))?;
+
+        Ok(Self {
+            k_filter: KWeightFilter::new(sample_rate)?,
+            gated_powers: GatedPowers::momentary(sample_rate),
+            loudness: Loudness::new(g_weights),
+        })
+    }
+
+    pub fn push_frame(&mut self, frame: [f64; N]) {
+        let filtered = self.k_filter.process(frame);
+
+        if let Some(gated_powers) = self.gated_powers.process(filtered) {
+            self.loudness.push(gated_powers);
+        }
+    }
+
+    pub fn push_frames<I>(&mut self, frames: I)
+    where
+        I: IntoIterator<Item = [f64; N]>,
+    {
+        for frame in frames {
+            self.push_frame(frame);
+        }
+    }
+
+    /// Calculates the integrated loudness of the frames pushed so far. Returns
+    /// `Error::InsufficientLoudContent` if no gate blocks were loud enough to
+    /// be measured.
+    pub fn finish(&self) -> Result<LoudnessResult, Error> {
+        self.loudness.calculate().ok_or(Error::InsufficientLoudContent)
+    }
+
+    /// Estimates the integrated loudness of the frames pushed so far, for
+    /// updating real-time meters, such as EBU R128 meters that refresh every
+    /// 100 ms. Both gating passes are applied to the blocks seen so far.
+    ///
+    /// The estimate is not stable: it changes as more frames are pushed, and
+    /// can vary a lot over the first few seconds, when there are only a few
+    /// blocks. Unlike `finish`, this never fails, and while no blocks are loud
+    /// enough to be measured, the loudness is reported as negative infinity,
+    /// which meters usually show as an empty scale.
+    pub fn partial_result(&self) -> LoudnessResult {
+        self.loudness.calculate().unwrap_or_else(|| {
+            let absolute_pass = GatePassStats {
+                num_blocks: 0,
+                mean_loudness_lufs: f64::NEG_INFINITY,
+                threshold_lufs: ABSOLUTE_LOUDNESS_THRESHOLD_BS1770_4,
+            };
+
+            let relative_pass = GatePassStats {
+                num_blocks: 0,
+                mean_loudness_lufs: f64::NEG_INFINITY,
+                threshold_lufs: f64::NEG_INFINITY,
+            };
+
+            LoudnessResult {
+                integrated: f64::NEG_INFINITY,
+                absolute_pass,
+                relative_pass,
+                max_sample_peak_dbfs: None,
+            }
+        })
+    }
+
+    /// Counts the gate blocks of the frames pushed so far, and how many of
+    /// them pass each gate.
+    pub fn diagnostics(&self) -> LoudnessDiagnostics {
+        self.loudness.diagnostics()
+    }
+
+    /// Restarts the measurement from scratch, reusing the existing buffers.
+    pub fn reset(&mut self) {
+        self.k_filter.reset();
+        self.gated_powers.reset();
+        self.loudness.reset();
+    }
+}
+
+/// Measures the integrated loudness of a signal. The sample rate and channel
+/// weights must both be provided before any frames can be processed or the
+/// result can be calculated, which is checked at compile time by tracking
+/// which settings have been provided in the type of the measurement.
+pub struct Measurement<R, W, const N: usize> {
+    sample_rate: R,
+    channel_weights: W,
+    accumulator: Option<MeasurementAccumulator<N>>,
+}
+
+impl<const N: usize> Measurement<Unset, Unset, N> {
+    pub fn new() -> Self {
+        Self {
+            sample_rate: Unset,
+            channel_weights: Unset,
+            accumulator: None,
+        }
+    }
+}
+
+impl<const N: usize> Default for Measurement<Unset, Unset, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W, const N: usize> Measurement<Unset, W, N> {
+    pub fn sample_rate(self, sample_rate: u32) -> Measurement<SampleRate, W, N> {
+        Measurement {
+            sample_rate: SampleRate(sample_rate),
+            channel_weights: self.channel_weights,
+            accumulator: None,
+        }
+    }
+}
+
+impl<R, const N: usize> Measurement<R, Unset, N> {
+    /// Sets the weight of each channel, in channel order. See
+    /// `ChannelWeights` for the standard weights of common layouts.
+    pub fn channel_weights(self, channel_weights: [f64; N]) -> Measurement<R, Weights<N>, N> {
+        Measurement {
+            sample_rate: self.sample_rate,
+            channel_weights: Weights(channel_weights),
+            accumulator: None,
+        }
+    }
+}
+
+impl<const N: usize> Measurement<SampleRate, Weights<N>, N> {
+    fn accumulator(&mut self) -> Result<&mut MeasurementAccumulator<N>, Error> {
+        if self.accumulator.is_none() {
+            self.accumulator = Some(MeasurementAccumulator::new(self.sample_rate.0, self.channel_weights.0)?);
+        }
+
+        Ok(self.accumulator.as_mut().unwrap())
+    }
+
+    /// Processes a sequence of frames. This can be called multiple times, with
+    /// each call continuing on from the frames of the previous one. Returns
+    /// `Error::InvalidSampleRate` if the sample rate is invalid, as
+    /// `MeasurementAccumulator::new` does.
+    pub fn process<I>(mut self, frames: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = [f64; N]>,
+    {
+        self.accumulator()?.push_frames(frames);
+
+        Ok(self)
+    }
+
+    /// Calculates the integrated loudness of all of the processed frames.
+    /// Returns `Error::InsufficientLoudContent` if no gate blocks were loud
+    /// enough to be measured.
+    pub fn finish(mut self) -> Result<LoudnessResult, Error> {
+        self.accumulator()?.finish()
+    }
+
+    /// Converts this into an accumulator, for pushing frames incrementally.
+    pub fn into_accumulator(mut self) -> Result<MeasurementAccumulator<N>, Error> {
+        self.accumulator()?;
+
+        Ok(self.accumulator.unwrap())
+    }
+}
+
+/// Measures the integrated loudness of a WAV file, using the sample rate and
+/// channel count from its header. Samples can be PCM of 8 to 32 bits, or
+/// 32-bit float; other formats return `Error::UnsupportedFormat`.
+///
+/// The channel mask of the file is not read. Instead, the standard WAV channel
+/// order is assumed for each supported channel count:
+///
+/// * 1: C
+/// * 2: L, R
+/// * 5: L, R, C, Ls, Rs
+/// * 6: L, R, C, LFE, Ls, Rs
+/// * 8: L, R, C, LFE, Lb, Rb, Ls, Rs, with the side pair (Ls, Rs) last
+///
+/// Other channel counts return `Error::InvalidChannelCount`.
+///
+/// The channel count is only known once the file has been opened, so this is
+/// a free function, rather than part of the `Measurement` builder, which is
+/// generic over it.
+#[cfg(feature = "hound")]
+pub fn measure_file<P>(path: P) -> Result<LoudnessResult, Error>
+where
+    P: AsRef<Path>,
+{
+    measure_wav_reader(hound::WavReader::open(path)?)
+}
+
+/// Measures the integrated loudness of an already opened WAV file, such as one
+/// read from a `BufReader` or an in-memory `Cursor`. See `measure_file` for the
+/// supported formats.
+#[cfg(feature = "hound")]
+pub fn measure_wav_reader<R>(reader: hound::WavReader<R>) -> Result<LoudnessResult, Error>
+where
+    R: Read,
+{
+    // Each supported channel count maps to a const channel count.
+    match reader.spec().channels {
+        1 => measure_wav_frames(reader, ChannelWeights::mono().weights()),
+        2 => measure_wav_frames(reader, ChannelWeights::stereo().weights()),
+        5 => measure_wav_frames(reader, ChannelWeights::surround_5_0().weights()),
+        6 => measure_wav_frames(reader, ChannelWeights::surround_5_1().weights()),
+        8 => measure_wav_frames(reader, WAV_7_1_WEIGHTS),
+        n => Err(Error::InvalidChannelCount(n as usize)),
+    }
+}
+
+#[cfg(feature = "hound")]
+fn measure_wav_frames<R, const N: usize>(reader: hound::WavReader<R>, g_weights: [f64; N]) -> Result<LoudnessResult, Error>
+where
+    R: Read,
+{
+    let mut accumulator = MeasurementAccumulator::new(reader.spec().sample_rate, g_weights)?;
+    let mut frames = WavFrames::new(reader)?;
+
+    accumulator.push_frames(&mut frames);
+
+    match frames.error() {
+        Some(e) => Err(e.clone()),
+        None => accumulator.finish(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_util::TestUtil;
+
+    use approx::assert_abs_diff_eq;
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    #[test]
+    fn measurement() {
+        let sine = |seconds: usize| TestUtil::sine_997(SAMPLE_RATE, SAMPLE_RATE as usize * seconds).map(|x| [x, x]);
+
+        let result = Measurement::new()
+            .sample_rate(SAMPLE_RATE)
+            .channel_weights([1.0, 1.0])
+            .process(sine(2)).unwrap()
+            .process(sine(1)).unwrap()
+            .finish().unwrap()
+        ;
+
+        assert_abs_diff_eq!(result.integrated, 0.0, epsilon = 1e-2);
+
+        // The settings can be given in any order.
+        let silent = Measurement::new()
+            .channel_weights([1.0])
+            .sample_rate(SAMPLE_RATE)
+            .process(std::iter::repeat([0.0]).take(SAMPLE_RATE as usize)).unwrap()
+            .finish()
+        ;
+
+        assert_eq!(silent, Err(Error::InsufficientLoudContent));
+
+        let invalid = Measurement::new()
+            .sample_rate(0)
+            .channel_weights([1.0]);
+
+        assert!(matches!(invalid.finish(), Err(Error::InvalidSampleRate(0))));
+
+        // At 1 Hz, a 400 ms block has no frames.
+        let invalid = Measurement::new()
+            .sample_rate(1)
+            .channel_weights([1.0])
+            .process(std::iter::repeat([0.0]).take(10))
+        ;
+
+        assert!(matches!(invalid, Err(Error::InvalidSampleRate(1))));
+        assert!(matches!(MeasurementAccumulator::new(1, [1.0]), Err(Error::InvalidSampleRate(1))));
+    }
+
+    #[test]
+    fn measurement_accumulator() {
+        let sine = TestUtil::sine_997(SAMPLE_RATE, SAMPLE_RATE as usize * 3).map(|x| [x, x]).collect::<Vec<_>>();
+
+        let expected = Measurement::new()
+            .sample_rate(SAMPLE_RATE)
+            .channel_weights([1.0, 1.0])
+            .process(sine.iter().copied()).unwrap()
+            .finish()
+        ;
+
+        let mut accumulator = MeasurementAccumulator::new(SAMPLE_RATE, [1.0, 1.0]).unwrap();
+        assert_eq!(accumulator.finish(), Err(Error::InsufficientLoudContent));
+
+        // Frames can be pushed singly and in chunks of any size.
+        let (head, tail) = sine.split_at(12345);
+        for &frame in head {
+            accumulator.push_frame(frame);
+        }
+        for chunk in tail.chunks(1000) {
+            accumulator.push_frames(chunk.iter().copied());
+        }
+
+        assert_eq!(accumulator.finish(), expected);
+
+        // The result can be taken in the middle of a stream, which leaves the
+        // measurement running.
+        let mut from_builder = Measurement::new()
+            .sample_rate(SAMPLE_RATE)
+            .channel_weights([1.0, 1.0])
+            .into_accumulator().unwrap()
+        ;
+
+        from_builder.push_frames(sine[..SAMPLE_RATE as usize].iter().copied());
+        assert!(from_builder.finish().is_ok());
+        from_builder.push_frames(sine[SAMPLE_RATE as usize..].iter().copied());
+        assert_eq!(from_builder.finish(), expected);
+
+        accumulator.reset();
+        assert_eq!(accumulator.finish(), Err(Error::InsufficientLoudContent));
+
+        accumulator.push_frames(sine.iter().copied());
+        assert_eq!(accumulator.finish(), expected);
+    }
+
+    #[test]
+    fn partial_result() {
+        let mut accumulator = MeasurementAccumulator::new(SAMPLE_RATE, [1.0]).unwrap();
+
+        let result = accumulator.partial_result();
+        assert_eq!(result.integrated, f64::NEG_INFINITY);
+        assert_eq!(result.absolute_pass.num_blocks, 0);
+
+        // Update the estimate every 100 ms, as a meter would.
+        let sine = TestUtil::sine_997(SAMPLE_RATE, SAMPLE_RATE as usize * 3).map(|x| [x]).collect::<Vec<_>>();
+
+        let mut num_measured = 0;
+        for chunk in sine.chunks(SAMPLE_RATE as usize / 10) {
+            accumulator.push_frames(chunk.iter().copied());
+
+            let partial = accumulator.partial_result();
+            match accumulator.finish() {
+                Ok(result) => {
+                    assert_eq!(partial, result);
+                    num_measured += 1;
+                },
+                Err(_) => assert_eq!(partial.integrated, f64::NEG_INFINITY),
+            }
+        }
+
+        // The first block is only complete after 400 ms.
+        assert_eq!(num_measured, 27);
+        assert_abs_diff_eq!(accumulator.partial_result().integrated, -3.01, epsilon = 1e-2);
+    }
+
+    #[cfg(feature = "hound")]
+    #[test]
+    fn measure_file() {
+        const LEN: usize = SAMPLE_RATE as usize * 2;
+
+        let samples = TestUtil::sine_997_i16(SAMPLE_RATE, LEN);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sine.wav");
+
+        std::fs::write(&path, TestUtil::sine_997_wav(SAMPLE_RATE, LEN, &[1, 1])).unwrap();
+
+        let expected = Measurement::new()
+            .sample_rate(SAMPLE_RATE)
+            .channel_weights([1.0, 1.0])
+            .process(samples.iter().map(|&x| [x as f64 / 32768.0; 2])).unwrap()
+            .finish()
+        ;
+
+        assert_eq!(super::measure_file(&path), expected);
+        assert!(matches!(super::measure_file(dir.path().join("missing.wav")), Err(Error::Io(_))));
+
+        // The sample rate comes from the header, which may be invalid.
+        let path = dir.path().join("low_rate.wav");
+        std::fs::write(&path, TestUtil::sine_997_wav(1, 0, &[1, 1])).unwrap();
+        assert!(matches!(super::measure_file(&path), Err(Error::InvalidSampleRate(1))));
+    }
+
+    #[cfg(feature = "hound")]
+    #[test]
+    fn measure_wav_reader() {
+        const LEN: usize = SAMPLE_RATE as usize * 2;
+
+        let samples = TestUtil::sine_997_i16(SAMPLE_RATE, LEN);
+
+        let wav = |gains: &[i16]| {
+            let wav = TestUtil::sine_997_wav(SAMPLE_RATE, LEN, gains);
+
+            hound::WavReader::new(std::io::Cursor::new(wav)).unwrap()
+        };
+
+        // 5.0 audio uses the surround weights, which boost the last two
+        // channels.
+        let expected = Measurement::new()
+            .sample_rate(SAMPLE_RATE)
+            .channel_weights(ChannelWeights::surround_5_0().weights())
+            .process(samples.iter().map(|&x| [x as f64 / 32768.0; 5])).unwrap()
+            .finish()
+        ;
+
+        assert_eq!(super::measure_wav_reader(wav(&[1; 5])), expected);
+
+        let mono = super::measure_wav_reader(wav(&[1])).unwrap();
+        assert_abs_diff_eq!(mono.integrated, expected.unwrap().integrated - 10.0 * (1.0 + 1.0 + 1.0 + 1.41 + 1.41f64).log10(), epsilon = 1e-6);
+
+        // In 7.1 WAV files, the back pair comes before the side pair, and only
+        // the side pair is boosted.
+        let back = super::measure_wav_reader(wav(&[0, 0, 0, 0, 1, 1, 0, 0])).unwrap();
+        let side = super::measure_wav_reader(wav(&[0, 0, 0, 0, 0, 0, 1, 1])).unwrap();
+        assert_abs_diff_eq!(side.integrated - back.integrated, 10.0 * 1.41f64.log10(), epsilon = 1e-6);
+
+        let stereo = super::measure_wav_reader(wav(&[1, 1])).unwrap();
+        assert_abs_diff_eq!(back.integrated, stereo.integrated, epsilon = 1e-6);
+
+        assert_eq!(super::measure_wav_reader(wav(&[1; 3])), Err(Error::InvalidChannelCount(3)));
+    }
+
+    #[test]
+    fn diagnostics() {
+        let mut accumulator = MeasurementAccumulator::new(SAMPLE_RATE, [1.0]).unwrap();
+
+        // One second of silence gives 7 blocks, none of which are loud.
+        accumulator.push_frames(std::iter::repeat([0.0]).take(SAMPLE_RATE as usize));
+
+        assert_eq!(accumulator.diagnostics(), LoudnessDiagnostics {
+            total_blocks: 7,
+            blocks_above_absolute: 0,
+            blocks_above_relative: 0,
+        });
+        assert_eq!(accumulator.finish(), Err(Error::InsufficientLoudContent));
+
+        // A loud second follows, and then a quiet one that is above the
+        // absolute gate but below the relative one.
+        let sine = |amplitude: f64| TestUtil::sine_997(SAMPLE_RATE, SAMPLE_RATE as usize).map(move |x| [amplitude * x]);
+
+        accumulator.push_frames(sine(0.5));
+        accumulator.push_frames(sine(0.01));
+
+        let diagnostics = accumulator.diagnostics();
+        assert_eq!(diagnostics.total_blocks, 27);
+        assert!(diagnostics.blocks_above_absolute > diagnostics.blocks_above_relative);
+        assert!(diagnostics.blocks_above_relative > 0);
+
+        let result = accumulator.finish().unwrap();
+        assert_eq!(result.absolute_pass.num_blocks, diagnostics.blocks_above_absolute);
+        assert_eq!(result.relative_pass.num_blocks, diagnostics.blocks_above_relative);
+    }
+}
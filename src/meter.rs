@@ -0,0 +1,223 @@
+//! Ungated loudness meters over sliding windows, according to EBU R128.
+
+use std::collections::VecDeque;
+
+use sampara::Frame;
+
+use crate::gated_loudness::Gating;
+use crate::util::Util;
+
+/// Iterator adapter that yields the per-channel mean square of a sliding
+/// window of frames. The first output is yielded once the window is full, and
+/// then once every step after that.
+pub(crate) struct WindowedPowers<I, const N: usize>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    samples: I,
+    window: VecDeque<I::Item>,
+    window_len: usize,
+    step_len: usize,
+
+    // Number of frames left to read until the next output.
+    until_next: usize,
+}
+
+impl<I, const N: usize> WindowedPowers<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    pub fn new(samples: I, window_len: usize, step_len: usize) -> Self {
+        assert!(window_len > 0);
+        assert!(step_len > 0);
+
+        Self {
+            samples,
+            window: VecDeque::with_capacity(window_len + 1),
+            window_len,
+            step_len,
+            until_next: window_len,
+        }
+    }
+
+    /// The number of outputs that will be yielded after reading a given
+    /// number of frames.
+    fn num_outputs(&self, num_frames: usize) -> usize {
+        if num_frames < self.until_next { 0 }
+        else { 1 + (num_frames - self.until_next) / self.step_len }
+    }
+}
+
+impl<I, const N: usize> Iterator for WindowedPowers<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.window.push_back(self.samples.next()?);
+
+            if self.window.len() > self.window_len {
+                self.window.pop_front();
+            }
+
+            self.until_next -= 1;
+
+            if self.until_next == 0 {
+                self.until_next = self.step_len;
+
+                let mut powers = I::Item::EQUILIBRIUM;
+
+                for &frame in self.window.iter() {
+                    powers.zip_transform(frame, |p, x| p + x * x);
+                }
+
+                for p in powers.channels_mut() {
+                    *p /= self.window.len() as f64;
+                }
+
+                return Some(powers);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.samples.size_hint();
+
+        (self.num_outputs(lower), upper.map(|u| self.num_outputs(u)))
+    }
+}
+
+impl<I, const N: usize> ExactSizeIterator for WindowedPowers<I, N>
+where
+    I: ExactSizeIterator,
+    I::Item: Frame<N, Sample = f64>,
+{}
+
+/// Iterator adapter that yields the ungated loudness of a sliding window of
+/// K-weighted frames, in LUFS. The first value is yielded once the window is
+/// full, and then once every step after that.
+///
+/// EBU R128 defines two such meters: the momentary loudness (M), with a
+/// 400 ms window and a 100 ms step, and the short-term loudness (S), with a
+/// 3 second window and a 1 second step.
+pub struct WindowedLoudness<I, const N: usize>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    powers: WindowedPowers<I, N>,
+    g_weights: I::Item,
+}
+
+impl<I, const N: usize> WindowedLoudness<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    /// Creates a meter with the window and step lengths of a given gating.
+    /// Panics if either length does not contain any frames at the given
+    /// sample rate.
+    pub fn new(samples: I, sample_rate: u32, gating: Gating, g_weights: I::Item) -> Self {
+        let (window_len, step_len) = gating.frame_lens(sample_rate);
+
+        Self {
+            powers: WindowedPowers::new(samples, window_len, step_len),
+            g_weights,
+        }
+    }
+
+    /// Creates a meter for the momentary loudness (M).
+    pub fn momentary(samples: I, sample_rate: u32, g_weights: I::Item) -> Self {
+        Self::new(samples, sample_rate, Gating::Momentary, g_weights)
+    }
+
+    /// Creates a meter for the short-term loudness (S).
+    pub fn short_term(samples: I, sample_rate: u32, g_weights: I::Item) -> Self {
+        Self::new(samples, sample_rate, Gating::Shortterm, g_weights)
+    }
+}
+
+impl<I, const N: usize> Iterator for WindowedLoudness<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    type Item = f64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let powers = self.powers.next()?;
+        Some(Util::loudness(powers, self.g_weights))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.powers.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::filter::FilteredSamples;
+    use crate::test_util::TestUtil;
+
+    use approx::assert_abs_diff_eq;
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    fn sine(seconds: usize) -> impl ExactSizeIterator<Item = [f64; 2]> {
+        TestUtil::sine_997(SAMPLE_RATE, SAMPLE_RATE as usize * seconds).map(|x| [x, x])
+    }
+
+    #[test]
+    fn short_term_loudness() {
+        let filtered = FilteredSamples::new(sine(10), SAMPLE_RATE).unwrap();
+        let mut short_term = WindowedLoudness::short_term(filtered, SAMPLE_RATE, [1.0, 1.0]);
+
+        // One value at 3 seconds, and then one for each second after.
+        assert_eq!(short_term.size_hint(), (8, Some(8)));
+
+        short_term.next();
+        assert_eq!(short_term.size_hint(), (7, Some(7)));
+
+        let produced = short_term.collect::<Vec<_>>();
+        assert_eq!(produced.len(), 7);
+
+        for lufs in produced {
+            assert_abs_diff_eq!(lufs, 0.0, epsilon = 1e-2);
+        }
+    }
+
+    #[test]
+    fn momentary_loudness() {
+        let filtered = FilteredSamples::new(sine(2), SAMPLE_RATE).unwrap();
+        let momentary = WindowedLoudness::momentary(filtered, SAMPLE_RATE, [1.0, 1.0]);
+
+        // One value at 400 ms, and then one for each 100 ms after.
+        assert_eq!(momentary.size_hint(), (17, Some(17)));
+
+        let produced = momentary.collect::<Vec<_>>();
+        assert_eq!(produced.len(), 17);
+
+        for lufs in produced {
+            assert_abs_diff_eq!(lufs, 0.0, epsilon = 1e-2);
+        }
+
+        // Inputs with an unknown length give a matching range of outputs.
+        let samples = sine(1).filter(|_| true);
+        let momentary = WindowedLoudness::momentary(samples, SAMPLE_RATE, [1.0, 1.0]);
+        assert_eq!(momentary.size_hint(), (0, Some(7)));
+
+        // The momentary meter is a windowed meter with momentary gating.
+        let custom = Gating::Custom { gate_len_ms: 400, delta_len_ms: 100 };
+        assert!(
+            WindowedLoudness::new(sine(1), SAMPLE_RATE, custom, [1.0, 1.0])
+                .eq(WindowedLoudness::momentary(sine(1), SAMPLE_RATE, [1.0, 1.0]))
+        );
+    }
+}
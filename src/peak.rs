@@ -2,6 +2,9 @@
 
 use sampara::{Frame, Signal};
 
+#[cfg(not(any(feature = "std", test)))]
+use crate::float::F64Ext;
+
 /// Keeps a running absolute max of samples per channel that have been seen in
 /// a signal. Each channel is updated independently.
 pub struct RunningPeak<S, const N: usize>
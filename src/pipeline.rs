@@ -2,6 +2,7 @@ use std::collections::{HashMap, HashSet};
 
 use sampara::{Frame, Calculator};
 
+use crate::error::Error;
 use crate::filter::KWeightFilter;
 use crate::gated_loudness::{Gating, GatedLoudness};
 
@@ -64,13 +65,13 @@ where
     pub fn calculate(self) -> Output {
         let averages = self.avg_gl_map.into_iter()
             .map(|(gating, gl)| {
-                (gating, gl.calculate())
+                (gating, gl.calculate().map(|r| r.integrated))
             })
             .collect();
 
         let maximums = self.max_gl_map.into_iter()
             .map(|(gating, gl)| {
-                (gating, gl.calculate())
+                (gating, gl.calculate().map(|r| r.integrated))
             })
             .collect();
 
@@ -155,10 +156,10 @@ where
         self
     }
 
-    pub fn build(&self) -> Pipeline<F, N> {
+    pub fn build(&self) -> Result<Pipeline<F, N>, Error> {
         let Self { sample_rate, g_weights, avg_gatings, max_gatings } = self;
 
-        let k_filter = KWeightFilter::new(*sample_rate);
+        let k_filter = KWeightFilter::new(*sample_rate)?;
 
         let avg_gl_map = avg_gatings.iter()
             .map(|&g| (g, GatedLoudness::new(*sample_rate, *g_weights, g)))
@@ -167,10 +168,10 @@ where
             .map(|&g| (g, GatedLoudness::new(*sample_rate, *g_weights, g)))
             .collect();
 
-        Pipeline {
+        Ok(Pipeline {
             k_filter,
             avg_gl_map,
             max_gl_map,
-        }
+        })
     }
 }
@@ -0,0 +1,97 @@
+//! ReplayGain 2.0 tags, which are based on the EBU R128 integrated loudness.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::gated_loudness::{LoudnessAndTruePeakResult, LoudnessResult};
+
+/// The reference loudness of ReplayGain 2.0, in LUFS. This is 5 LU louder
+/// than the EBU R128 target, to roughly match the loudness that the original
+/// ReplayGain reference of 89 dB SPL produced.
+pub const REPLAY_GAIN_2_REFERENCE_LUFS: f64 = -18.0;
+
+/// The track gain and peak values written by ReplayGain taggers.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ReplayGainTags {
+    /// The gain to apply to the track to bring it to the reference loudness,
+    /// in dB.
+    pub track_gain_db: f64,
+    /// The peak level of the track, if it was measured. This is in dBTP if it
+    /// is a true peak, or in dBFS if it is a sample peak.
+    pub track_peak_db: Option<f64>,
+}
+
+impl ReplayGainTags {
+    /// The peak as a linear amplitude, where 1.0 is full scale, which is how
+    /// it is written in tags.
+    pub fn track_peak_amplitude(&self) -> Option<f64> {
+        self.track_peak_db.map(|peak_db| 10.0f64.powf(peak_db / 20.0))
+    }
+}
+
+impl Display for ReplayGainTags {
+    /// Formats the tags one per line, as `KEY=VALUE` pairs, such as
+    /// `REPLAYGAIN_TRACK_GAIN=-4.70 dB`. The peak is only written if it was
+    /// measured.
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "REPLAYGAIN_TRACK_GAIN={:+.2} dB", self.track_gain_db)?;
+
+        if let Some(peak) = self.track_peak_amplitude() {
+            write!(f, "\nREPLAYGAIN_TRACK_PEAK={:.6}", peak)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Calculates ReplayGain tags from loudness measurements.
+pub struct ReplayGain;
+
+impl ReplayGain {
+    /// Calculates the tags for a track, given its loudness and a reference
+    /// loudness in LUFS, which is usually `REPLAY_GAIN_2_REFERENCE_LUFS`. The
+    /// peak is the sample peak of the track, if it was measured along with
+    /// the loudness.
+    pub fn from_loudness_result(result: &LoudnessResult, reference_lufs: f64) -> ReplayGainTags {
+        ReplayGainTags {
+            track_gain_db: result.normalization_gain_db(reference_lufs),
+            track_peak_db: result.max_sample_peak_dbfs,
+        }
+    }
+
+    /// Calculates the tags for a track, using its true peak as the peak.
+    pub fn from_loudness_and_true_peak(result: &LoudnessAndTruePeakResult, reference_lufs: f64) -> ReplayGainTags {
+        ReplayGainTags {
+            track_peak_db: Some(result.true_peak_dbtp),
+            ..Self::from_loudness_result(&result.loudness, reference_lufs)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_util::TestUtil;
+
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn from_loudness_result() {
+        let tags = ReplayGain::from_loudness_result(&TestUtil::loudness_result(-13.3, None), REPLAY_GAIN_2_REFERENCE_LUFS);
+
+        assert_abs_diff_eq!(tags.track_gain_db, -4.7, epsilon = 1e-9);
+        assert_eq!(tags.track_peak_db, None);
+        assert_eq!(tags.to_string(), "REPLAYGAIN_TRACK_GAIN=-4.70 dB");
+
+        let quiet = TestUtil::loudness_result(-25.13, Some(-6.0));
+        let tags = ReplayGain::from_loudness_result(&quiet, REPLAY_GAIN_2_REFERENCE_LUFS);
+
+        assert_eq!(tags.to_string(), "REPLAYGAIN_TRACK_GAIN=+7.13 dB\nREPLAYGAIN_TRACK_PEAK=0.501187");
+
+        let with_true_peak = LoudnessAndTruePeakResult { loudness: quiet, true_peak_dbtp: 0.0 };
+        let tags = ReplayGain::from_loudness_and_true_peak(&with_true_peak, REPLAY_GAIN_2_REFERENCE_LUFS);
+
+        assert_eq!(tags.track_peak_db, Some(0.0));
+        assert_eq!(tags.to_string(), "REPLAYGAIN_TRACK_GAIN=+7.13 dB\nREPLAYGAIN_TRACK_PEAK=1.000000");
+    }
+}
@@ -0,0 +1,293 @@
+//! Rational sample rate conversion of signals.
+
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use sampara::{Frame, Signal};
+use sampara::signal::{self, FromFrames};
+
+use crate::error::Error;
+
+// Number of taps of the interpolation filter used for each output phase.
+const TAPS_PER_PHASE: usize = 32;
+
+/// Converts a signal from one sample rate to another, by upsampling by an
+/// integer factor, applying a windowed-sinc lowpass filter, and then
+/// downsampling by another integer factor. The filter is evaluated in
+/// polyphase form, so that only the taps needed for each output frame are
+/// computed.
+///
+/// The output is delayed by roughly half of the filter length, i.e.
+/// `TAPS_PER_PHASE / 2` input frames, and the final frames that are still in
+/// the filter when the input signal ends are not emitted.
+pub struct SampleRateConverter<S, const N: usize>
+where
+    S: Signal<N>,
+    S::Frame: Frame<N, Sample = f64>,
+{
+    frames: S,
+
+    // The upsampling and downsampling factors.
+    up: usize,
+    down: usize,
+
+    // Polyphase filter taps, stored as `TAPS_PER_PHASE` taps for each of the
+    // `up` phases in sequence.
+    taps: Vec<f64>,
+
+    // The most recent input frames, with the newest at the front.
+    history: VecDeque<S::Frame>,
+    num_consumed: usize,
+
+    // The position of the next output frame, in input frames plus a phase.
+    index: usize,
+    phase: usize,
+}
+
+impl<S, const N: usize> SampleRateConverter<S, N>
+where
+    S: Signal<N>,
+    S::Frame: Frame<N, Sample = f64>,
+{
+    pub fn new(frames: S, input_rate: u32, output_rate: u32) -> Result<Self, Error> {
+        for &rate in [input_rate, output_rate].iter() {
+            if rate == 0 {
+                return Err(Error::InvalidSampleRate(rate));
+            }
+        }
+
+        Self::with_ratio(frames, (output_rate, input_rate))
+    }
+
+    /// Creates a converter that changes the sample rate by a ratio of
+    /// `numerator / denominator`, such as `(160, 147)` for 44.1 kHz to
+    /// 48 kHz. The ratio does not need to be in lowest terms.
+    pub fn with_ratio(frames: S, ratio: (u32, u32)) -> Result<Self, Error> {
+        let (numerator, denominator) = ratio;
+
+        if numerator == 0 || denominator == 0 {
+            return Err(Error::InvalidResamplingRatio(numerator, denominator));
+        }
+
+        let g = gcd(numerator, denominator);
+        let up = (numerator / g) as usize;
+        let down = (denominator / g) as usize;
+
+        Ok(Self {
+            frames,
+            up,
+            down,
+            taps: polyphase_taps(up, down),
+            history: std::iter::repeat(Frame::EQUILIBRIUM).take(TAPS_PER_PHASE).collect(),
+            num_consumed: 0,
+            index: 0,
+            phase: 0,
+        })
+    }
+}
+
+impl<S, const N: usize> Signal<N> for SampleRateConverter<S, N>
+where
+    S: Signal<N>,
+    S::Frame: Frame<N, Sample = f64>,
+{
+    type Frame = S::Frame;
+
+    fn next(&mut self) -> Option<Self::Frame> {
+        // Make sure that the input frame at the current index has been read.
+        while self.num_consumed <= self.index {
+            let frame = self.frames.next()?;
+
+            self.history.pop_back();
+            self.history.push_front(frame);
+            self.num_consumed += 1;
+        }
+
+        let phase_taps = &self.taps[self.phase * TAPS_PER_PHASE..][..TAPS_PER_PHASE];
+
+        let mut output: Self::Frame = Frame::EQUILIBRIUM;
+        for (&tap, &frame) in phase_taps.iter().zip(self.history.iter()) {
+            output.zip_transform(frame, |o, x| o + tap * x);
+        }
+
+        // Advance to the next output position.
+        self.phase += self.down;
+        self.index += self.phase / self.up;
+        self.phase %= self.up;
+
+        Some(output)
+    }
+}
+
+/// Iterator adapter that converts the sample rate of an iterator of frames,
+/// using a `SampleRateConverter`. This allows signals at non-standard rates,
+/// such as the 47952 Hz of pulled-down video, to be converted before being
+/// passed to a `FilteredSamples`.
+pub struct ResampledSamples<I, const N: usize>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    converter: SampleRateConverter<FromFrames<I>, N>,
+}
+
+impl<I, const N: usize> ResampledSamples<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    pub fn new(samples: I, input_rate: u32, output_rate: u32) -> Result<Self, Error> {
+        Ok(Self {
+            converter: SampleRateConverter::new(signal::from_frames(samples), input_rate, output_rate)?,
+        })
+    }
+
+    /// Creates an adapter that changes the sample rate by a ratio of
+    /// `numerator / denominator`.
+    pub fn with_ratio(samples: I, ratio: (u32, u32)) -> Result<Self, Error> {
+        Ok(Self {
+            converter: SampleRateConverter::with_ratio(signal::from_frames(samples), ratio)?,
+        })
+    }
+}
+
+impl<I, const N: usize> Iterator for ResampledSamples<I, N>
+where
+    I: Iterator,
+    I::Item: Frame<N, Sample = f64>,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.converter.next()
+    }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a }
+    else { gcd(b, a % b) }
+}
+
+/// Designs a Blackman-windowed sinc lowpass filter for the upsampled rate, and
+/// rearranges it into polyphase form.
+fn polyphase_taps(up: usize, down: usize) -> Vec<f64> {
+    let len = up * TAPS_PER_PHASE;
+    let center = (len - 1) as f64 / 2.0;
+
+    // Cutoff frequency, in cycles per upsampled sample. This is the Nyquist
+    // frequency of the lower of the input and output rates.
+    let cutoff = 0.5 / up.max(down) as f64;
+
+    let prototype = (0..len)
+        .map(|k| {
+            let t = k as f64 - center;
+
+            let sinc =
+                if t == 0.0 { 2.0 * cutoff }
+                else { (2.0 * PI * cutoff * t).sin() / (PI * t) }
+            ;
+
+            let w = 2.0 * PI * k as f64 / (len - 1).max(1) as f64;
+            let window = 0.42 - 0.5 * w.cos() + 0.08 * (2.0 * w).cos();
+
+            sinc * window
+        })
+        .collect::<Vec<_>>();
+
+    // Normalize so that each phase has roughly unity gain at DC. This also
+    // makes up for the zeros inserted when upsampling.
+    let scale = up as f64 / prototype.iter().sum::<f64>();
+
+    let mut taps = vec![0.0; len];
+    for phase in 0..up {
+        for j in 0..TAPS_PER_PHASE {
+            taps[phase * TAPS_PER_PHASE + j] = prototype[phase + j * up] * scale;
+        }
+    }
+
+    taps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sampara::signal;
+    use sampara::wavegen::{Sine, Phase};
+
+    use crate::test_util::TestUtil;
+
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn sample_rate_converter() {
+        assert_eq!(gcd(44100, 48000), 300);
+
+        let taps = polyphase_taps(160, 147);
+        for phase in taps.chunks(TAPS_PER_PHASE) {
+            assert_abs_diff_eq!(phase.iter().sum::<f64>(), 1.0, epsilon = 1e-2);
+        }
+
+        // A sine wave below the cutoff keeps its power after conversion.
+        let phase = Phase::fixed_hz(44100.0, [997.0]);
+        let signal = phase.gen_wave(Sine).take(44100);
+
+        let mut converter = SampleRateConverter::new(signal, 44100, 48000).unwrap();
+
+        let mut num_frames = 0;
+        let mut power = 0.0;
+        while let Some([x]) = converter.next() {
+            // Skip the filter's startup transient.
+            if num_frames >= TAPS_PER_PHASE * 2 {
+                power += x * x;
+            }
+            num_frames += 1;
+        }
+
+        let expected_frames = 48000;
+        assert!(num_frames <= expected_frames && num_frames >= expected_frames - TAPS_PER_PHASE * 2);
+
+        power /= (num_frames - TAPS_PER_PHASE * 2) as f64;
+        assert_abs_diff_eq!(power, 0.5, epsilon = 1e-2);
+
+        assert_eq!(
+            SampleRateConverter::new(signal::empty::<[f64; 1], 1>(), 0, 48000).err(),
+            Some(Error::InvalidSampleRate(0)),
+        );
+    }
+
+    #[test]
+    fn with_ratio() {
+        let sine = || Phase::fixed_hz(44100.0, [997.0]).gen_wave(Sine).take(4410);
+
+        // The ratio is reduced, so these are all the same conversion.
+        for &ratio in [(160, 147), (320, 294), (48000, 44100)].iter() {
+            let mut expected = SampleRateConverter::new(sine(), 44100, 48000).unwrap();
+            let mut produced = SampleRateConverter::with_ratio(sine(), ratio).unwrap();
+
+            while let Some(frame) = expected.next() {
+                assert_eq!(produced.next(), Some(frame));
+            }
+            assert_eq!(produced.next(), None);
+        }
+
+        assert_eq!(
+            SampleRateConverter::with_ratio(sine(), (0, 147)).err(),
+            Some(Error::InvalidResamplingRatio(0, 147)),
+        );
+
+        // Video rates convert to 48 kHz, and the iterator adapter can feed a
+        // `FilteredSamples`.
+        let frames = TestUtil::sine_997(47952, 47952).map(|x| [x]);
+        let resampled = ResampledSamples::new(frames, 47952, 48000).unwrap();
+        let filtered = crate::filter::FilteredSamples::new(resampled, 48000).unwrap();
+
+        let powers = filtered.skip(TAPS_PER_PHASE * 2).map(|[x]| x * x).collect::<Vec<_>>();
+        let power = powers.iter().sum::<f64>() / powers.len() as f64;
+
+        assert!(powers.len() > 48000 - TAPS_PER_PHASE * 4);
+
+        // The K-weighting filter adds about 0.69 dB at this frequency.
+        assert_abs_diff_eq!(10.0 * power.log10(), -3.01 + 0.69, epsilon = 0.05);
+    }
+}
@@ -0,0 +1,102 @@
+//! Extra adapters for signals.
+
+use sampara::{Frame, Signal};
+
+use crate::util::Util;
+
+/// Extension methods for signals.
+pub trait SignalExt<const N: usize>: Signal<N> + Sized {
+    /// Calls a function with a copy of each frame before yielding the frame
+    /// unchanged. This allows a signal to feed a side measurement (such as a
+    /// true peak meter) without needing a second copy of the signal.
+    fn tee<M>(self, side_effect: M) -> TeeSignal<Self, M, N>
+    where
+        M: FnMut(Self::Frame),
+    {
+        TeeSignal { signal: self, side_effect }
+    }
+
+    /// Applies a constant gain, in dB, to each frame. This is useful for
+    /// normalizing a signal to a target loudness, using the gain from
+    /// `LoudnessResult::normalization_gain_db`.
+    fn gain_db(self, gain_db: f64) -> GainAdjustedSignal<Self, N>
+    where
+        Self::Frame: Frame<N, Sample = f64>,
+    {
+        GainAdjustedSignal { signal: self, gain_db }
+    }
+}
+
+impl<S, const N: usize> SignalExt<N> for S
+where
+    S: Signal<N>,
+{}
+
+pub struct TeeSignal<S, M, const N: usize>
+where
+    S: Signal<N>,
+    M: FnMut(S::Frame),
+{
+    signal: S,
+    side_effect: M,
+}
+
+impl<S, M, const N: usize> Signal<N> for TeeSignal<S, M, N>
+where
+    S: Signal<N>,
+    M: FnMut(S::Frame),
+{
+    type Frame = S::Frame;
+
+    fn next(&mut self) -> Option<Self::Frame> {
+        let frame = self.signal.next()?;
+
+        (self.side_effect)(frame);
+
+        Some(frame)
+    }
+}
+
+pub struct GainAdjustedSignal<S, const N: usize>
+where
+    S: Signal<N>,
+    S::Frame: Frame<N, Sample = f64>,
+{
+    signal: S,
+    gain_db: f64,
+}
+
+impl<S, const N: usize> Signal<N> for GainAdjustedSignal<S, N>
+where
+    S: Signal<N>,
+    S::Frame: Frame<N, Sample = f64>,
+{
+    type Frame = S::Frame;
+
+    fn next(&mut self) -> Option<Self::Frame> {
+        Some(Util::apply_gain_db(self.signal.next()?, self.gain_db))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sampara::signal;
+
+    #[test]
+    fn tee() {
+        let frames = [[0.1, -0.1], [0.2, -0.2], [0.3, -0.3]];
+
+        let mut seen = Vec::new();
+        let mut teed = signal::from_frames(frames.iter().copied()).tee(|f| seen.push(f));
+
+        let mut produced = Vec::new();
+        while let Some(frame) = teed.next() {
+            produced.push(frame);
+        }
+
+        assert_eq!(produced, frames);
+        assert_eq!(seen, frames);
+    }
+}
@@ -0,0 +1,261 @@
+//! Running statistics over frames.
+
+use sampara::Frame;
+
+#[cfg(not(any(feature = "std", test)))]
+use crate::float::F64Ext;
+
+/// Keeps a running weighted mean and variance of frames, per channel.
+///
+/// The mean is updated incrementally (as in West's weighted variant of
+/// Welford's algorithm) instead of keeping a running sum and dividing at the
+/// end. The accumulated value always stays on the same scale as the frames
+/// themselves, so adding many frames does not lose precision to a large sum,
+/// and the mean can be read at any point without overflow. The rounding error
+/// of each update is bounded by the size of the difference between the new
+/// frame and the current mean.
+///
+/// The variance is tracked the same way, by accumulating the weighted sum of
+/// squared differences from the mean, which Welford's algorithm updates using
+/// the mean from both before and after each frame is added.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stats<F, const N: usize>
+where
+    F: Frame<N, Sample = f64>,
+{
+    mean: F,
+    m2: F,
+    total_weight: f64,
+    count: usize,
+}
+
+impl<F, const N: usize> Stats<F, N>
+where
+    F: Frame<N, Sample = f64>,
+{
+    pub fn new() -> Self {
+        Self {
+            mean: F::EQUILIBRIUM,
+            m2: F::EQUILIBRIUM,
+            total_weight: 0.0,
+            count: 0,
+        }
+    }
+
+    pub fn add(&mut self, frame: F) {
+        self.add_weighted(frame, 1.0)
+    }
+
+    /// Adds a frame that counts for `weight` frames towards the mean.
+    pub fn add_weighted(&mut self, frame: F, weight: f64) {
+        self.count += 1;
+        self.total_weight += weight;
+
+        // Frames with no weight leave the mean and variance unchanged.
+        if self.total_weight > 0.0 {
+            let mut delta = frame;
+            delta.zip_transform(self.mean, |x, m| x - m);
+
+            let ratio = weight / self.total_weight;
+            self.mean.zip_transform(frame, |m, x| m + ratio * (x - m));
+
+            // The product of the differences from the old and new means.
+            let mut new_delta = frame;
+            new_delta.zip_transform(self.mean, |x, m| x - m);
+            delta.zip_transform(new_delta, |d, e| d * e);
+
+            self.m2.zip_transform(delta, |s, p| s + weight * p);
+        }
+    }
+
+    /// Combines the frames added to another accumulator into this one, as if
+    /// they had all been added to this one. This allows partial results from
+    /// parallel workers to be combined.
+    pub fn merge(&mut self, other: &Self) {
+        let self_weight = self.total_weight;
+
+        self.count += other.count;
+        self.total_weight += other.total_weight;
+
+        if self.total_weight > 0.0 {
+            let ratio = other.total_weight / self.total_weight;
+
+            // The squared differences of the two sets are combined, plus a
+            // term for the difference between their means.
+            let mut delta = other.mean;
+            delta.zip_transform(self.mean, |o, m| o - m);
+
+            self.m2.zip_transform(other.m2, |a, b| a + b);
+            self.m2.zip_transform(delta, |s, d| s + d * d * self_weight * ratio);
+
+            self.mean.zip_transform(other.mean, |m, x| m + ratio * (x - m));
+        }
+    }
+
+    /// The mean of the frames added so far, or `None` if no frames with a
+    /// positive weight have been added.
+    pub fn mean(&self) -> Option<F> {
+        if self.total_weight > 0.0 { Some(self.mean) } else { None }
+    }
+
+    /// The weighted population variance of the frames added so far, per
+    /// channel, or `None` if no frames with a positive weight have been
+    /// added.
+    pub fn variance(&self) -> Option<F> {
+        if self.total_weight <= 0.0 {
+            return None;
+        }
+
+        let mut variance = self.m2;
+        for v in variance.channels_mut() {
+            *v /= self.total_weight;
+        }
+
+        Some(variance)
+    }
+
+    /// The weighted population standard deviation of the frames added so
+    /// far, per channel.
+    pub fn std_dev(&self) -> Option<F> {
+        self.variance().map(|mut std_dev| {
+            for s in std_dev.channels_mut() {
+                *s = s.sqrt();
+            }
+
+            std_dev
+        })
+    }
+
+    /// The number of frames added so far, regardless of their weight.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+}
+
+impl<F, const N: usize> Default for Stats<F, N>
+where
+    F: Frame<N, Sample = f64>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn add_weighted() {
+        let mut stats = Stats::new();
+        assert_eq!(stats.mean(), None);
+
+        stats.add([1.0, 4.0]);
+        stats.add_weighted([4.0, 1.0], 2.0);
+        stats.add_weighted([100.0, 100.0], 0.0);
+
+        let [a, b] = stats.mean().unwrap();
+        assert_abs_diff_eq!(a, 3.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(b, 2.0, epsilon = 1e-12);
+        assert_eq!(stats.count(), 3);
+    }
+
+    #[test]
+    fn variance() {
+        let frames = [[2.0, -1.0], [4.0, 0.5], [4.0, 3.0], [5.0, -2.5], [7.0, 0.0], [9.0, 1.25]];
+
+        let mut stats = Stats::new();
+        assert_eq!(stats.variance(), None);
+
+        for &f in frames.iter() {
+            stats.add(f);
+        }
+
+        // Compare against the two pass formula of `sum((x - mean)^2) / n`.
+        let n = frames.len() as f64;
+        for c in 0..2 {
+            let mean = frames.iter().map(|f| f[c]).sum::<f64>() / n;
+            let expected = frames.iter().map(|f| (f[c] - mean).powi(2)).sum::<f64>() / n;
+
+            assert_abs_diff_eq!(stats.variance().unwrap()[c], expected, epsilon = 1e-12);
+            assert_abs_diff_eq!(stats.std_dev().unwrap()[c], expected.sqrt(), epsilon = 1e-12);
+        }
+
+        // A weight counts as that many copies of the frame.
+        let mut weighted = Stats::new();
+        weighted.add_weighted([1.0], 2.0);
+        weighted.add_weighted([4.0], 1.0);
+        weighted.add_weighted([100.0], 0.0);
+
+        let mut repeated = Stats::new();
+        for &f in [[1.0], [1.0], [4.0]].iter() {
+            repeated.add(f);
+        }
+
+        assert_abs_diff_eq!(weighted.variance().unwrap()[0], 2.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(weighted.variance().unwrap()[0], repeated.variance().unwrap()[0], epsilon = 1e-12);
+    }
+
+    #[test]
+    fn merge() {
+        let frames = [[1.0, 4.0], [4.0, 1.0], [-2.0, 0.5], [7.0, 3.0], [0.0, 0.0]];
+        let weights = [1.0, 2.0, 0.5, 1.5, 0.0];
+
+        let mut expected = Stats::new();
+        for (&f, &w) in frames.iter().zip(weights.iter()) {
+            expected.add_weighted(f, w);
+        }
+
+        let mut left = Stats::new();
+        let mut right = Stats::new();
+        for (i, (&f, &w)) in frames.iter().zip(weights.iter()).enumerate() {
+            if i < 2 { left.add_weighted(f, w) }
+            else { right.add_weighted(f, w) }
+        }
+
+        left.merge(&right);
+
+        let [a, b] = left.mean().unwrap();
+        let [ea, eb] = expected.mean().unwrap();
+        assert_abs_diff_eq!(a, ea, epsilon = 1e-12);
+        assert_abs_diff_eq!(b, eb, epsilon = 1e-12);
+        assert_eq!(left.count(), expected.count());
+
+        let [a, b] = left.variance().unwrap();
+        let [ea, eb] = expected.variance().unwrap();
+        assert_abs_diff_eq!(a, ea, epsilon = 1e-12);
+        assert_abs_diff_eq!(b, eb, epsilon = 1e-12);
+
+        // Merging into or from an empty accumulator is a no-op.
+        let mut empty = Stats::new();
+        empty.merge(&expected);
+        assert_eq!(empty, expected);
+
+        expected.merge(&Stats::new());
+        assert_eq!(empty, expected);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let mut stats = Stats::new();
+        stats.add([0.1, 1.0 / 3.0]);
+        stats.add_weighted([0.7, 2.0f64.sqrt()], 0.3);
+
+        let json = serde_json::to_string(&stats).unwrap();
+        let produced: Stats<[f64; 2], 2> = serde_json::from_str(&json).unwrap();
+
+        let [a, b] = produced.mean().unwrap();
+        let [ea, eb] = stats.mean().unwrap();
+        assert_eq!((a.to_bits(), b.to_bits()), (ea.to_bits(), eb.to_bits()));
+        assert_eq!(produced.total_weight.to_bits(), stats.total_weight.to_bits());
+        assert_eq!(produced.count(), stats.count());
+    }
+}
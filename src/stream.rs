@@ -0,0 +1,207 @@
+//! Asynchronous counterparts of `FilteredSamples` and
+//! `MeasurementAccumulator`, for audio that arrives as a `Stream`, such as
+//! from a channel receiver or an `AsyncRead` in an async runtime.
+
+use std::future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use sampara::Frame;
+
+use crate::error::Error;
+use crate::filter::KWeightFilter;
+use crate::gated_loudness::{LoudnessDiagnostics, LoudnessResult};
+use crate::measurement::MeasurementAccumulator;
+
+/// Stream adapter that applies K-weighting to each frame of a stream, in the
+/// same way as `FilteredSamples` does for iterators.
+pub struct AsyncFilteredSamples<S, const N: usize>
+where
+    S: Stream + Unpin,
+    S::Item: Frame<N, Sample = f64>,
+{
+    samples: S,
+    filter: KWeightFilter<S::Item, N>,
+}
+
+impl<S, const N: usize> AsyncFilteredSamples<S, N>
+where
+    S: Stream + Unpin,
+    S::Item: Frame<N, Sample = f64>,
+{
+    pub fn new(samples: S, sample_rate: u32) -> Result<Self, Error> {
+        Ok(Self {
+            samples,
+            filter: KWeightFilter::new(sample_rate)?,
+        })
+    }
+
+    /// Returns the remaining unfiltered frames, discarding the filter state.
+    pub fn into_inner(self) -> S {
+        self.samples
+    }
+}
+
+impl<S, const N: usize> Stream for AsyncFilteredSamples<S, N>
+where
+    S: Stream + Unpin,
+    S::Item: Frame<N, Sample = f64> + Unpin,
+{
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        match Pin::new(&mut this.samples).poll_next(cx) {
+            Poll::Ready(Some(frame)) => Poll::Ready(Some(this.filter.process(frame))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.samples.size_hint()
+    }
+}
+
+/// Measures the integrated loudness of a stream of frames incrementally, using
+/// a `MeasurementAccumulator`. Frames can be pushed from several streams in
+/// turn, and the loudness of the frames pushed so far can be calculated
+/// between pushes.
+pub struct AsyncMeasurementAccumulator<const N: usize> {
+    inner: MeasurementAccumulator<N>,
+}
+
+impl<const N: usize> AsyncMeasurementAccumulator<N> {
+    pub fn new(sample_rate: u32, g_weights: [f64; N]) -> Result<Self, Error> {
+        Ok(Self {
+            inner: MeasurementAccumulator::new(sample_rate, g_weights)?,
+        })
+    }
+
+    /// Pushes every frame of a stream, returning once the stream ends.
+    pub async fn push_frames<S>(&mut self, frames: S)
+    where
+        S: Stream<Item = [f64; N]> + Unpin,
+    {
+        let mut frames = frames;
+
+        while let Some(frame) = future::poll_fn(|cx| Pin::new(&mut frames).poll_next(cx)).await {
+            self.inner.push_frame(frame);
+        }
+    }
+
+    /// See `MeasurementAccumulator::finish`.
+    pub fn finish(&self) -> Result<LoudnessResult, Error> {
+        self.inner.finish()
+    }
+
+    /// See `MeasurementAccumulator::partial_result`.
+    pub fn partial_result(&self) -> LoudnessResult {
+        self.inner.partial_result()
+    }
+
+    pub fn diagnostics(&self) -> LoudnessDiagnostics {
+        self.inner.diagnostics()
+    }
+
+    pub fn into_inner(self) -> MeasurementAccumulator<N> {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::future::Future;
+    use std::task::{RawWaker, RawWakerVTable, Waker};
+
+    use crate::filter::FilteredSamples;
+
+    // A stream that yields the items of an iterator, and is pending every
+    // other poll, as a stream fed by a real audio source would sometimes be.
+    struct IterStream<I> {
+        iter: I,
+        pending: bool,
+    }
+
+    impl<I: Iterator + Unpin> Stream for IterStream<I> {
+        type Item = I::Item;
+
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let this = self.get_mut();
+
+            this.pending = !this.pending;
+            if this.pending {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            Poll::Ready(this.iter.next())
+        }
+    }
+
+    fn stream<I: Iterator>(iter: I) -> IterStream<I> {
+        IterStream { iter, pending: false }
+    }
+
+    fn noop_waker() -> Waker {
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        fn noop(_: *const ()) {}
+
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+        unsafe { Waker::from_raw(clone(std::ptr::null())) }
+    }
+
+    // Polls a future until it is ready, as none of the futures here wait on
+    // anything but the streams above.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    fn frames() -> impl Iterator<Item = [f64; 2]> + Clone + Unpin {
+        (0..48000).map(|i| {
+            let x = (i as f64 * 0.13).sin() * 0.5;
+            [x, -x]
+        })
+    }
+
+    #[test]
+    fn async_filtered_samples() {
+        let expected = FilteredSamples::new(frames(), 48000).unwrap().collect::<Vec<_>>();
+
+        let mut filtered = AsyncFilteredSamples::new(stream(frames()), 48000).unwrap();
+
+        let mut produced = Vec::new();
+        block_on(async {
+            while let Some(frame) = future::poll_fn(|cx| Pin::new(&mut filtered).poll_next(cx)).await {
+                produced.push(frame);
+            }
+        });
+
+        assert_eq!(produced, expected);
+
+        let mut expected = MeasurementAccumulator::new(48000, [1.0, 1.0]).unwrap();
+        expected.push_frames(frames());
+
+        let mut accumulator = AsyncMeasurementAccumulator::new(48000, [1.0, 1.0]).unwrap();
+        block_on(accumulator.push_frames(stream(frames().take(20000))));
+        block_on(accumulator.push_frames(stream(frames().skip(20000))));
+
+        assert_eq!(accumulator.finish(), expected.finish());
+    }
+}
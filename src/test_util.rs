@@ -14,7 +14,7 @@ use hound::{Error as HoundError, WavReader, WavIntoSamples, SampleFormat, Result
 use serde::Deserialize;
 
 use crate::filter::KWeightFilter;
-use crate::gated_loudness::{GatedPowers, Loudness, Gating};
+use crate::gated_loudness::{GatedPowers, GatePassStats, Loudness, LoudnessResult, Gating};
 
 const MAX_CHANNELS: usize = 5;
 const G_WEIGHTS: [f64; MAX_CHANNELS] = [1.0, 1.0, 1.0, 1.41, 1.41];
@@ -246,13 +246,13 @@ impl<R: Read> TestReader<R> {
 
         let signal = self.into_signal();
 
-        let k_weighter = KWeightFilter::new(sample_rate);
+        let k_weighter = KWeightFilter::new(sample_rate).unwrap();
         let power_gater = GatedPowers::momentary(sample_rate);
 
         let filtered_signal = signal.process(k_weighter);
         let gated_signal = filtered_signal.process_lazy(power_gater);
 
-        let loudness = gated_signal.calculate(Loudness::new(G_WEIGHTS)).unwrap();
+        let loudness = gated_signal.calculate(Loudness::new(G_WEIGHTS)).unwrap().integrated;
 
         println!("Loudness: {}", loudness)
     }
@@ -363,7 +363,7 @@ impl TestUtil {
 
         let sample_rate = track_reader.sample_rate();
 
-        let mut k_weighter = KWeightFilter::new(sample_rate);
+        let mut k_weighter = KWeightFilter::new(sample_rate).unwrap();
 
         let mut momentary_gater = GatedPowers::momentary(sample_rate);
         let mut shortterm_gater = GatedPowers::shortterm(sample_rate);
@@ -391,9 +391,11 @@ impl TestUtil {
         }
 
         let momentary_mean = momentary_loudness_calc.calculate()
-            .expect("unable to calculate momentary loudness for track");
+            .expect("unable to calculate momentary loudness for track")
+            .integrated;
         let shortterm_mean = shortterm_loudness_calc.calculate()
-            .expect("unable to calculate shortterm loudness for track");
+            .expect("unable to calculate shortterm loudness for track")
+            .integrated;
 
         let track_analysis = Analysis {
             momentary_mean,
@@ -429,7 +431,7 @@ impl TestUtil {
                 expected_sample_rate = Some(sample_rate);
             }
 
-            let mut k_weighter = KWeightFilter::new(sample_rate);
+            let mut k_weighter = KWeightFilter::new(sample_rate).unwrap();
 
             let mut momentary_gater = GatedPowers::momentary(sample_rate);
             let mut shortterm_gater = GatedPowers::shortterm(sample_rate);
@@ -453,8 +455,8 @@ impl TestUtil {
                 }
             }
 
-            let momentary_mean = momentary_loudness_calc.calculate().expect("unable to calculate momentary loudness for track");
-            let shortterm_mean = shortterm_loudness_calc.calculate().expect("unable to calculate shortterm loudness for track");
+            let momentary_mean = momentary_loudness_calc.calculate().expect("unable to calculate momentary loudness for track").integrated;
+            let shortterm_mean = shortterm_loudness_calc.calculate().expect("unable to calculate shortterm loudness for track").integrated;
 
             let track_analysis = Analysis {
                 momentary_mean,
@@ -531,6 +533,13 @@ impl TestUtil {
             .unwrap_or(false)
     }
 
+    pub fn check_scipy() -> bool {
+        Command::new("python3").arg("-c").arg("import scipy.signal")
+            .status()
+            .map(|s| s.success())
+            .unwrap_or(false)
+    }
+
     pub fn load_flac_data(path: &Path) -> Result<FlacFrames<File>, LoadFlacError> {
         let file = File::open(path).map_err(LoadFlacError::Io)?;
 
@@ -579,6 +588,87 @@ impl TestUtil {
         res
     }
 
+    /// Runs a Python script, and parses what it prints as whitespace-separated
+    /// floats.
+    pub fn python_eval_floats(script: &str) -> Vec<f64> {
+        let output = Command::new("python3").arg("-c").arg(script)
+            .output()
+            .unwrap_or_else(|e| panic!("failed to execute command: {}", e));
+
+        let Output { status, stdout, stderr } = output;
+
+        assert!(
+            status.success(),
+            "python3 returned with non-zero code: {}: {}",
+            status, String::from_utf8_lossy(&stderr),
+        );
+
+        String::from_utf8(stdout)
+            .unwrap_or_else(|e| panic!("cannot convert stdout bytes into string: {}", e))
+            .split_whitespace()
+            .map(|s| s.parse::<f64>().unwrap_or_else(|e| panic!("cannot parse float {:?}: {}", s, e)))
+            .collect()
+    }
+
+    /// Generates a full-scale 997 Hz sine, the reference tone of BS.1770,
+    /// which measures 0 LUFS when copied into both channels of a stereo
+    /// signal.
+    pub fn sine_997(sample_rate: u32, len: usize) -> impl ExactSizeIterator<Item = f64> + Clone {
+        (0..len).map(move |i| (2.0 * PI * 997.0 * i as f64 / sample_rate as f64).sin())
+    }
+
+    /// Generates stereo frames of a 997 Hz sine that alternates between loud
+    /// and quiet passages of 4 seconds each, 20 dB apart, which has a wide
+    /// loudness range.
+    pub fn alternating_997(sample_rate: u32, len: usize) -> Vec<[f64; 2]> {
+        let passage_len = sample_rate as usize * 4;
+
+        Self::sine_997(sample_rate, len).enumerate().map(|(i, x)| {
+            let amplitude = if (i / passage_len) % 2 == 0 { 0.5 } else { 0.05 };
+            [amplitude * x; 2]
+        }).collect()
+    }
+
+    /// The samples of `sine_997` at half scale, quantized to 16 bits.
+    pub fn sine_997_i16(sample_rate: u32, len: usize) -> Vec<i16> {
+        Self::sine_997(sample_rate, len).map(|x| (x * 16384.0).round() as i16).collect()
+    }
+
+    /// Encodes the samples of `sine_997_i16` as a 16-bit WAV file, with one
+    /// channel per gain, each channel being the samples scaled by its gain.
+    pub fn sine_997_wav(sample_rate: u32, len: usize, gains: &[i16]) -> Vec<u8> {
+        let spec = hound::WavSpec {
+            channels: gains.len() as u16,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+
+        let mut wav = std::io::Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut wav, spec).unwrap();
+        for x in Self::sine_997_i16(sample_rate, len) {
+            for &gain in gains.iter() {
+                writer.write_sample(x * gain).unwrap();
+            }
+        }
+        writer.finalize().unwrap();
+
+        wav.into_inner()
+    }
+
+    /// A loudness result for a single block at the given loudness, for
+    /// testing policies that only look at the integrated loudness and peak.
+    pub fn loudness_result(integrated: f64, max_sample_peak_dbfs: Option<f64>) -> LoudnessResult {
+        let pass = GatePassStats { num_blocks: 1, mean_loudness_lufs: integrated, threshold_lufs: -70.0 };
+
+        LoudnessResult {
+            integrated,
+            absolute_pass: pass,
+            relative_pass: pass,
+            max_sample_peak_dbfs,
+        }
+    }
+
     pub fn load_audio_data(path: &Path) -> (Vec<f64>, u32, u8) {
         // Get sample rate.
         let stdout_str = Self::sox_eval_string(
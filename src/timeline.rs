@@ -0,0 +1,181 @@
+//! Collections of time-stamped gating blocks, for exporting a loudness
+//! timeline to other tools.
+
+use std::io::Write;
+
+use crate::error::Error;
+use crate::gating::TimestampedGateBlock;
+
+/// The columns written by `LoudnessTimeline::to_csv`.
+const CSV_HEADER: &str = "start_sample,end_sample,start_time_s,end_time_s,loudness_lufs,passed_absolute,passed_relative";
+
+/// All of the time-stamped gating blocks of a signal, such as those yielded by
+/// `GatedPowerBlocks::with_timestamps`, along with the sample rate needed to
+/// convert their positions into times.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoudnessTimeline<F> {
+    sample_rate: u32,
+    blocks: Vec<TimestampedGateBlock<F>>,
+}
+
+impl<F> LoudnessTimeline<F> {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            blocks: Vec::new(),
+        }
+    }
+
+    pub fn from_blocks<I>(blocks: I, sample_rate: u32) -> Self
+    where
+        I: IntoIterator<Item = TimestampedGateBlock<F>>,
+    {
+        Self {
+            sample_rate,
+            blocks: blocks.into_iter().collect(),
+        }
+    }
+
+    pub fn push(&mut self, block: TimestampedGateBlock<F>) {
+        self.blocks.push(block);
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn blocks(&self) -> &[TimestampedGateBlock<F>] {
+        &self.blocks
+    }
+
+    /// The blocks that passed both gates, and so count towards the integrated
+    /// loudness.
+    pub fn gated_blocks(&self) -> impl Iterator<Item = &TimestampedGateBlock<F>> {
+        self.blocks.iter().filter(|block| block.passed_relative)
+    }
+
+    fn seconds(&self, sample: u64) -> f64 {
+        sample as f64 / self.sample_rate as f64
+    }
+
+    /// Writes the timeline as CSV, with a header row and then one row per
+    /// block. The columns are `start_sample`, `end_sample`, `start_time_s`,
+    /// `end_time_s`, `loudness_lufs`, `passed_absolute` and `passed_relative`.
+    /// Silent blocks have a loudness of `-inf`.
+    pub fn to_csv<W>(&self, writer: W) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let mut writer = writer;
+
+        writeln!(writer, "{}", CSV_HEADER)?;
+
+        for block in self.blocks.iter() {
+            writeln!(
+                writer, "{},{},{},{},{},{},{}",
+                block.start_sample, block.end_sample,
+                self.seconds(block.start_sample), self.seconds(block.end_sample),
+                block.loudness, block.passed_absolute, block.passed_relative,
+            )?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Writes the timeline as a JSON array, with one object per block, using
+    /// the same fields as the columns of `to_csv`. JSON has no infinities, so
+    /// silent blocks have a loudness of `null`.
+    #[cfg(feature = "serde_json")]
+    pub fn to_json<W>(&self, writer: W) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let mut writer = writer;
+
+        let rows = self.blocks.iter()
+            .map(|block| serde_json::json!({
+                "start_sample": block.start_sample,
+                "end_sample": block.end_sample,
+                "start_time_s": self.seconds(block.start_sample),
+                "end_time_s": self.seconds(block.end_sample),
+                "loudness_lufs": block.loudness,
+                "passed_absolute": block.passed_absolute,
+                "passed_relative": block.passed_relative,
+            }))
+            .collect::<Vec<_>>()
+        ;
+
+        serde_json::to_writer(&mut writer, &serde_json::json!(rows)).map_err(std::io::Error::from)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+impl<F> Extend<TimestampedGateBlock<F>> for LoudnessTimeline<F> {
+    fn extend<I>(&mut self, blocks: I)
+    where
+        I: IntoIterator<Item = TimestampedGateBlock<F>>,
+    {
+        self.blocks.extend(blocks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timeline() -> LoudnessTimeline<[f64; 1]> {
+        let blocks = [(-20.5, true, true), (-45.25, true, false), (f64::NEG_INFINITY, false, false)]
+            .iter()
+            .enumerate()
+            .map(|(k, &(loudness, passed_absolute, passed_relative))| TimestampedGateBlock {
+                start_sample: k as u64 * 4800,
+                end_sample: k as u64 * 4800 + 19200,
+                power: [0.0],
+                loudness,
+                passed_absolute,
+                passed_relative,
+            })
+        ;
+
+        LoudnessTimeline::from_blocks(blocks, 48000)
+    }
+
+    #[test]
+    fn to_csv() {
+        let timeline = timeline();
+        assert_eq!(timeline.gated_blocks().count(), 1);
+
+        let mut csv = Vec::new();
+        timeline.to_csv(&mut csv).unwrap();
+
+        let expected = "\
+            start_sample,end_sample,start_time_s,end_time_s,loudness_lufs,passed_absolute,passed_relative\n\
+            0,19200,0,0.4,-20.5,true,true\n\
+            4800,24000,0.1,0.5,-45.25,true,false\n\
+            9600,28800,0.2,0.6,-inf,false,false\n\
+        ";
+
+        assert_eq!(String::from_utf8(csv).unwrap(), expected);
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn to_json() {
+        let mut json = Vec::new();
+        timeline().to_json(&mut json).unwrap();
+
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(value.as_array().unwrap().len(), 3);
+
+        assert_eq!(value[1]["start_sample"], 4800);
+        assert_eq!(value[1]["end_time_s"], 0.5);
+        assert_eq!(value[1]["loudness_lufs"], -45.25);
+        assert_eq!(value[1]["passed_absolute"], true);
+        assert_eq!(value[1]["passed_relative"], false);
+        assert!(value[2]["loudness_lufs"].is_null());
+    }
+}
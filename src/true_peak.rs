@@ -0,0 +1,199 @@
+//! True peak measurement, according to Annex 2 of the BS.1770 spec.
+
+use alloc::collections::VecDeque;
+
+use sampara::{Frame, Signal};
+
+#[cfg(not(any(feature = "std", test)))]
+use crate::float::F64Ext;
+use crate::util::Util;
+
+/// Number of FIR taps in each phase of the oversampling filter.
+const TAPS_PER_PHASE: usize = 12;
+
+/// Polyphase coefficients of the 4x oversampling interpolation filter given in
+/// BS.1770-4 Annex 2, with one row per phase.
+const COEFFICIENTS: [[f64; TAPS_PER_PHASE]; 4] = [
+    [
+         0.0017089843750,  0.0109863281250, -0.0196533203125,  0.0332031250000,
+        -0.0594482421875,  0.1373291015625,  0.9721679687500, -0.1022949218750,
+         0.0476074218750, -0.0266113281250,  0.0148925781250, -0.0083007812500,
+    ],
+    [
+        -0.0291748046875,  0.0292968750000, -0.0517578125000,  0.0891113281250,
+        -0.1665039062500,  0.4650878906250,  0.7797851562500, -0.2003173828125,
+         0.1015625000000, -0.0582275390625,  0.0330810546875, -0.0189208984375,
+    ],
+    [
+        -0.0189208984375,  0.0330810546875, -0.0582275390625,  0.1015625000000,
+        -0.2003173828125,  0.7797851562500,  0.4650878906250, -0.1665039062500,
+         0.0891113281250, -0.0517578125000,  0.0292968750000, -0.0291748046875,
+    ],
+    [
+        -0.0083007812500,  0.0148925781250, -0.0266113281250,  0.0476074218750,
+        -0.1022949218750,  0.9721679687500,  0.1373291015625, -0.0594482421875,
+         0.0332031250000, -0.0196533203125,  0.0109863281250,  0.0017089843750,
+    ],
+];
+
+/// Keeps a running maximum of the true (inter-sample) peak per channel, by
+/// oversampling each frame by a factor of 4 and taking the absolute max of
+/// the interpolated samples.
+pub struct TruePeakMeter<F, const N: usize>
+where
+    F: Frame<N, Sample = f64>,
+{
+    // The most recently pushed frames, with the newest at the front.
+    history: VecDeque<F>,
+    peaks: F,
+}
+
+impl<F, const N: usize> TruePeakMeter<F, N>
+where
+    F: Frame<N, Sample = f64>,
+{
+    pub fn new() -> Self {
+        Self {
+            history: core::iter::repeat(F::EQUILIBRIUM).take(TAPS_PER_PHASE).collect(),
+            peaks: F::EQUILIBRIUM,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new()
+    }
+
+    pub fn push_frame(&mut self, frame: F) {
+        self.history.pop_back();
+        self.history.push_front(frame);
+
+        for phase in COEFFICIENTS.iter() {
+            let mut interpolated = F::EQUILIBRIUM;
+
+            for (&c, &x) in phase.iter().zip(self.history.iter()) {
+                interpolated.zip_transform(x, |a, x| a + c * x);
+            }
+
+            self.peaks.zip_transform(interpolated, |p, x| p.max(x.abs()));
+        }
+    }
+
+    /// The linear true peak of each channel seen so far.
+    pub fn peaks(&self) -> F {
+        self.peaks
+    }
+
+    /// The highest true peak across all channels, in dBTP.
+    pub fn max_dbtp(&self) -> f64 {
+        Util::dbfs(self.peaks.channels().copied().fold(0.0, f64::max))
+    }
+}
+
+impl<F, const N: usize> Default for TruePeakMeter<F, N>
+where
+    F: Frame<N, Sample = f64>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Signal adapter that passes frames through unchanged, while keeping track of
+/// their running true peak.
+pub struct TruePeakSamples<S, const N: usize>
+where
+    S: Signal<N>,
+    S::Frame: Frame<N, Sample = f64>,
+{
+    frames: S,
+    meter: TruePeakMeter<S::Frame, N>,
+}
+
+impl<S, const N: usize> TruePeakSamples<S, N>
+where
+    S: Signal<N>,
+    S::Frame: Frame<N, Sample = f64>,
+{
+    pub fn new(frames: S) -> Self {
+        Self {
+            frames,
+            meter: TruePeakMeter::new(),
+        }
+    }
+
+    /// The linear true peak of each channel seen so far.
+    pub fn peaks(&self) -> S::Frame {
+        self.meter.peaks()
+    }
+
+    /// The highest true peak across all channels seen so far, in dBTP.
+    pub fn peak_dbtp(&self) -> f64 {
+        self.meter.max_dbtp()
+    }
+}
+
+impl<S, const N: usize> Signal<N> for TruePeakSamples<S, N>
+where
+    S: Signal<N>,
+    S::Frame: Frame<N, Sample = f64>,
+{
+    type Frame = S::Frame;
+
+    fn next(&mut self) -> Option<Self::Frame> {
+        let frame = self.frames.next()?;
+
+        self.meter.push_frame(frame);
+
+        // Pass through the original frame.
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sampara::signal;
+
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn true_peak_meter() {
+        // A sine at a quarter of the sample rate, offset by 45 degrees, has
+        // samples at only ~0.707, while its true peak is at 1.0.
+        let mut meter = TruePeakMeter::new();
+
+        for i in 0..1000 {
+            let x = (std::f64::consts::FRAC_PI_2 * i as f64 + std::f64::consts::FRAC_PI_4).sin();
+            meter.push_frame([x, 0.5 * x]);
+        }
+
+        let [l, r] = meter.peaks();
+
+        assert_abs_diff_eq!(l, 1.0, epsilon = 0.02);
+        assert_abs_diff_eq!(r, 0.5, epsilon = 0.01);
+        assert_abs_diff_eq!(meter.max_dbtp(), 0.0, epsilon = 0.2);
+
+        meter.reset();
+        assert_eq!(meter.peaks(), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn true_peak_samples() {
+        let frames = (0..1000).map(|i| {
+            let x = (std::f64::consts::FRAC_PI_2 * i as f64 + std::f64::consts::FRAC_PI_4).sin();
+            [0.5 * x]
+        }).collect::<Vec<_>>();
+
+        let mut true_peak = TruePeakSamples::new(signal::from_frames(frames.iter().copied()));
+
+        let mut produced = Vec::new();
+        while let Some(frame) = true_peak.next() {
+            produced.push(frame);
+        }
+
+        assert_eq!(produced, frames);
+        assert_abs_diff_eq!(true_peak.peaks()[0], 0.5, epsilon = 0.01);
+        assert_abs_diff_eq!(true_peak.peak_dbtp(), -6.0206, epsilon = 0.2);
+    }
+}
@@ -0,0 +1,120 @@
+//! Units of loudness, so that values measured against different standards are
+//! not mixed up.
+//!
+//! LUFS (loudness units relative to full scale, from EBU R128) and LKFS
+//! (loudness, K-weighted, relative to full scale, from ITU-R BS.1770) are two
+//! names for the same measurement, and a value in one is numerically the same
+//! in the other. Neither is the same as dBFS, which measures the level of the
+//! samples themselves, without K-weighting, gating or channel weighting.
+
+use core::fmt::{Display, Formatter, Result as FmtResult};
+
+/// A unit of loudness.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LoudnessUnit {
+    /// Loudness units relative to full scale, as used by EBU R128.
+    Lufs,
+    /// Loudness, K-weighted, relative to full scale, as used by ITU-R BS.1770
+    /// and ATSC A/85.
+    Lkfs,
+}
+
+impl LoudnessUnit {
+    /// The symbol of this unit, such as "LUFS".
+    pub const fn symbol(&self) -> &'static str {
+        match self {
+            Self::Lufs => "LUFS",
+            Self::Lkfs => "LKFS",
+        }
+    }
+}
+
+impl Display for LoudnessUnit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(self.symbol())
+    }
+}
+
+/// A loudness value, tagged with the unit it is expressed in.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LoudnessValue(pub f64, pub LoudnessUnit);
+
+impl LoudnessValue {
+    pub const fn lufs(value: f64) -> Self {
+        Self(value, LoudnessUnit::Lufs)
+    }
+
+    pub const fn lkfs(value: f64) -> Self {
+        Self(value, LoudnessUnit::Lkfs)
+    }
+
+    pub const fn value(&self) -> f64 {
+        self.0
+    }
+
+    pub const fn unit(&self) -> LoudnessUnit {
+        self.1
+    }
+
+    /// Expresses this loudness in another unit. As LUFS and LKFS measure the
+    /// same thing, only the unit changes, and the value stays the same.
+    pub const fn to_unit(self, unit: LoudnessUnit) -> Self {
+        Self(self.0, unit)
+    }
+
+    pub const fn to_lufs(self) -> Self {
+        self.to_unit(LoudnessUnit::Lufs)
+    }
+
+    pub const fn to_lkfs(self) -> Self {
+        self.to_unit(LoudnessUnit::Lkfs)
+    }
+
+    /// A rough estimate of the RMS level of the signal, in dBFS. The
+    /// -0.691 dB offset in the loudness formula cancels out the gain of the
+    /// K-weighting filter at 997 Hz, so a sine wave near 1 kHz in a single
+    /// front channel has a loudness equal to its RMS level, and that is what
+    /// this assumes. For any other signal, this is only an approximation:
+    /// K-weighting boosts high frequencies and cuts low ones, gating ignores
+    /// quiet passages, and the powers of all channels are summed. This is not
+    /// a sample or true peak level, which are at least 3.01 dB higher for a
+    /// sine wave.
+    pub const fn to_dbfs_approximation(self) -> f64 {
+        self.0
+    }
+}
+
+impl Display for LoudnessValue {
+    /// Formats the value and its unit, such as "-23.0 LUFS". The value has one
+    /// decimal place, unless a precision is given.
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let precision = f.precision().unwrap_or(1);
+
+        write!(f, "{:.*} {}", precision, self.0, self.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loudness_value() {
+        let lufs = LoudnessValue::lufs(-23.0);
+
+        assert_eq!(lufs.to_string(), "-23.0 LUFS");
+        assert_eq!(lufs.to_lkfs().to_string(), "-23.0 LKFS");
+        assert_eq!(format!("{:.2}", LoudnessValue::lkfs(-24.123)), "-24.12 LKFS");
+
+        // Converting between units keeps the value, but the units still need
+        // to match for values to be equal.
+        assert_eq!(lufs.to_lkfs().value(), lufs.value());
+        assert_eq!(lufs.to_lkfs().unit(), LoudnessUnit::Lkfs);
+        assert_ne!(lufs.to_lkfs(), lufs);
+        assert_eq!(lufs.to_lkfs().to_lufs(), lufs);
+
+        assert_eq!(LoudnessValue::lufs(-3.01).to_dbfs_approximation(), -3.01);
+    }
+}
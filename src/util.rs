@@ -1,5 +1,8 @@
 use sampara::Frame;
 
+#[cfg(not(any(feature = "std", test)))]
+use crate::float::F64Ext;
+
 const DEN_THRESHOLD: f64 = 1.0e-15;
 
 pub struct Util;
@@ -10,9 +13,28 @@ impl Util {
         -0.691 + 10.0 * x.log10()
     }
 
+    /// Converts a linear amplitude into decibels relative to full scale.
+    #[inline]
+    pub fn dbfs(x: f64) -> f64 {
+        20.0 * x.log10()
+    }
+
     /// Given the mean squares (powers) of an input signal and a set of
     /// per-channel weights, calculates the weighted loudness across all input
-    /// channels. This is equation #4 in the ITU BS.1770 tech spec.
+    /// channels, in LUFS. This is equation #4 in the ITU BS.1770 tech spec:
+    ///
+    /// `L = -0.691 + 10 * log10(sum(w_i * G_i))`
+    ///
+    /// where `G_i` is the mean square of channel `i` after K-weighting, and
+    /// `w_i` is the weight of channel `i`, which depends on its position (see
+    /// `ChannelWeights`). The -0.691 offset cancels out the gain of the
+    /// K-weighting filter at 997 Hz, so that a full scale 997 Hz sine in a
+    /// single front channel reads as -3.01 LUFS.
+    ///
+    /// If the weighted sum of the powers is zero (e.g. for digital silence,
+    /// or if all of the non-silent channels have a weight of zero), this
+    /// returns negative infinity. Callers that gate or average loudness values
+    /// should treat this as being below any threshold.
     pub fn loudness<F, const N: usize>(mean_sq: F, weights: F) -> f64
     where
         F: Frame<N, Sample = f64>,
@@ -22,6 +44,70 @@ impl Util {
         Util::lufs(zipped.channels().sum())
     }
 
+    /// Calculates the loudness of a stereo signal from the RMS level of each
+    /// channel, in LUFS, for quickly checking the results of other
+    /// implementations. This is `loudness` with both weights at 1.0:
+    ///
+    /// `L = -0.691 + 10 * log10(rms_l^2 + rms_r^2)`
+    ///
+    /// The channel powers are summed, not averaged, so a signal in both
+    /// channels is 3.01 LU louder than the same signal in only one of them.
+    ///
+    /// This only holds if both RMS levels are measured after K-weighting, and
+    /// the signal has no gating blocks below the gates, such as for a steady
+    /// tone or noise. Otherwise, gating makes the integrated loudness differ.
+    /// It is also only valid for left and right channels, which both have a
+    /// weight of 1.0; it does not apply to layouts with surround channels,
+    /// such as 5.1, where the surround channels have a weight of 1.41.
+    #[inline]
+    pub fn loudness_from_rms(rms_l: f64, rms_r: f64) -> f64 {
+        Util::lufs(rms_l * rms_l + rms_r * rms_r)
+    }
+
+    /// The gain, in dB, that needs to be applied to a signal with a measured
+    /// loudness to bring it to a target loudness, such as -23 LUFS for EBU
+    /// R128. Applying a gain shifts the loudness of every gate block by the
+    /// same amount, and so the relative gate along with it, but not the
+    /// absolute gate at -70 LUFS. The result is therefore exact as long as no
+    /// gate blocks cross the absolute gate when the gain is applied.
+    #[inline]
+    pub fn normalization_gain_db(measured: f64, target: f64) -> f64 {
+        target - measured
+    }
+
+    /// Scales every channel of a frame by a gain, in dB.
+    pub fn apply_gain_db<F, const N: usize>(mut frame: F, gain_db: f64) -> F
+    where
+        F: Frame<N, Sample = f64>,
+    {
+        let gain = 10.0f64.powf(gain_db / 20.0);
+
+        for x in frame.channels_mut() {
+            *x *= gain;
+        }
+
+        frame
+    }
+
+    /// Calculates the per-channel mean square (power) of a block of frames.
+    pub fn mean_square<const N: usize>(block: &[[f64; N]]) -> [f64; N] {
+        let mut sums = [0.0; N];
+
+        for frame in block {
+            for (s, x) in sums.iter_mut().zip(frame.iter()) {
+                *s += x * x;
+            }
+        }
+
+        if !block.is_empty() {
+            for s in sums.iter_mut() {
+                *s /= block.len() as f64;
+            }
+        }
+
+        sums
+    }
+
     pub fn frame_peak<F, const N: usize>(frame: F) -> f64
     where
         F: Frame<N, Sample = f64>,
@@ -58,6 +144,8 @@ impl Util {
 mod tests {
     use super::*;
 
+    use proptest::prelude::*;
+
     #[test]
     fn ms_to_samples() {
         let inputs_and_expected = vec![
@@ -78,4 +166,62 @@ mod tests {
             assert_eq!(expected, produced)
         }
     }
+
+    #[test]
+    fn mean_square() {
+        let block = [[1.0, 0.5], [-1.0, 0.0], [0.0, -0.5], [0.0, 0.0]];
+
+        assert_eq!(Util::mean_square(&block), [0.5, 0.125]);
+        assert_eq!(Util::mean_square::<2>(&[]), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn loudness_of_silence() {
+        assert_eq!(Util::loudness([0.0, 0.0], [1.0, 1.0]), f64::NEG_INFINITY);
+        assert_eq!(Util::loudness([0.5, 0.5], [0.0, 0.0]), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn loudness_from_rms() {
+        let (rms_l, rms_r) = (0.5, 0.25);
+        assert_eq!(Util::loudness_from_rms(rms_l, rms_r), Util::loudness([rms_l * rms_l, rms_r * rms_r], [1.0, 1.0]));
+
+        // Doubling the power of a mono signal by copying it to both channels
+        // raises the loudness by 3.01 LU.
+        let mono = Util::loudness_from_rms(0.5, 0.0);
+        assert!((Util::loudness_from_rms(0.5, 0.5) - mono - 3.0103).abs() < 1e-4);
+
+        assert_eq!(Util::loudness_from_rms(0.0, 0.0), f64::NEG_INFINITY);
+    }
+
+    proptest! {
+        #[test]
+        fn loudness_matches_formula(
+            powers in [1.0e-12f64..1.0, 1.0e-12f64..1.0, 1.0e-12f64..1.0],
+            weights in [0.0f64..2.0, 0.0f64..2.0, 0.0f64..2.0],
+        ) {
+            let weighted_sum = powers.iter().zip(weights.iter()).map(|(g, w)| w * g).sum::<f64>();
+            prop_assume!(weighted_sum > 0.0);
+
+            let expected = -0.691 + 10.0 * weighted_sum.log10();
+            let produced = Util::loudness(powers, weights);
+
+            prop_assert!((produced - expected).abs() <= 1.0e-9);
+        }
+
+        #[test]
+        fn loudness_scales_with_power(
+            powers in [1.0e-12f64..1.0, 1.0e-12f64..1.0],
+            gain_db in -60.0f64..60.0,
+        ) {
+            // Scaling every channel's power by a gain raises the loudness by
+            // that gain.
+            let gain = 10.0f64.powf(gain_db / 10.0);
+            let scaled = [powers[0] * gain, powers[1] * gain];
+
+            let produced = Util::loudness(scaled, [1.0, 1.0]) - Util::loudness(powers, [1.0, 1.0]);
+
+            prop_assert!((produced - gain_db).abs() <= 1.0e-9);
+        }
+    }
 }